@@ -0,0 +1,113 @@
+//! Alert Data Object decoding
+//!
+//! A PD 3.0 partner sends an Alert message to report a change in its status, holding a single 32-bit data object:
+//!
+//! | Bits  | Field                    | Contents                                                                      |
+//! |-------|--------------------------|-------------------------------------------------------------------------------|
+//! | 31:24 | Type of Alert            | Bit 25 battery, 26 OCP, 27 OTP, 28 operating condition, 29 input, 30 OVP      |
+//! | 23:20 | Fixed Batteries          | Fixed batteries whose status changed                                          |
+//! | 19:16 | Hot Swappable Batteries  | Hot swappable batteries whose status changed                                  |
+//! | 15:4  | Reserved                 |                                                                               |
+//! | 3:0   | Extended Alert Type      | PD 3.1 only, not decoded                                                      |
+//!
+//! The controller reports that an Alert was received through the alert message received interrupt, surfaced as
+//! `PdInterruptEvent::AlertReceived` and by the embassy wrapper's `wait_for_partner_alert`, but doesn't provide a
+//! register holding the data object. Nothing in the driver can produce a [`PartnerAlert`], so this module is private
+//! until the data object is readable. Details of over-current, over-temperature, and over-voltage events are in the
+//! partner's Status Data Block, which the controller can't request either.
+
+/// Type of alert, battery status change
+const ALERT_BATTERY_STATUS: u32 = 1 << 25;
+/// Type of alert, over-current protection event
+const ALERT_OCP: u32 = 1 << 26;
+/// Type of alert, over-temperature protection event
+const ALERT_OTP: u32 = 1 << 27;
+/// Type of alert, operating condition change
+const ALERT_OPERATING_CONDITION: u32 = 1 << 28;
+/// Type of alert, source input change
+const ALERT_SOURCE_INPUT: u32 = 1 << 29;
+/// Type of alert, over-voltage protection event
+const ALERT_OVP: u32 = 1 << 30;
+
+/// Bit offset of the fixed batteries
+const FIXED_BATTERIES_SHIFT: u32 = 20;
+/// Bit offset of the hot swappable batteries
+const HOT_SWAPPABLE_BATTERIES_SHIFT: u32 = 16;
+/// Mask of a battery field
+const BATTERIES_MASK: u32 = 0xF;
+
+/// Decoded Alert Data Object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PartnerAlert {
+    /// Status of a battery changed, see `fixed_batteries` and `hot_swappable_batteries`
+    pub battery_status_change: bool,
+    /// Over-current protection event, only sent by a source
+    pub ocp: bool,
+    /// Over-temperature protection event
+    pub otp: bool,
+    /// Operating condition changed, e.g. the partner's temperature status
+    pub operating_condition_change: bool,
+    /// Source input changed, e.g. external power was removed
+    pub source_input_change: bool,
+    /// Over-voltage protection event, only sent by a sink
+    pub ovp: bool,
+    /// Fixed batteries whose status changed, bit N for battery N
+    pub fixed_batteries: u8,
+    /// Hot swappable batteries whose status changed, bit N for battery N
+    pub hot_swappable_batteries: u8,
+}
+
+impl PartnerAlert {
+    /// Decode an Alert Data Object
+    pub fn new(ado: u32) -> Self {
+        Self {
+            battery_status_change: ado & ALERT_BATTERY_STATUS != 0,
+            ocp: ado & ALERT_OCP != 0,
+            otp: ado & ALERT_OTP != 0,
+            operating_condition_change: ado & ALERT_OPERATING_CONDITION != 0,
+            source_input_change: ado & ALERT_SOURCE_INPUT != 0,
+            ovp: ado & ALERT_OVP != 0,
+            fixed_batteries: ((ado >> FIXED_BATTERIES_SHIFT) & BATTERIES_MASK) as u8,
+            hot_swappable_batteries: ((ado >> HOT_SWAPPABLE_BATTERIES_SHIFT) & BATTERIES_MASK) as u8,
+        }
+    }
+
+    /// Returns true if the alert reports a protection event, the partner may be about to cut power
+    pub fn is_fault(&self) -> bool {
+        self.ocp || self.otp || self.ovp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode_alert() {
+        // OTP with a status change on fixed battery 1
+        assert_eq!(
+            PartnerAlert::new(0x0A20_0000),
+            PartnerAlert {
+                battery_status_change: true,
+                ocp: false,
+                otp: true,
+                operating_condition_change: false,
+                source_input_change: false,
+                ovp: false,
+                fixed_batteries: 0x2,
+                hot_swappable_batteries: 0,
+            }
+        );
+        assert!(PartnerAlert::new(0x0A20_0000).is_fault());
+
+        // Source input change on hot swappable battery 0
+        let alert = PartnerAlert::new(0x2001_0000);
+        assert!(alert.source_input_change);
+        assert_eq!(alert.hot_swappable_batteries, 0x1);
+        assert!(!alert.is_fault());
+
+        // Reserved and extended alert bits are ignored
+        assert_eq!(PartnerAlert::new(0x8100_FFFF), PartnerAlert::new(0));
+    }
+}
@@ -151,7 +151,8 @@ impl<M: RawMutex, B: I2c> UpdateTarget for Tps6699x<'_, M, B> {
     async fn fw_update_burst_write(&mut self, address: u8, data: &[u8]) -> Result<(), Error<Self::BusError>> {
         let mut inner = self.controller.inner.lock().await;
 
-        inner.bus.write(address, data).await.map_err(Error::Bus)?;
+        let result = inner.bus.write(address, data).await;
+        result.map_err(|e| inner.map_bus_error(e))?;
         Ok(())
     }
 }
@@ -0,0 +1,232 @@
+//! Controller startup sequence
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embedded_hal_async::i2c::I2c;
+use embedded_usb_pd::{Error, PortId};
+
+use super::Tps6699x;
+use crate::registers::field_sets::IntEventBus1;
+use crate::registers::port_config::PowerRole;
+use crate::registers::I2cTimeout;
+use crate::{debug, error, TYPICAL_BOOT_TIME_MS};
+
+/// Startup configuration, see [`Tps6699x::initialize`]
+///
+/// Settings left as `None` keep the controller's current value, normally the one from the application configuration.
+#[derive(Debug, Clone, Copy)]
+pub struct InitConfig<'a> {
+    /// Time to wait for the controller to reach app mode
+    pub boot_timeout_ms: u32,
+    /// Time to wait for a command completion interrupt before polling the command register
+    pub command_interrupt_timeout_ms: Option<u32>,
+    /// Controller I2C timeout
+    pub i2c_timeout: Option<I2cTimeout>,
+    /// Interrupt mask for every port, must include command completion
+    pub interrupt_mask: Option<IntEventBus1>,
+    /// Default power role for every port
+    pub default_power_role: Option<PowerRole>,
+    /// Sink policy for every port as `(voltage_mv, max_current_ma)` entries, see [`Tps6699x::set_sink_policy`]
+    pub sink_policy: Option<&'a [(u16, u16)]>,
+}
+
+impl Default for InitConfig<'_> {
+    fn default() -> Self {
+        Self {
+            boot_timeout_ms: 2 * TYPICAL_BOOT_TIME_MS,
+            command_interrupt_timeout_ms: None,
+            i2c_timeout: None,
+            interrupt_mask: None,
+            default_power_role: None,
+            sink_policy: None,
+        }
+    }
+}
+
+/// Step of the startup sequence, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InitStep {
+    /// Waiting for app mode
+    WaitForAppMode,
+    /// Reading the firmware version
+    ReadFwVersion,
+    /// Configuring the driver's command timeouts
+    CommandTimeouts,
+    /// Setting the I2C timeout
+    I2cTimeout,
+    /// Setting the interrupt mask
+    InterruptMask(PortId),
+    /// Setting the default power role
+    DefaultPowerRole(PortId),
+    /// Setting the sink policy
+    SinkPolicy(PortId),
+}
+
+impl<M: RawMutex, B: I2c> Tps6699x<'_, M, B> {
+    /// Run the startup sequence
+    ///
+    /// The steps run in the order of [`InitStep`]: the controller must reach app mode before any configuration is
+    /// accepted, controller-level settings come before port settings, and the interrupt mask is set before the port
+    /// settings so the host sees any events they cause. On failure, returns the failing step along with its error,
+    /// the steps before it remain applied.
+    pub async fn initialize(&mut self, config: &InitConfig<'_>) -> Result<(), (InitStep, Error<B::Error>)> {
+        let step = |step: InitStep| {
+            move |e: Error<B::Error>| {
+                error!("Initialization failed at {:?}", step);
+                (step, e)
+            }
+        };
+
+        self.wait_for_app_mode(config.boot_timeout_ms)
+            .await
+            .map_err(step(InitStep::WaitForAppMode))?;
+
+        let version = self.get_fw_version().await.map_err(step(InitStep::ReadFwVersion))?;
+        debug!("Firmware version: {:#x}", version);
+
+        if let Some(timeout_ms) = config.command_interrupt_timeout_ms {
+            self.set_command_interrupt_timeout_ms(timeout_ms)
+                .map_err(step(InitStep::CommandTimeouts))?;
+        }
+
+        if let Some(timeout) = config.i2c_timeout {
+            self.set_i2c_timeout(timeout)
+                .await
+                .map_err(step(InitStep::I2cTimeout))?;
+        }
+
+        for port in 0..self.num_ports() {
+            let port = PortId(port as u8);
+            if let Some(mask) = config.interrupt_mask {
+                self.modify_interrupt_mask(port, |m| *m = mask)
+                    .await
+                    .map_err(step(InitStep::InterruptMask(port)))?;
+            }
+        }
+
+        for port in 0..self.num_ports() {
+            let port = PortId(port as u8);
+            if let Some(role) = config.default_power_role {
+                self.set_default_power_role(port, role)
+                    .await
+                    .map_err(step(InitStep::DefaultPowerRole(port)))?;
+            }
+        }
+
+        for port in 0..self.num_ports() {
+            let port = PortId(port as u8);
+            if let Some(policy) = config.sink_policy {
+                self.set_sink_policy(port, policy)
+                    .await
+                    .map_err(step(InitStep::SinkPolicy(port)))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use std::vec::Vec;
+
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+    use embedded_usb_pd::PdError;
+
+    use super::*;
+    use crate::asynchronous::embassy::controller::Controller;
+    use crate::registers::field_sets::{PortConfig, Status, SystemConfig};
+    use crate::registers::tx_sink_caps::REG_TX_SINK_CAPS_LEN;
+    use crate::registers::TypecStateMachine;
+    use crate::test::{create_register_read, create_register_write, PORT0_ADDR0, PORT1_ADDR0};
+    use crate::{Mode, PORT1};
+
+    const POLICY: [(u16, u16); 1] = [(5000, 3000)];
+
+    fn config(mask: IntEventBus1) -> InitConfig<'static> {
+        InitConfig {
+            command_interrupt_timeout_ms: Some(50),
+            i2c_timeout: Some(I2cTimeout::Timeout1000ms),
+            interrupt_mask: Some(mask),
+            default_power_role: Some(PowerRole::Sink),
+            sink_policy: Some(&POLICY),
+            ..Default::default()
+        }
+    }
+
+    /// Transactions of the steps before the default power role
+    fn controller_steps(mask: IntEventBus1) -> Vec<Transaction> {
+        let mut system_config = SystemConfig::new();
+        system_config.set_i_2_c_timeout(I2cTimeout::Timeout1000ms);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(PORT0_ADDR0, 0x03, Mode::App0));
+        transactions.push(create_register_read(PORT0_ADDR0, 0x0F, 0x1234u32.to_le_bytes()));
+        transactions.push(create_register_read(PORT0_ADDR0, 0x27, SystemConfig::new()));
+        transactions.push(create_register_write(PORT0_ADDR0, 0x27, system_config));
+        transactions.push(create_register_read(PORT0_ADDR0, 0x27, system_config));
+        for addr in [PORT0_ADDR0, PORT1_ADDR0] {
+            transactions.push(create_register_read(addr, 0x16, IntEventBus1::new_zero()));
+            transactions.push(create_register_write(addr, 0x16, mask));
+        }
+        transactions
+    }
+
+    #[tokio::test]
+    async fn test_initialize() {
+        let mut mask = IntEventBus1::new_zero();
+        mask.set_cmd_1_completed(true);
+        mask.set_plug_event(true);
+
+        let mut sink = PortConfig::new();
+        sink.set_typec_state_machine(TypecStateMachine::Sink);
+        let mut sink_caps = [0u8; REG_TX_SINK_CAPS_LEN];
+        sink_caps[0] = 1;
+        sink_caps[1..5].copy_from_slice(&0x0001_912Cu32.to_le_bytes());
+
+        let mut controller: Controller<NoopRawMutex, Mock> =
+            Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            let shared = tps.controller;
+
+            // Every step runs, in order
+            let mut transactions = controller_steps(mask);
+            for addr in [PORT0_ADDR0, PORT1_ADDR0] {
+                transactions.push(create_register_read(addr, 0x28, PortConfig::new()));
+                transactions.push(create_register_write(addr, 0x28, sink));
+                transactions.push(create_register_read(addr, 0x28, sink));
+            }
+            for addr in [PORT0_ADDR0, PORT1_ADDR0] {
+                transactions.push(create_register_read(addr, 0x1A, Status::new_zero()));
+                transactions.push(create_register_write(addr, 0x33, sink_caps));
+            }
+            shared.inner.lock().await.bus.update_expectations(&transactions);
+
+            tps.initialize(&config(mask)).await.unwrap();
+            assert_eq!(tps.command_interrupt_timeout_ms(), 50);
+            shared.inner.lock().await.bus.done();
+
+            // The second port rejects the power role, the later steps don't run
+            let mut drp = PortConfig::new();
+            drp.set_typec_state_machine(TypecStateMachine::Drp);
+            let mut transactions = controller_steps(mask);
+            transactions.push(create_register_read(PORT0_ADDR0, 0x28, PortConfig::new()));
+            transactions.push(create_register_write(PORT0_ADDR0, 0x28, sink));
+            transactions.push(create_register_read(PORT0_ADDR0, 0x28, sink));
+            transactions.push(create_register_read(PORT1_ADDR0, 0x28, drp));
+            transactions.push(create_register_write(PORT1_ADDR0, 0x28, sink));
+            transactions.push(create_register_read(PORT1_ADDR0, 0x28, drp));
+            shared.inner.lock().await.bus.update_expectations(&transactions);
+
+            assert!(matches!(
+                tps.initialize(&config(mask)).await,
+                Err((InitStep::DefaultPowerRole(PORT1), Error::Pd(PdError::InvalidParams)))
+            ));
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+}
@@ -1,40 +1,151 @@
 //! This module contains a high-level API uses embassy synchronization types
+use core::cell::Cell;
 use core::iter::zip;
-use core::sync::atomic::AtomicBool;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
 use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::blocking_mutex::Mutex as BlockingMutex;
 use embassy_sync::mutex::{Mutex, MutexGuard};
+use embassy_sync::pubsub::{PubSubChannel, Subscriber};
 use embassy_sync::signal::Signal;
-use embassy_time::{with_timeout, Delay, Duration};
+use embassy_time::{with_timeout, Delay, Duration, Instant, Timer};
 use embedded_hal::digital::InputPin;
 use embedded_hal_async::delay::DelayNs;
 use embedded_hal_async::i2c::I2c;
 use embedded_usb_pd::{Error, PdError, PortId};
 
-use super::interrupt::{self, InterruptController};
+use super::interrupt::{self, InterruptController, InterruptLine};
 use crate::asynchronous::internal;
+use crate::collections::{BuildDescription, DeviceInfo, SvidList};
 use crate::command::*;
-use crate::registers::field_sets::IntEventBus1;
-use crate::registers::{self};
-use crate::{error, Mode, MAX_SUPPORTED_PORTS};
+use crate::pdo::{PartnerInfo, PpsRange};
+use crate::registers::boot_flags::FirmwareRegions;
+use crate::registers::cc_status::{CcStatus, Termination};
+use crate::registers::chip_id::ChipId;
+use crate::registers::controller_config::ControllerConfig;
+use crate::registers::discovered_svids::DiscoveredSvids;
+use crate::registers::field_sets::{IntEventBus1, Status};
+use crate::registers::pd_events::{self, PdInterruptEvent, PdInterruptEvents, PD_EVENT_KINDS};
+use crate::registers::port_config::{PowerRole, RolePolicy, RolePreference};
+use crate::registers::port_control::{AutoVdmConfig, FrsStatus};
+use crate::registers::power_path::OvercurrentStatus;
+use crate::registers::reset_cause::ResetCause;
+use crate::registers::rx_caps::SourceCapabilities;
+use crate::registers::status_change::{self, PortSnapshot};
+use crate::registers::telemetry::PortTelemetry;
+use crate::registers::tx_identity::TxIdentity;
+use crate::registers::tx_sink_caps::SinkCapabilities;
+use crate::registers::usb_status::UsbStatus;
+use crate::registers::{self, Register};
+use crate::{debug, error, trace, Mode, MAX_SUPPORTED_PORTS};
 
 pub mod fw_update;
+pub mod init;
+pub mod recovery;
+#[cfg(feature = "interrupt-stats")]
+pub mod stats;
+#[cfg(feature = "task")]
 pub mod task;
 
+/// Default time to wait for a command completion interrupt before polling the command register
+pub const DEFAULT_COMMAND_INTERRUPT_TIMEOUT_MS: u32 = 25;
+
+/// Maximum number of concurrent interrupt subscribers
+pub const MAX_INTERRUPT_SUBSCRIBERS: usize = 4;
+/// Number of interrupt snapshots buffered for each subscriber before the oldest is dropped
+pub const INTERRUPT_SUBSCRIBER_DEPTH: usize = 4;
+
+/// Subscriber that observes every interrupt snapshot, see [`Tps6699x::subscribe_interrupts`]
+pub type InterruptSubscriber<'a, M> =
+    Subscriber<'a, M, [IntEventBus1; MAX_SUPPORTED_PORTS], INTERRUPT_SUBSCRIBER_DEPTH, MAX_INTERRUPT_SUBSCRIBERS, 0>;
+
 pub mod controller {
     use super::*;
     use crate::{TPS66993_NUM_PORTS, TPS66994_NUM_PORTS};
 
+    /// Latest pending event of each kind on a port, indexed by [`PdInterruptEvent::priority`]
+    pub(super) type PendingEvents = [Option<PdInterruptEvent>; PD_EVENT_KINDS];
+
     /// Controller struct. This struct is meant to be created and then immediately broken into its parts
+    ///
+    /// # Locking
+    ///
+    /// Both ports share a single lock around the low-level driver. Nearly all of the time spent under the lock is bus
+    /// traffic on the one I2C bus both ports sit behind, decoding is negligible in comparison, so a lock per port
+    /// would still serialize on the bus and only add a lock ordering to get wrong. Instead, operations hold the lock
+    /// for as little as possible:
+    ///
+    /// * Register accessors hold it for a single read or read-modify-write.
+    /// * Commands hold it to send the command and to read the result, but not while waiting for completion, so other
+    ///   operations on either port can run while a command is in progress.
+    /// * Waits for a booting controller, [`Tps6699x::wait_for_app_mode`] and [`Tps6699x::read_register_retry`], hold
+    ///   it for each read but not while waiting between reads.
+    /// * [`Tps6699x::execute_batch`] holds it for the whole batch, that's its purpose.
+    /// * Resets hold it until the controller has restarted, so no other operation reaches the controller mid-reset.
+    ///   This covers [`Tps6699x::restore_defaults`], automatic recovery, and entering and leaving firmware update mode,
+    ///   each of which blocks every other operation for the reset delay, over a second for a full reset.
+    /// * Configuration and software state, such as timeouts and counters, never take the driver lock.
+    ///
+    /// Outside of batches and resets, a quick operation on one port therefore waits for at most the bus transactions of
+    /// a single step on the other.
     pub struct Controller<M: RawMutex, B: I2c> {
-        /// Low-level TPS6699x driver
+        /// Low-level TPS6699x driver, shared by both ports
         pub(super) inner: Mutex<M, internal::Tps6699x<B>>,
         /// Signal for awaiting an interrupt
         pub(super) interrupt_waker: Signal<M, [IntEventBus1; MAX_SUPPORTED_PORTS]>,
+        /// Signal for awaiting a command completion interrupt on each port
+        pub(super) command_wakers: [Signal<M, ()>; MAX_SUPPORTED_PORTS],
+        /// Channel delivering every interrupt snapshot to each subscriber
+        pub(super) interrupt_channel: PubSubChannel<
+            M,
+            [IntEventBus1; MAX_SUPPORTED_PORTS],
+            INTERRUPT_SUBSCRIBER_DEPTH,
+            MAX_INTERRUPT_SUBSCRIBERS,
+            0,
+        >,
         /// Current interrupt state
         pub(super) interrupts_enabled: [AtomicBool; MAX_SUPPORTED_PORTS],
+        /// Number of commands awaiting completion on each port, see [`super::CommandPin`]
+        pub(super) commands_in_progress: [AtomicU32; MAX_SUPPORTED_PORTS],
         /// Number of active ports
         pub(super) num_ports: usize,
+        /// Time to wait for a command completion interrupt before polling the command register
+        pub(super) command_interrupt_timeout_ms: AtomicU32,
+        /// Minimum command timeout set by a [`CommandTimeoutGuard`], zero if there is none
+        pub(super) min_command_timeout_ms: AtomicU32,
+        /// Interval to poll the event register for command completion, zero to wait on the interrupt line instead
+        pub(super) command_poll_interval_ms: AtomicU32,
+        /// Drain pending interrupts before issuing a command
+        pub(super) drain_before_command: AtomicBool,
+        /// Mask interrupts in hardware for ports with interrupts disabled in software
+        pub(super) mask_disabled_ports: AtomicBool,
+        /// Interrupt masks saved while a disabled port is masked in hardware
+        pub(super) saved_masks: BlockingMutex<M, Cell<[Option<IntEventBus1>; MAX_SUPPORTED_PORTS]>>,
+        /// A fast role swap completed on each port since [`Tps6699x::get_frs_status`] was last called
+        pub(super) frs_triggered: [AtomicBool; MAX_SUPPORTED_PORTS],
+        /// Number of attaches seen on each port while the driver has been running
+        pub(super) attach_counts: [AtomicU32; MAX_SUPPORTED_PORTS],
+        /// PD events not yet returned by [`Tps6699x::next_event`], the latest of each kind by [`PdInterruptEvent::priority`]
+        pub(super) pending_events: BlockingMutex<M, Cell<[PendingEvents; MAX_SUPPORTED_PORTS]>>,
+        /// Time of the most recent PD-related interrupt on each port
+        pub(super) last_activity: BlockingMutex<M, Cell<[Option<Instant>; MAX_SUPPORTED_PORTS]>>,
+        /// Time of the most recent interrupt of any kind on each port
+        pub(super) last_interrupt: BlockingMutex<M, Cell<[Option<Instant>; MAX_SUPPORTED_PORTS]>>,
+        /// Port state observed by the most recent [`Tps6699x::poll_status_changes`]
+        pub(super) status_snapshots: BlockingMutex<M, Cell<[Option<PortSnapshot>; MAX_SUPPORTED_PORTS]>>,
+        /// Port state observed by the most recent status updated interrupt
+        pub(super) interrupt_snapshots: BlockingMutex<M, Cell<[Option<PortSnapshot>; MAX_SUPPORTED_PORTS]>>,
+        /// Automatic recovery policy, see [`Tps6699x::set_recovery_policy`]
+        pub(super) recovery_policy: BlockingMutex<M, Cell<Option<recovery::RecoveryPolicy>>>,
+        /// Number of consecutive command timeouts
+        pub(super) consecutive_timeouts: AtomicU32,
+        /// Number of recoveries attempted
+        pub(super) recoveries: AtomicU32,
+        /// A recovery is in progress
+        pub(super) recovering: AtomicBool,
+        /// Interrupt processing statistics
+        #[cfg(feature = "interrupt-stats")]
+        pub(super) interrupt_stats: super::stats::InterruptStatsRecorder,
     }
 
     impl<M: RawMutex, B: I2c> Controller<M, B> {
@@ -43,8 +154,30 @@ pub mod controller {
             Ok(Self {
                 inner: Mutex::new(internal::Tps6699x::new(bus, addr, num_ports)),
                 interrupt_waker: Signal::new(),
+                command_wakers: [const { Signal::new() }; MAX_SUPPORTED_PORTS],
+                interrupt_channel: PubSubChannel::new(),
                 interrupts_enabled: [const { AtomicBool::new(true) }; MAX_SUPPORTED_PORTS],
+                commands_in_progress: [const { AtomicU32::new(0) }; MAX_SUPPORTED_PORTS],
                 num_ports,
+                command_interrupt_timeout_ms: AtomicU32::new(DEFAULT_COMMAND_INTERRUPT_TIMEOUT_MS),
+                min_command_timeout_ms: AtomicU32::new(0),
+                command_poll_interval_ms: AtomicU32::new(0),
+                drain_before_command: AtomicBool::new(false),
+                mask_disabled_ports: AtomicBool::new(false),
+                saved_masks: BlockingMutex::new(Cell::new([None; MAX_SUPPORTED_PORTS])),
+                frs_triggered: [const { AtomicBool::new(false) }; MAX_SUPPORTED_PORTS],
+                attach_counts: [const { AtomicU32::new(0) }; MAX_SUPPORTED_PORTS],
+                pending_events: BlockingMutex::new(Cell::new([[None; PD_EVENT_KINDS]; MAX_SUPPORTED_PORTS])),
+                last_activity: BlockingMutex::new(Cell::new([None; MAX_SUPPORTED_PORTS])),
+                last_interrupt: BlockingMutex::new(Cell::new([None; MAX_SUPPORTED_PORTS])),
+                status_snapshots: BlockingMutex::new(Cell::new([None; MAX_SUPPORTED_PORTS])),
+                interrupt_snapshots: BlockingMutex::new(Cell::new([None; MAX_SUPPORTED_PORTS])),
+                recovery_policy: BlockingMutex::new(Cell::new(None)),
+                consecutive_timeouts: AtomicU32::new(0),
+                recoveries: AtomicU32::new(0),
+                recovering: AtomicBool::new(false),
+                #[cfg(feature = "interrupt-stats")]
+                interrupt_stats: super::stats::InterruptStatsRecorder::new(),
             })
         }
 
@@ -58,6 +191,12 @@ pub mod controller {
             Self::new(bus, addr, TPS66994_NUM_PORTS)
         }
 
+        /// Set a hook to translate bus errors, see [`internal::Tps6699x::set_bus_error_hook`]
+        pub fn with_bus_error_hook(mut self, hook: internal::BusErrorHook<B::Error>) -> Self {
+            self.inner.get_mut().set_bus_error_hook(hook);
+            self
+        }
+
         /// Breaks the controller into its parts
         pub fn make_parts(&mut self) -> (Tps6699x<'_, M, B>, Interrupt<'_, M, B>) {
             let tps = Tps6699x { controller: self };
@@ -68,7 +207,7 @@ pub mod controller {
         /// Enable or disable interrupts for the given ports
         pub(super) fn enable_interrupts(&self, enabled: [bool; MAX_SUPPORTED_PORTS]) {
             for (enabled, s) in zip(enabled.iter(), self.interrupts_enabled.iter()) {
-                s.store(*enabled, core::sync::atomic::Ordering::SeqCst);
+                s.store(*enabled, Ordering::SeqCst);
             }
         }
 
@@ -76,11 +215,149 @@ pub mod controller {
         pub(super) fn interrupts_enabled(&self) -> [bool; MAX_SUPPORTED_PORTS] {
             let mut interrupts_enabled = [false; MAX_SUPPORTED_PORTS];
             for (copy, enabled) in zip(interrupts_enabled.iter_mut(), self.interrupts_enabled.iter()) {
-                *copy = enabled.load(core::sync::atomic::Ordering::SeqCst);
+                *copy = enabled.load(Ordering::SeqCst);
             }
 
             interrupts_enabled
         }
+
+        /// Wake interrupt waiters and subscribers with the given flags
+        pub(super) fn notify_interrupt(&self, flags: [IntEventBus1; MAX_SUPPORTED_PORTS]) {
+            for (waker, flags) in zip(self.command_wakers.iter(), flags.iter()) {
+                if flags.cmd_1_completed() {
+                    waker.signal(());
+                }
+            }
+
+            self.interrupt_waker.signal(flags);
+            self.interrupt_channel.immediate_publisher().publish_immediate(flags);
+        }
+
+        /// Update the state derived from the interrupt flags processed on a port and buffer the events they report
+        ///
+        /// `status` is the port status read alongside the flags and `snapshot` the port state read for a status updated
+        /// interrupt, if any.
+        pub(super) fn record_interrupt(
+            &self,
+            port: usize,
+            flags: &IntEventBus1,
+            status: &Status,
+            snapshot: Option<PortSnapshot>,
+        ) {
+            if flags.plug_event() && status.plug_present() {
+                self.attach_counts[port].fetch_add(1, Ordering::SeqCst);
+            }
+
+            if flags.fast_role_swap_completed() {
+                self.frs_triggered[port].store(true, Ordering::SeqCst);
+            }
+
+            self.buffer_events(port, &pd_events::decode(flags, status));
+            if let Some(snapshot) = snapshot {
+                let previous = super::replace_snapshot(&self.interrupt_snapshots, port, snapshot);
+                self.buffer_events(port, &status_change::diff(&previous, &snapshot));
+            }
+
+            let now = Instant::now();
+            if *flags != IntEventBus1::new_zero() {
+                self.last_interrupt.lock(|last| {
+                    let mut instants = last.get();
+                    instants[port] = Some(now);
+                    last.set(instants);
+                });
+            }
+
+            if super::is_pd_activity(flags) {
+                self.last_activity.lock(|last| {
+                    let mut instants = last.get();
+                    instants[port] = Some(now);
+                    last.set(instants);
+                });
+            }
+        }
+
+        /// Deliver synthetic interrupt flags as if they'd been processed from hardware
+        ///
+        /// Interrupt waiters, subscribers and [`Tps6699x::next_event`] see the flags as they would from
+        /// [`Interrupt::process_interrupt`]. This bypasses the hardware entirely, nothing is read from or cleared on the
+        /// controller. Events are decoded against an unattached port status since none is read, so a new contract is
+        /// reported with the sink role, plug events aren't counted as attaches, and no status change events are
+        /// produced. The controller is borrowed by its parts, use [`Tps6699x::inject_interrupt`] while they exist.
+        #[cfg(feature = "testing")]
+        pub fn inject_interrupt(&self, flags: [IntEventBus1; MAX_SUPPORTED_PORTS]) {
+            for (port, flags) in flags.iter().enumerate().take(self.num_ports) {
+                self.record_interrupt(port, flags, &Status::new_zero(), None);
+            }
+
+            self.notify_interrupt(flags);
+        }
+
+        /// Returns the ports serviced by interrupt processing, see [`super::serviced_ports`]
+        pub(super) fn interrupts_serviced(&self) -> [bool; MAX_SUPPORTED_PORTS] {
+            super::serviced_ports(self.interrupts_enabled(), &self.commands_in_progress)
+        }
+
+        /// Buffer events for [`Tps6699x::next_event`], replacing any pending event of the same kind
+        pub(super) fn buffer_events(&self, port: usize, events: &[PdInterruptEvent]) {
+            self.pending_events.lock(|pending| {
+                let mut buffered = pending.get();
+                for event in events {
+                    buffered[port][event.priority()] = Some(*event);
+                }
+                pending.set(buffered);
+            });
+        }
+
+        /// Drop every event buffered for a port
+        pub(super) fn clear_events(&self, port: usize) {
+            self.pending_events.lock(|pending| {
+                let mut buffered = pending.get();
+                buffered[port] = [None; PD_EVENT_KINDS];
+                pending.set(buffered);
+            });
+        }
+
+        /// Take the highest priority event buffered for a port
+        pub(super) fn take_event(&self, port: usize) -> Option<PdInterruptEvent> {
+            self.pending_events.lock(|pending| {
+                let mut buffered = pending.get();
+                let event = buffered[port].iter_mut().find_map(Option::take);
+                pending.set(buffered);
+                event
+            })
+        }
+
+        /// Record the interrupt mask saved while a disabled port is masked in hardware
+        pub(super) fn set_saved_mask(&self, port: usize, mask: Option<IntEventBus1>) {
+            self.saved_masks.lock(|masks| {
+                let mut saved = masks.get();
+                saved[port] = mask;
+                masks.set(saved);
+            });
+        }
+
+        /// Returns true if a port masked in hardware while disabled has since been enabled again
+        pub(super) fn masks_to_restore(&self) -> bool {
+            let saved = self.saved_masks.lock(|masks| masks.get());
+            zip(saved.iter(), self.interrupts_enabled().iter()).any(|(mask, enabled)| mask.is_some() && *enabled)
+        }
+
+        /// Restore the saved interrupt mask of every port that has been enabled again
+        pub(super) async fn restore_interrupt_masks(
+            &self,
+            inner: &mut internal::Tps6699x<B>,
+        ) -> Result<(), Error<B::Error>> {
+            let enabled = self.interrupts_enabled();
+            for port in 0..inner.num_ports() {
+                let saved_mask = self.saved_masks.lock(|masks| masks.get()[port]);
+                if let (true, Some(mask)) = (enabled[port], saved_mask) {
+                    inner.set_interrupt_mask(PortId(port as u8), mask).await?;
+                    self.set_saved_mask(port, None);
+                }
+            }
+
+            Ok(())
+        }
     }
 }
 
@@ -91,8 +368,23 @@ pub struct Tps6699x<'a, M: RawMutex, B: I2c> {
 
 impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
     /// Locks the inner device
+    ///
+    /// Restores any interrupt masks left saved by a dropped [`InterruptGuard`], which can't access the bus itself.
     async fn lock_inner(&mut self) -> MutexGuard<'_, M, internal::Tps6699x<B>> {
-        self.controller.inner.lock().await
+        let mut inner = self.controller.inner.lock().await;
+        if self.controller.masks_to_restore() {
+            if self.controller.restore_interrupt_masks(&mut inner).await.is_err() {
+                // Retried on the next lock
+                debug!("Failed to restore interrupt masks");
+            }
+        }
+
+        inner
+    }
+
+    /// Locks the inner device if it isn't already locked
+    fn try_lock_inner(&mut self) -> Option<MutexGuard<'_, M, internal::Tps6699x<B>>> {
+        self.controller.inner.try_lock().ok()
     }
 
     /// Wrapper for `get_port_status``
@@ -100,6 +392,61 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         self.lock_inner().await.get_port_status(port).await
     }
 
+    /// Read the port status unless the driver lock is held, returns `None` without reading if it is
+    ///
+    /// The lock is held by every operation while it's on the bus, see [`controller::Controller`] for how long, so a
+    /// read can be skipped whenever another task is mid-transaction, not only during slow commands. Reads are skipped
+    /// transiently under contention and callers that need the status on every iteration should use
+    /// [`Self::get_port_status`].
+    pub async fn try_get_port_status(&mut self, port: PortId) -> Result<Option<Status>, Error<B::Error>> {
+        match self.try_lock_inner() {
+            Some(mut inner) => inner.get_port_status(port).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Read the port status and active contract and return the events since the previous call, see
+    /// [`status_change::diff`]
+    ///
+    /// Interrupt processing already diffs the status on every status updated interrupt and buffers the events for
+    /// [`Self::next_event`]. This keeps its own snapshot, so both can be used without taking events from each other.
+    /// The first call compares against an unattached port.
+    pub async fn poll_status_changes(&mut self, port: PortId) -> Result<PdInterruptEvents, Error<B::Error>> {
+        let snapshot = self.get_port_snapshot(port).await?;
+        let previous = replace_snapshot(&self.controller.status_snapshots, port.0 as usize, snapshot);
+        Ok(status_change::diff(&previous, &snapshot))
+    }
+
+    /// Wrapper for `get_port_snapshot`
+    pub async fn get_port_snapshot(&mut self, port: PortId) -> Result<PortSnapshot, Error<B::Error>> {
+        self.lock_inner().await.get_port_snapshot(port).await
+    }
+
+    /// Wrapper for `is_dead_battery`
+    pub async fn is_dead_battery(&mut self, port: PortId) -> Result<bool, Error<B::Error>> {
+        self.lock_inner().await.is_dead_battery(port).await
+    }
+
+    /// Wrapper for `is_epr_keepalive_ok`
+    pub async fn is_epr_keepalive_ok(&mut self, port: PortId) -> Result<Option<bool>, Error<B::Error>> {
+        self.lock_inner().await.is_epr_keepalive_ok(port).await
+    }
+
+    /// Wrapper for `is_vbus_present`
+    pub async fn is_vbus_present(&mut self, port: PortId) -> Result<bool, Error<B::Error>> {
+        self.lock_inner().await.is_vbus_present(port).await
+    }
+
+    /// Wrapper for `get_cc_status`
+    pub async fn get_cc_status(&mut self, port: PortId) -> Result<CcStatus, Error<B::Error>> {
+        self.lock_inner().await.get_cc_status(port).await
+    }
+
+    /// Wrapper for `get_termination`
+    pub async fn get_termination(&mut self, port: PortId) -> Result<Termination, Error<B::Error>> {
+        self.lock_inner().await.get_termination(port).await
+    }
+
     /// Wrapper for `get_active_pdo_contract`
     pub async fn get_active_pdo_contract(
         &mut self,
@@ -116,21 +463,174 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         self.lock_inner().await.get_active_rdo_contract(port).await
     }
 
+    /// Wrapper for `get_pps_range`
+    pub async fn get_pps_range(&mut self, port: PortId) -> Result<PpsRange, Error<B::Error>> {
+        self.lock_inner().await.get_pps_range(port).await
+    }
+
+    /// Wrapper for `has_capability_mismatch`
+    pub async fn has_capability_mismatch(&mut self, port: PortId) -> Result<bool, Error<B::Error>> {
+        self.lock_inner().await.has_capability_mismatch(port).await
+    }
+
+    /// Wrapper for `get_telemetry`
+    ///
+    /// All registers are read under a single lock, so no other driver access is interleaved with the snapshot. The
+    /// controller can still update the registers between reads, e.g. on detach.
+    pub async fn get_telemetry(&mut self, port: PortId) -> Result<PortTelemetry, Error<B::Error>> {
+        self.lock_inner().await.get_telemetry(port).await
+    }
+
+    /// Wrapper for `get_contract_current_ma`
+    pub async fn get_contract_current_ma(&mut self, port: PortId) -> Result<u16, Error<B::Error>> {
+        self.lock_inner().await.get_contract_current_ma(port).await
+    }
+
+    /// Wrapper for `get_available_headroom_ma`
+    pub async fn get_available_headroom_ma(&mut self, port: PortId) -> Result<u16, Error<B::Error>> {
+        self.lock_inner().await.get_available_headroom_ma(port).await
+    }
+
+    /// Wrapper for `get_partner_info`
+    pub async fn get_partner_info(&mut self, port: PortId) -> Result<Option<PartnerInfo>, Error<B::Error>> {
+        self.lock_inner().await.get_partner_info(port).await
+    }
+
+    /// Wrapper for `get_active_pdo_raw`
+    pub async fn get_active_pdo_raw(&mut self, port: PortId) -> Result<u32, Error<B::Error>> {
+        self.lock_inner().await.get_active_pdo_raw(port).await
+    }
+
+    /// Wrapper for `get_active_rdo_raw`
+    pub async fn get_active_rdo_raw(&mut self, port: PortId) -> Result<u32, Error<B::Error>> {
+        self.lock_inner().await.get_active_rdo_raw(port).await
+    }
+
+    /// Wrapper for `get_selected_pdo_index`
+    pub async fn get_selected_pdo_index(&mut self, port: PortId) -> Result<u8, Error<B::Error>> {
+        self.lock_inner().await.get_selected_pdo_index(port).await
+    }
+
+    /// Wrapper for `is_command_busy`
+    pub async fn is_command_busy(&mut self, port: PortId) -> Result<bool, Error<B::Error>> {
+        self.lock_inner().await.is_command_busy(port).await
+    }
+
     /// Wrapper for `get_mode`
     pub async fn get_mode(&mut self) -> Result<Mode, Error<B::Error>> {
         self.lock_inner().await.get_mode().await
     }
 
+    /// Wait for the controller to reach app mode, see `wait_for_app_mode` on the low-level driver
+    ///
+    /// The driver lock is only held for each mode read, other operations can run while waiting, though they'll likely
+    /// fail until the controller has booted.
+    pub async fn wait_for_app_mode(&mut self, timeout_ms: u32) -> Result<(), Error<B::Error>> {
+        let mut backoff = internal::Backoff::app_mode(timeout_ms);
+        while !self.lock_inner().await.is_app_mode().await {
+            let Some(wait_ms) = backoff.next_delay_ms() else {
+                return PdError::Timeout.into();
+            };
+
+            Timer::after_millis(wait_ms.into()).await;
+        }
+
+        Ok(())
+    }
+
+    /// Read a register, retrying through the window where the controller is rebooting
+    ///
+    /// See `read_register_retry` on the low-level driver for the retry behavior. The driver lock is only held for each
+    /// read, other operations can run between attempts.
+    pub async fn read_register_retry(
+        &mut self,
+        port: PortId,
+        reg: Register,
+        buf: &mut [u8],
+        max_attempts: u32,
+        delay_ms: u32,
+    ) -> Result<(), Error<B::Error>> {
+        if max_attempts == 0 || buf.is_empty() {
+            return PdError::InvalidParams.into();
+        }
+
+        let mut backoff = internal::Backoff::retry(delay_ms);
+        let mut attempt = 1;
+        loop {
+            match self.lock_inner().await.read_register_raw(port, reg, buf).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= max_attempts => return Err(e),
+                Err(_) => trace!("Register read failed, attempt {}", attempt),
+            }
+
+            // Can't fail, retries have no limit on the total delay
+            Timer::after_millis(backoff.next_delay_ms().unwrap_or(delay_ms).into()).await;
+            attempt += 1;
+        }
+    }
+
     /// Wrapper for `get_fw_version`
     pub async fn get_fw_version(&mut self) -> Result<u32, Error<B::Error>> {
         self.lock_inner().await.get_fw_version().await
     }
 
+    /// Wrapper for `get_boot_flags`
+    pub async fn get_boot_flags(&mut self) -> Result<registers::field_sets::BootFlags, Error<B::Error>> {
+        self.lock_inner().await.get_boot_flags().await
+    }
+
+    /// Wrapper for `get_firmware_regions`
+    pub async fn get_firmware_regions(&mut self) -> Result<FirmwareRegions, Error<B::Error>> {
+        self.lock_inner().await.get_firmware_regions().await
+    }
+
     /// Wrapper for `get_customer_use`
     pub async fn get_customer_use(&mut self) -> Result<u64, Error<B::Error>> {
         self.lock_inner().await.get_customer_use().await
     }
 
+    /// Wrapper for `get_uid`
+    pub async fn get_uid(&mut self) -> Result<[u8; registers::REG_UID_LEN], Error<B::Error>> {
+        self.lock_inner().await.get_uid().await
+    }
+
+    /// Wrapper for `read_mailbox`
+    pub async fn read_mailbox(&mut self, port: PortId, offset: usize, buf: &mut [u8]) -> Result<(), Error<B::Error>> {
+        self.lock_inner().await.read_mailbox(port, offset, buf).await
+    }
+
+    /// Wrapper for `write_mailbox`
+    pub async fn write_mailbox(&mut self, port: PortId, offset: usize, data: &[u8]) -> Result<(), Error<B::Error>> {
+        self.lock_inner().await.write_mailbox(port, offset, data).await
+    }
+
+    /// Wrapper for `get_build_description_raw`
+    pub async fn get_build_description_raw(
+        &mut self,
+    ) -> Result<[u8; registers::REG_BUILD_DESCRIPTION_LEN], Error<B::Error>> {
+        self.lock_inner().await.get_build_description_raw().await
+    }
+
+    /// Wrapper for `get_build_description`
+    pub async fn get_build_description(&mut self) -> Result<BuildDescription, Error<B::Error>> {
+        self.lock_inner().await.get_build_description().await
+    }
+
+    /// Wrapper for `get_device_info_raw`
+    pub async fn get_device_info_raw(&mut self) -> Result<[u8; registers::REG_DEVICE_INFO_LEN], Error<B::Error>> {
+        self.lock_inner().await.get_device_info_raw().await
+    }
+
+    /// Wrapper for `get_device_info`
+    pub async fn get_device_info(&mut self) -> Result<DeviceInfo, Error<B::Error>> {
+        self.lock_inner().await.get_device_info().await
+    }
+
+    /// Wrapper for `get_chip_id`
+    pub async fn get_chip_id(&mut self) -> Result<ChipId, Error<B::Error>> {
+        self.lock_inner().await.get_chip_id().await
+    }
+
     /// Wrapper for `get_power_path_status`
     pub async fn get_power_path_status(
         &mut self,
@@ -139,11 +639,170 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         self.lock_inner().await.get_power_path_status(port).await
     }
 
+    /// Wrapper for `get_overcurrent_status`
+    pub async fn get_overcurrent_status(&mut self, port: PortId) -> Result<OvercurrentStatus, Error<B::Error>> {
+        self.lock_inner().await.get_overcurrent_status(port).await
+    }
+
+    /// Wrapper for `interrupt_report`
+    ///
+    /// Pending interrupts are only read, so this doesn't disturb interrupt processing.
+    pub async fn interrupt_report(&mut self) -> Result<[PdInterruptEvents; MAX_SUPPORTED_PORTS], Error<B::Error>> {
+        self.lock_inner().await.interrupt_report().await
+    }
+
+    /// Wrapper for `get_interrupt_mask`
+    pub async fn get_interrupt_mask(&mut self, port: PortId) -> Result<IntEventBus1, Error<B::Error>> {
+        self.lock_inner().await.get_interrupt_mask(port).await
+    }
+
+    /// Wrapper for `modify_interrupt_mask`
+    pub async fn modify_interrupt_mask(
+        &mut self,
+        port: PortId,
+        f: impl FnOnce(&mut IntEventBus1),
+    ) -> Result<IntEventBus1, Error<B::Error>> {
+        self.lock_inner().await.modify_interrupt_mask(port, f).await
+    }
+
+    /// Wrapper for `get_last_reset_cause`
+    pub async fn get_last_reset_cause(&mut self, port: PortId) -> Result<ResetCause, Error<B::Error>> {
+        self.lock_inner().await.get_last_reset_cause(port).await
+    }
+
     /// Wrapper for `get_pd_status`
     pub async fn get_pd_status(&mut self, port: PortId) -> Result<registers::field_sets::PdStatus, Error<B::Error>> {
         self.lock_inner().await.get_pd_status(port).await
     }
 
+    /// Wrapper for `get_data_status`
+    pub async fn get_data_status(
+        &mut self,
+        port: PortId,
+    ) -> Result<registers::field_sets::DataStatus, Error<B::Error>> {
+        self.lock_inner().await.get_data_status(port).await
+    }
+
+    /// Wrapper for `get_usb_status`
+    pub async fn get_usb_status(&mut self, port: PortId) -> Result<UsbStatus, Error<B::Error>> {
+        self.lock_inner().await.get_usb_status(port).await
+    }
+
+    /// Wait for the given port to detach, returns immediately if it's already detached
+    pub async fn wait_for_detach(&mut self, port: PortId) -> Result<(), Error<B::Error>> {
+        // Pending interrupts are kept so a detach between the status read and the wait isn't missed
+        while self.get_port_status(port).await?.plug_present() {
+            self.wait_interrupt(false, |p, flags| p == port && flags.plug_event())
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// Wait for the USB host status of the given port to change, returns the new USB status
+    pub async fn wait_usb_host_change(&mut self, port: PortId) -> Result<UsbStatus, Error<B::Error>> {
+        self.wait_interrupt(false, |p, flags| {
+            p == port && (flags.usb_host_present() || flags.usb_host_not_present())
+        })
+        .await;
+        self.get_usb_status(port).await
+    }
+
+    /// Get fast role swap status
+    ///
+    /// The trigger is latched by interrupt processing when a fast role swap completes and cleared by this call, so
+    /// each swap is reported once. Swaps on a port with interrupts disabled aren't latched until they're processed.
+    pub async fn get_frs_status(&mut self, port: PortId) -> Result<FrsStatus, Error<B::Error>> {
+        let (control, status) = {
+            let mut inner = self.lock_inner().await;
+            (inner.get_port_control(port).await?, inner.get_port_status(port).await?)
+        };
+
+        let triggered = self.controller.frs_triggered[port.0 as usize].swap(false, Ordering::SeqCst);
+        Ok(FrsStatus::new(&control, &status, triggered))
+    }
+
+    /// Wrapper for `get_discovered_svids_full`
+    pub async fn get_discovered_svids_full(&mut self, port: PortId) -> Result<DiscoveredSvids, Error<B::Error>> {
+        self.lock_inner().await.get_discovered_svids_full(port).await
+    }
+
+    /// Wrapper for `get_discovered_svids`
+    pub async fn get_discovered_svids(&mut self, port: PortId) -> Result<SvidList, Error<B::Error>> {
+        self.lock_inner().await.get_discovered_svids(port).await
+    }
+
+    /// Wrapper for `get_tx_identity`
+    pub async fn get_tx_identity(&mut self, port: PortId) -> Result<TxIdentity, Error<B::Error>> {
+        self.lock_inner().await.get_tx_identity(port).await
+    }
+
+    /// Wrapper for `set_tx_identity`
+    pub async fn set_tx_identity(&mut self, port: PortId, identity: &TxIdentity) -> Result<(), Error<B::Error>> {
+        self.lock_inner().await.set_tx_identity(port, identity).await
+    }
+
+    /// Wrapper for `get_tx_sink_caps`
+    pub async fn get_tx_sink_caps(&mut self, port: PortId) -> Result<SinkCapabilities, Error<B::Error>> {
+        self.lock_inner().await.get_tx_sink_caps(port).await
+    }
+
+    /// Wrapper for `set_tx_sink_caps`
+    pub async fn set_tx_sink_caps(&mut self, port: PortId, caps: &SinkCapabilities) -> Result<(), Error<B::Error>> {
+        self.lock_inner().await.set_tx_sink_caps(port, caps).await
+    }
+
+    /// Wrapper for `set_sink_policy`
+    pub async fn set_sink_policy(&mut self, port: PortId, policy: &[(u16, u16)]) -> Result<(), Error<B::Error>> {
+        self.lock_inner().await.set_sink_policy(port, policy).await
+    }
+
+    /// Wrapper for `get_port_config`
+    pub async fn get_port_config(
+        &mut self,
+        port: PortId,
+    ) -> Result<registers::field_sets::PortConfig, Error<B::Error>> {
+        self.lock_inner().await.get_port_config(port).await
+    }
+
+    /// Wrapper for `set_port_config`
+    pub async fn set_port_config(
+        &mut self,
+        port: PortId,
+        config: registers::field_sets::PortConfig,
+    ) -> Result<(), Error<B::Error>> {
+        self.lock_inner().await.set_port_config(port, config).await
+    }
+
+    /// Wrapper for `get_role_preference`
+    pub async fn get_role_preference(&mut self, port: PortId) -> Result<RolePreference, Error<B::Error>> {
+        self.lock_inner().await.get_role_preference(port).await
+    }
+
+    /// Wrapper for `set_role_preference`
+    pub async fn set_role_preference(
+        &mut self,
+        port: PortId,
+        preference: RolePreference,
+    ) -> Result<(), Error<B::Error>> {
+        self.lock_inner().await.set_role_preference(port, preference).await
+    }
+
+    /// Wrapper for `get_default_power_role`
+    pub async fn get_default_power_role(&mut self, port: PortId) -> Result<PowerRole, Error<B::Error>> {
+        self.lock_inner().await.get_default_power_role(port).await
+    }
+
+    /// Wrapper for `set_default_power_role`
+    pub async fn set_default_power_role(&mut self, port: PortId, role: PowerRole) -> Result<(), Error<B::Error>> {
+        self.lock_inner().await.set_default_power_role(port, role).await
+    }
+
+    /// Wrapper for `set_port_role_policy`
+    pub async fn set_port_role_policy(&mut self, port: PortId, policy: RolePolicy) -> Result<(), Error<B::Error>> {
+        self.lock_inner().await.set_port_role_policy(port, policy).await
+    }
+
     /// Wrapper for `get_port_control`
     pub async fn get_port_control(
         &mut self,
@@ -161,6 +820,31 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         self.lock_inner().await.set_port_control(port, control).await
     }
 
+    /// Wrapper for `get_auto_vdm_config`
+    pub async fn get_auto_vdm_config(&mut self, port: PortId) -> Result<AutoVdmConfig, Error<B::Error>> {
+        self.lock_inner().await.get_auto_vdm_config(port).await
+    }
+
+    /// Wrapper for `set_auto_vdm_config`
+    pub async fn set_auto_vdm_config(&mut self, port: PortId, config: AutoVdmConfig) -> Result<(), Error<B::Error>> {
+        self.lock_inner().await.set_auto_vdm_config(port, config).await
+    }
+
+    /// Wrapper for `get_vconn_current_limit_ma`
+    pub async fn get_vconn_current_limit_ma(&mut self, port: PortId) -> Result<u16, Error<B::Error>> {
+        self.lock_inner().await.get_vconn_current_limit_ma(port).await
+    }
+
+    /// Wrapper for `set_vconn_current_limit_ma`
+    pub async fn set_vconn_current_limit_ma(&mut self, port: PortId, ma: u16) -> Result<(), Error<B::Error>> {
+        self.lock_inner().await.set_vconn_current_limit_ma(port, ma).await
+    }
+
+    /// Wrapper for `set_usb_data_enable`
+    pub async fn set_usb_data_enable(&mut self, port: PortId, enable: bool) -> Result<(), Error<B::Error>> {
+        self.lock_inner().await.set_usb_data_enable(port, enable).await
+    }
+
     /// Wrapper for `get_system_config`
     pub async fn get_system_config(&mut self) -> Result<registers::field_sets::SystemConfig, Error<B::Error>> {
         self.lock_inner().await.get_system_config().await
@@ -179,62 +863,428 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         self.lock_inner().await.enable_source(port, enable).await
     }
 
+    /// Wrapper for `set_source_inhibit`
+    pub async fn set_source_inhibit(&mut self, port: PortId, inhibit: bool) -> Result<(), Error<B::Error>> {
+        self.lock_inner().await.set_source_inhibit(port, inhibit).await
+    }
+
+    /// Wrapper for `get_i2c_timeout`
+    pub async fn get_i2c_timeout(&mut self) -> Result<registers::I2cTimeout, Error<B::Error>> {
+        self.lock_inner().await.get_i2c_timeout().await
+    }
+
+    /// Wrapper for `set_i2c_timeout`
+    pub async fn set_i2c_timeout(&mut self, timeout: registers::I2cTimeout) -> Result<(), Error<B::Error>> {
+        self.lock_inner().await.set_i2c_timeout(timeout).await
+    }
+
+    /// Wrapper for `export_config`
+    pub async fn export_config(&mut self) -> Result<ControllerConfig, Error<B::Error>> {
+        self.lock_inner().await.export_config().await
+    }
+
+    /// Wrapper for `apply_config`
+    pub async fn apply_config(&mut self, config: &ControllerConfig) -> Result<(), Error<B::Error>> {
+        self.lock_inner().await.apply_config(config).await
+    }
+
+    /// Wrapper for `set_bus_speed_hz`
+    pub async fn set_bus_speed_hz(&mut self, bus_speed_hz: u32) -> Result<(), Error<B::Error>> {
+        self.lock_inner().await.set_bus_speed_hz(bus_speed_hz)
+    }
+
     /// Returns the number of ports
     pub fn num_ports(&self) -> usize {
         self.controller.num_ports
     }
 
-    /// Wait for an interrupt to occur that satisfies the given predicate
-    pub async fn wait_interrupt(
-        &mut self,
-        clear_current: bool,
-        f: impl Fn(PortId, IntEventBus1) -> bool,
-    ) -> [IntEventBus1; MAX_SUPPORTED_PORTS] {
-        if clear_current {
-            self.controller.interrupt_waker.reset();
+    /// Returns the number of attaches seen on the given port
+    ///
+    /// This is a software counter, it only counts attaches processed by `process_interrupt` while the driver is running.
+    pub fn get_attach_count(&self, port: PortId) -> Result<u32, Error<B::Error>> {
+        if port.0 as usize >= self.controller.num_ports {
+            return PdError::InvalidPort.into();
         }
 
-        loop {
-            let flags = self.controller.interrupt_waker.wait().await;
-            for (port, flag) in flags.iter().enumerate() {
-                if f(PortId(port as u8), *flag) {
-                    return flags;
-                }
-            }
+        Ok(self.controller.attach_counts[port.0 as usize].load(Ordering::SeqCst))
+    }
+
+    /// Returns the time of the most recent PD-related interrupt on the given port, `None` if there hasn't been one
+    ///
+    /// The controller doesn't expose PD message counters, so this is tracked in software whenever `process_interrupt`
+    /// decodes a PD-related event. It doesn't touch the bus, but it only reflects interrupts processed while the driver
+    /// has been running and with the relevant events unmasked.
+    pub fn last_activity_instant(&self, port: PortId) -> Result<Option<Instant>, Error<B::Error>> {
+        if port.0 as usize >= self.controller.num_ports {
+            return PdError::InvalidPort.into();
         }
+
+        Ok(self.controller.last_activity.lock(|last| last.get()[port.0 as usize]))
     }
 
-    /// Execute the given command with no timeout
-    async fn execute_command_no_timeout(
-        &mut self,
-        port: PortId,
-        cmd: Command,
-        indata: Option<&[u8]>,
-        outdata: Option<&mut [u8]>,
-    ) -> Result<ReturnValue, Error<B::Error>> {
-        {
-            let mut inner = self.lock_inner().await;
-            let mut delay = Delay;
-            inner.send_command(&mut delay, port, cmd, indata).await?;
+    /// Returns the time since the given port last raised an interrupt, `None` if it hasn't raised one
+    ///
+    /// Tracked in software, so it only reflects interrupts observed by `process_interrupt` while the driver has been
+    /// running. Masked events, ports with interrupts disabled, and interrupts cleared by other hosts aren't seen. It
+    /// doesn't touch the bus.
+    pub fn last_interrupt_age(&self, port: PortId) -> Result<Option<Duration>, Error<B::Error>> {
+        if port.0 as usize >= self.controller.num_ports {
+            return PdError::InvalidPort.into();
         }
 
-        self.wait_interrupt(false, |p, flags| p == port && flags.cmd_1_completed())
-            .await;
-        {
-            let mut inner = self.lock_inner().await;
-            inner.read_command_result(port, outdata).await
+        let last = self.controller.last_interrupt.lock(|last| last.get()[port.0 as usize]);
+        Ok(last.map(|instant| instant.elapsed()))
+    }
+
+    /// Returns a snapshot of interrupt processing statistics
+    #[cfg(feature = "interrupt-stats")]
+    pub fn interrupt_stats(&self) -> stats::InterruptStats {
+        self.controller.interrupt_stats.snapshot()
+    }
+
+    /// Raise the timeout of every command to at least `timeout_ms` until the returned guard is dropped
+    ///
+    /// Commands with a longer timeout of their own keep it. Use this to scope a long timeout to a block of slow
+    /// operations, such as a firmware update, without affecting commands issued afterwards.
+    pub fn with_command_timeout(&mut self, timeout_ms: u32) -> CommandTimeoutGuard<'a> {
+        CommandTimeoutGuard::new(&self.controller.min_command_timeout_ms, timeout_ms)
+    }
+
+    /// Returns the interval commands poll the event register for completion, `None` if they wait on the interrupt line
+    pub fn command_poll_interval_ms(&self) -> Option<u32> {
+        match self.controller.command_poll_interval_ms.load(Ordering::SeqCst) {
+            0 => None,
+            interval_ms => Some(interval_ms),
         }
     }
 
-    /// Execute the given command with a timeout
-    async fn execute_command(
-        &mut self,
+    /// Poll the event register for command completion instead of waiting on the interrupt line, `None` restores
+    /// interrupt-driven completion
+    ///
+    /// This is for boards without the interrupt line wired to a readable GPIO, `process_interrupt` doesn't need to run
+    /// for commands to complete. Completion is detected up to one interval late and every poll is a bus transaction, so
+    /// shorter intervals trade bus traffic for latency. Only the command completion event is cleared, other events are
+    /// left pending.
+    pub fn set_command_poll_interval_ms(&mut self, interval_ms: Option<u32>) -> Result<(), Error<B::Error>> {
+        if interval_ms == Some(0) {
+            return PdError::InvalidParams.into();
+        }
+
+        self.controller
+            .command_poll_interval_ms
+            .store(interval_ms.unwrap_or(0), Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Returns the time to wait for a command completion interrupt before polling the command register
+    pub fn command_interrupt_timeout_ms(&self) -> u32 {
+        self.controller.command_interrupt_timeout_ms.load(Ordering::SeqCst)
+    }
+
+    /// Set the time to wait for a command completion interrupt before polling the command register
+    ///
+    /// Lower values detect a missed interrupt sooner at the cost of more bus traffic during long commands
+    pub fn set_command_interrupt_timeout_ms(&mut self, timeout_ms: u32) -> Result<(), Error<B::Error>> {
+        if timeout_ms == 0 {
+            return PdError::InvalidParams.into();
+        }
+
+        self.controller
+            .command_interrupt_timeout_ms
+            .store(timeout_ms, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Returns true if pending interrupts are drained before issuing a command
+    pub fn drain_before_command(&self) -> bool {
+        self.controller.drain_before_command.load(Ordering::SeqCst)
+    }
+
+    /// Set whether pending interrupts are drained before issuing a command
+    ///
+    /// Stale completion snapshots are always dropped once a command is accepted, draining also clears a stale
+    /// completion from the event register, which matters when polling for completion. Any other events drained this
+    /// way are not delivered to interrupt waiters.
+    pub fn set_drain_before_command(&mut self, drain: bool) {
+        self.controller.drain_before_command.store(drain, Ordering::SeqCst);
+    }
+
+    /// Returns true if ports with interrupts disabled are masked in hardware
+    pub fn mask_disabled_ports(&self) -> bool {
+        self.controller.mask_disabled_ports.load(Ordering::SeqCst)
+    }
+
+    /// Set whether ports with interrupts disabled are masked in hardware
+    ///
+    /// By default `process_interrupt` only skips disabled ports, so a disabled port with pending events keeps the
+    /// interrupt line asserted and interrupt processing spins without servicing it. With masking enabled,
+    /// `process_interrupt` saves the port's interrupt mask and masks all of its events. The saved mask is restored when
+    /// interrupts are enabled again through [`InterruptController::enable_interrupts_guarded`]. Dropping a guard can't
+    /// access the bus, so a port re-enabled that way gets its mask back on the next operation that locks the driver.
+    /// Events still latch while masked and assert the line when the mask is restored.
+    /// Commands issued to a masked port complete by polling the command register.
+    pub fn set_mask_disabled_ports(&mut self, mask: bool) {
+        self.controller.mask_disabled_ports.store(mask, Ordering::SeqCst);
+    }
+
+    /// Subscribe to interrupts, each subscriber observes every interrupt snapshot
+    ///
+    /// `wait_interrupt` and friends share a single signal, so concurrent waiters steal snapshots from each other.
+    /// Subscribers don't, which allows independent tasks to watch for different events. Each subscriber buffers up to
+    /// [`INTERRUPT_SUBSCRIBER_DEPTH`] snapshots, if it falls behind the oldest are dropped and reported as lagged. At
+    /// most [`MAX_INTERRUPT_SUBSCRIBERS`] can exist at once, returns `Busy` if that limit is reached. Dropping a
+    /// subscriber frees its slot.
+    pub fn subscribe_interrupts(&self) -> Result<InterruptSubscriber<'a, M>, Error<B::Error>> {
+        let controller = self.controller;
+        controller
+            .interrupt_channel
+            .subscriber()
+            .map_err(|_| Error::Pd(PdError::Busy))
+    }
+
+    /// Wrapper for [`controller::Controller::inject_interrupt`]
+    #[cfg(feature = "testing")]
+    pub fn inject_interrupt(&self, flags: [IntEventBus1; MAX_SUPPORTED_PORTS]) {
+        self.controller.inject_interrupt(flags);
+    }
+
+    /// Read and clear any pending interrupts on the given port, returns the interrupts that were pending
+    ///
+    /// The interrupt waker is also reset so previously signaled interrupts aren't observed by `wait_interrupt`.
+    pub async fn drain_interrupts(&mut self, port: PortId) -> Result<IntEventBus1, Error<B::Error>> {
+        let flags = self.lock_inner().await.clear_interrupt(port).await?;
+        self.controller.interrupt_waker.reset();
+        self.controller.command_wakers[port.0 as usize].reset();
+        Ok(flags)
+    }
+
+    /// Wait for an interrupt to occur that satisfies the given predicate
+    pub async fn wait_interrupt(
+        &mut self,
+        clear_current: bool,
+        f: impl Fn(PortId, IntEventBus1) -> bool,
+    ) -> [IntEventBus1; MAX_SUPPORTED_PORTS] {
+        if clear_current {
+            self.controller.interrupt_waker.reset();
+        }
+
+        loop {
+            let flags = self.controller.interrupt_waker.wait().await;
+            for (port, flag) in flags.iter().enumerate() {
+                if f(PortId(port as u8), *flag) {
+                    return flags;
+                }
+            }
+        }
+    }
+
+    /// Wait for an interrupt that satisfies any of the given labeled predicates, returns the label of the match
+    ///
+    /// Predicates are checked in order so earlier entries take priority when several match. Predicates of different
+    /// types can be combined by passing them as `&dyn Fn(PortId, IntEventBus1) -> bool`.
+    pub async fn wait_any<L: Copy, F: Fn(PortId, IntEventBus1) -> bool>(
+        &mut self,
+        clear_current: bool,
+        specs: &[(L, F)],
+    ) -> (L, [IntEventBus1; MAX_SUPPORTED_PORTS]) {
+        if clear_current {
+            self.controller.interrupt_waker.reset();
+        }
+
+        loop {
+            let flags = self.controller.interrupt_waker.wait().await;
+            for (label, f) in specs {
+                for (port, flag) in flags.iter().enumerate() {
+                    if f(PortId(port as u8), *flag) {
+                        return (*label, flags);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Wait for the next PD event on the given port
+    ///
+    /// Events are decoded from every interrupt processed since the driver started and buffered until returned, so none
+    /// are missed between calls. This includes the status changes found on each status updated interrupt, see
+    /// [`status_change::diff`]. When several are pending the highest priority one is returned first, in the order of
+    /// [`PdInterruptEvent`]. Each kind of event is buffered once, an event that repeats before it's returned is only
+    /// returned once, with the data of its latest occurrence. Returns `InvalidPort` for an inactive port. An interrupt
+    /// subscriber is used to wait for events, so this returns `Busy` if [`MAX_INTERRUPT_SUBSCRIBERS`] already exist.
+    pub async fn next_event(&mut self, port: PortId) -> Result<PdInterruptEvent, Error<B::Error>> {
+        if port.0 as usize >= self.num_ports() {
+            return PdError::InvalidPort.into();
+        }
+
+        // Subscribe before checking so an event buffered after the check still wakes this task
+        let mut subscriber = self.subscribe_interrupts()?;
+        loop {
+            if let Some(event) = self.controller.take_event(port.0 as usize) {
+                return Ok(event);
+            }
+
+            subscriber.next_message_pure().await;
+        }
+    }
+
+    /// Drop the PD events buffered for [`Self::next_event`] on the given port
+    ///
+    /// Events are buffered from driver start, so the first call to [`Self::next_event`] can return an event from long
+    /// before. Call this first to only see events from now on. Returns `InvalidPort` for an inactive port.
+    pub fn clear_pending_events(&mut self, port: PortId) -> Result<(), Error<B::Error>> {
+        if port.0 as usize >= self.num_ports() {
+            return PdError::InvalidPort.into();
+        }
+
+        self.controller.clear_events(port.0 as usize);
+        Ok(())
+    }
+
+    /// Execute the given command with no timeout, returns the length of the data the controller reported
+    async fn execute_command_sized_no_timeout(
+        &mut self,
+        port: PortId,
+        cmd: Command,
+        indata: Option<&[u8]>,
+        outdata: &mut [u8],
+    ) -> Result<(ReturnValue, usize), Error<B::Error>> {
+        if self.drain_before_command() {
+            let flags = self.drain_interrupts(port).await?;
+            if flags != IntEventBus1::new_zero() {
+                debug!("Drained interrupts before command {:#?}: {:#?}", cmd, flags);
+            }
+        }
+
+        // Pin the port while the lock is held so interrupt processing can't run between sending and pinning
+        let controller = self.controller;
+        let _pin = {
+            let mut inner = self.lock_inner().await;
+            // Don't overwrite a command another task or host is waiting on
+            if inner.is_command_busy(port).await? {
+                return PdError::Busy.into();
+            }
+
+            let mut delay = Delay;
+            inner.send_command(&mut delay, port, cmd, indata).await?;
+
+            // Confirm the command was accepted before waiting on its completion, a fast command may already be done
+            if !inner.is_command_busy(port).await? {
+                return inner.read_command_result_sized(port, outdata).await;
+            }
+
+            // Interrupt processing needs the lock, so any completion signaled from here on belongs to this command and
+            // a stale completion from a previous command on this port can be dropped. Other ports and interrupt waiters
+            // are unaffected.
+            controller.command_wakers[port.0 as usize].reset();
+            CommandPin::new(&controller.commands_in_progress[port.0 as usize])
+        };
+
+        if let Some(interval_ms) = self.command_poll_interval_ms() {
+            loop {
+                let mut inner = self.lock_inner().await;
+                if inner.poll_command_complete(port).await? {
+                    // The completion event can be left over from a command that completed before it was waited on
+                    match inner.read_command_result_sized(port, outdata).await {
+                        Err(Error::Pd(PdError::Busy)) => debug!("Stale command completion on port {}", port.0),
+                        result => return result,
+                    }
+                }
+
+                drop(inner);
+                Timer::after_millis(interval_ms.into()).await;
+            }
+        }
+
+        // Wait for the completion interrupt, but fall back to polling the command register in case it was missed
+        loop {
+            let timeout = Duration::from_millis(self.command_interrupt_timeout_ms().into());
+            let signaled = with_timeout(timeout, controller.command_wakers[port.0 as usize].wait())
+                .await
+                .is_ok();
+
+            // A command that completed before it was waited on leaves its completion event set in hardware, so the
+            // signal can come from processing that event rather than this command. Only the command register tells.
+            match self.lock_inner().await.read_command_result_sized(port, outdata).await {
+                Err(Error::Pd(PdError::Busy)) => {
+                    if signaled {
+                        debug!("Stale command completion on port {}", port.0);
+                    }
+                }
+                result => {
+                    if !signaled {
+                        debug!("Command {:#?} completed without interrupt", cmd);
+                    }
+
+                    return result;
+                }
+            }
+        }
+    }
+
+    /// Execute the given command with no timeout
+    async fn execute_command_no_timeout(
+        &mut self,
+        port: PortId,
+        cmd: Command,
+        indata: Option<&[u8]>,
+        outdata: Option<&mut [u8]>,
+    ) -> Result<ReturnValue, Error<B::Error>> {
+        let outdata = outdata.unwrap_or(&mut []);
+        if outdata.len() > registers::REG_DATA1_LEN - 1 {
+            // Data length too long
+            return PdError::InvalidParams.into();
+        }
+
+        self.execute_command_sized_no_timeout(port, cmd, indata, outdata)
+            .await
+            .map(|(ret, _)| ret)
+    }
+
+    /// Execute the given command with a timeout, returns the length of the data the controller reported
+    ///
+    /// Unlike the fixed-size command wrappers, `outdata` can be any length up to
+    /// [`internal::MAX_COMMAND_DATA_LEN`], for firmware that reports more or less data than expected. It's filled with
+    /// as much of the reported data as fits, a reported length larger than `outdata` means the data was truncated. See
+    /// [`internal::Tps6699x::read_command_result_sized`] for how the length is obtained.
+    pub async fn execute_command_sized(
+        &mut self,
+        port: PortId,
+        cmd: Command,
+        timeout_ms: u32,
+        indata: Option<&[u8]>,
+        outdata: &mut [u8],
+    ) -> Result<(ReturnValue, usize), Error<B::Error>> {
+        let started = self.command_started()?;
+        let timeout_ms = timeout_ms.max(self.controller.min_command_timeout_ms.load(Ordering::SeqCst));
+        let result = with_timeout(
+            Duration::from_millis(timeout_ms.into()),
+            self.execute_command_sized_no_timeout(port, cmd, indata, outdata),
+        )
+        .await;
+        if result.is_err() {
+            error!("Command {:#?} timed out", cmd);
+            self.command_finished(started, true).await;
+            return PdError::Timeout.into();
+        }
+
+        let result = result.unwrap();
+        if result.is_ok() {
+            self.command_finished(started, false).await;
+        }
+        result
+    }
+
+    /// Execute the given command with a timeout
+    async fn execute_command(
+        &mut self,
         port: PortId,
         cmd: Command,
         timeout_ms: u32,
         indata: Option<&[u8]>,
         outdata: Option<&mut [u8]>,
     ) -> Result<ReturnValue, Error<B::Error>> {
+        let started = self.command_started()?;
+        let timeout_ms = timeout_ms.max(self.controller.min_command_timeout_ms.load(Ordering::SeqCst));
         let result = with_timeout(
             Duration::from_millis(timeout_ms.into()),
             self.execute_command_no_timeout(port, cmd, indata, outdata),
@@ -242,10 +1292,15 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         .await;
         if result.is_err() {
             error!("Command {:#?} timed out", cmd);
+            self.command_finished(started, true).await;
             return PdError::Timeout.into();
         }
 
-        result.unwrap()
+        let result = result.unwrap();
+        if result.is_ok() {
+            self.command_finished(started, false).await;
+        }
+        result
     }
 
     async fn execute_srdy(&mut self, port: PortId, switch: SrdySwitch) -> Result<ReturnValue, Error<B::Error>> {
@@ -278,6 +1333,245 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         Ok(())
     }
 
+    /// Enter a PD compliance BIST mode, this is a test feature for certification and not for normal operation
+    ///
+    /// The port must have an explicit contract, the partner, normally a compliance tester, then observes the selected
+    /// mode. Carrier mode ends on its own after tBISTContMode and test data mode ends when the tester issues a hard
+    /// reset. Shared test mode lasts until [`Self::exit_bist_mode`].
+    pub async fn set_bist_mode(&mut self, port: PortId, mode: BistMode) -> Result<(), Error<B::Error>> {
+        let arg_bytes = [mode.into()];
+        let ret = self
+            .execute_command(port, Command::Bist, BIST_TIMEOUT_MS, Some(&arg_bytes), None)
+            .await?;
+        let ret: Result<(), PdError> = ret.into();
+        ret.map_err(Error::Pd)
+    }
+
+    /// Exit the BIST shared test mode
+    pub async fn exit_bist_mode(&mut self, port: PortId) -> Result<(), Error<B::Error>> {
+        let arg_bytes = [BIST_EXIT_SHARED_TEST];
+        let ret = self
+            .execute_command(port, Command::Bist, BIST_TIMEOUT_MS, Some(&arg_bytes), None)
+            .await?;
+        let ret: Result<(), PdError> = ret.into();
+        ret.map_err(Error::Pd)
+    }
+
+    /// Wrapper for `get_rx_source_caps`
+    pub async fn get_rx_source_caps(&mut self, port: PortId) -> Result<SourceCapabilities, Error<B::Error>> {
+        self.lock_inner().await.get_rx_source_caps(port).await
+    }
+
+    /// Wrapper for `get_source_pdo_count`
+    pub async fn get_source_pdo_count(&mut self, port: PortId) -> Result<u8, Error<B::Error>> {
+        self.lock_inner().await.get_source_pdo_count(port).await
+    }
+
+    /// Wrapper for `get_rx_sink_caps`
+    pub async fn get_rx_sink_caps(&mut self, port: PortId) -> Result<SinkCapabilities, Error<B::Error>> {
+        self.lock_inner().await.get_rx_sink_caps(port).await
+    }
+
+    /// Send the given get caps command and wait for the partner's response
+    async fn request_partner_caps(
+        &mut self,
+        port: PortId,
+        cmd: Command,
+        received: fn(&IntEventBus1) -> bool,
+    ) -> Result<(), Error<B::Error>> {
+        // Subscribe before sending so a fast response can't be missed
+        let mut subscriber = self.subscribe_interrupts()?;
+        let ret = self.execute_command(port, cmd, GET_CAPS_TIMEOUT_MS, None, None).await?;
+        let ret: Result<(), PdError> = ret.into();
+        ret.map_err(Error::Pd)?;
+
+        let response = async {
+            loop {
+                let flags = subscriber.next_message_pure().await[port.0 as usize];
+                if received(&flags) {
+                    break true;
+                }
+
+                if flags.no_caps_response() {
+                    break false;
+                }
+            }
+        };
+
+        match with_timeout(Duration::from_millis(PARTNER_CAPS_TIMEOUT_MS.into()), response).await {
+            Ok(true) => Ok(()),
+            Ok(false) => PdError::Failed.into(),
+            Err(_) => PdError::Timeout.into(),
+        }
+    }
+
+    /// Request fresh source capabilities from the port partner, unlike [`Self::get_rx_source_caps`] which returns the
+    /// most recently received capabilities
+    ///
+    /// Returns `Failed` if the controller reports the partner didn't respond with capabilities, and `Timeout` if
+    /// neither the capabilities nor that report arrive in time. An interrupt subscriber is used to observe the
+    /// response, so this returns `Busy` if [`MAX_INTERRUPT_SUBSCRIBERS`] already exist.
+    pub async fn request_partner_source_caps(&mut self, port: PortId) -> Result<SourceCapabilities, Error<B::Error>> {
+        self.request_partner_caps(port, Command::Gsrc, IntEventBus1::source_caps_received)
+            .await?;
+        self.get_rx_source_caps(port).await
+    }
+
+    /// Request fresh sink capabilities from the port partner, see [`Self::request_partner_source_caps`]
+    pub async fn request_partner_sink_caps(&mut self, port: PortId) -> Result<SinkCapabilities, Error<B::Error>> {
+        self.request_partner_caps(port, Command::Gskc, IntEventBus1::sink_caps_received)
+            .await?;
+        self.get_rx_sink_caps(port).await
+    }
+
+    /// Wait for the port partner to send an Alert message
+    ///
+    /// Only Alerts received after the call are observed, returns `Timeout` if none arrives within `timeout_ms`. The
+    /// controller only reports that an Alert was received, not its Alert Data Object, so the type of alert isn't
+    /// known. Any alert can mean a partner source is about to cut power. An interrupt subscriber is used to observe the Alert,
+    /// so this returns `Busy` if [`MAX_INTERRUPT_SUBSCRIBERS`] already exist.
+    pub async fn wait_for_partner_alert(&mut self, port: PortId, timeout_ms: u32) -> Result<(), Error<B::Error>> {
+        if port.0 as usize >= self.num_ports() {
+            return PdError::InvalidPort.into();
+        }
+
+        let mut subscriber = self.subscribe_interrupts()?;
+        let alert = async { while !subscriber.next_message_pure().await[port.0 as usize].alert_message_received() {} };
+
+        with_timeout(Duration::from_millis(timeout_ms.into()), alert)
+            .await
+            .map_err(|_| Error::Pd(PdError::Timeout))
+    }
+
+    /// Renegotiate to the best contract the source can fully provide, e.g. after a capability mismatch
+    ///
+    /// The sink capabilities are limited to the PDOs the most recently received source capabilities can satisfy, see
+    /// [`SinkCapabilities::satisfiable_by`] for the selection logic. Fresh source capabilities are then requested,
+    /// which makes the controller send a new request against the limited capabilities. The limited capabilities stay
+    /// in place until they're set again. Returns `Failed` if there's nothing to drop, and `Failed`, `Timeout` or `Busy`
+    /// as [`Self::request_partner_source_caps`].
+    pub async fn renegotiate_lower(&mut self, port: PortId) -> Result<(), Error<B::Error>> {
+        {
+            let mut inner = self.lock_inner().await;
+            let source = inner.get_rx_source_caps(port).await?;
+            let current = inner.get_tx_sink_caps(port).await?;
+            let limited = current.satisfiable_by(&source).map_err(Error::Pd)?;
+            if limited == current {
+                return PdError::Failed.into();
+            }
+
+            inner.set_tx_sink_caps(port, &limited).await?;
+        }
+
+        self.request_partner_source_caps(port).await?;
+        Ok(())
+    }
+
+    /// Negotiate with each sink policy in turn until the source accepts the request, returns the index of the accepted
+    /// policy
+    ///
+    /// Neither the TPS66993 nor the TPS66994 has a setting to retry a rejected request with another PDO or an event
+    /// for a rejected request, so the fallback is done here rather than by the controller. Each policy is programmed
+    /// with [`Self::set_sink_policy`], then fresh source capabilities are requested so the controller sends a new
+    /// request. The policy is accepted once a new contract is established. If the partner doesn't respond, rejects the
+    /// request, or hard resets, so no contract follows within a second, the next policy is tried. This only runs when
+    /// called, a request rejected later, e.g. after the source's capabilities change, isn't retried. The last policy
+    /// tried stays programmed.
+    ///
+    /// Returns `InvalidParams` if `policies` is empty and `Failed` if no policy was accepted. An interrupt subscriber
+    /// is used to observe the contract, so this returns `Busy` if [`MAX_INTERRUPT_SUBSCRIBERS`] already exist.
+    pub async fn negotiate_with_fallback(
+        &mut self,
+        port: PortId,
+        policies: &[&[(u16, u16)]],
+    ) -> Result<usize, Error<B::Error>> {
+        if policies.is_empty() {
+            return PdError::InvalidParams.into();
+        }
+
+        for (i, policy) in policies.iter().enumerate() {
+            self.set_sink_policy(port, policy).await?;
+
+            // Subscribe before sending so a fast contract can't be missed
+            let mut subscriber = self.subscribe_interrupts()?;
+            let ret = self
+                .execute_command(port, Command::Gsrc, GET_CAPS_TIMEOUT_MS, None, None)
+                .await?;
+            let ret: Result<(), PdError> = ret.into();
+            ret.map_err(Error::Pd)?;
+
+            let contract = async {
+                loop {
+                    let flags = subscriber.next_message_pure().await[port.0 as usize];
+                    if flags.new_consumer_contract() {
+                        break true;
+                    }
+
+                    if flags.no_caps_response() || flags.hard_reset() {
+                        break false;
+                    }
+                }
+            };
+
+            if let Ok(true) = with_timeout(Duration::from_millis(CONTRACT_TIMEOUT_MS.into()), contract).await {
+                return Ok(i);
+            }
+
+            debug!("Sink policy {} not accepted on port {}", i, port.0);
+        }
+
+        PdError::Failed.into()
+    }
+
+    /// Execute a batch of commands under a single lock, stopping at the first failure
+    ///
+    /// Commands are polled for completion rather than waiting on the completion interrupt since interrupt processing
+    /// requires the lock. On failure, returns the index of the failing command along with its error.
+    pub async fn execute_batch<const N: usize>(
+        &mut self,
+        batch: &CommandBatch<'_, N>,
+    ) -> Result<(), (usize, Error<B::Error>)> {
+        let mut delay = Delay;
+        self.lock_inner().await.execute_batch(&mut delay, batch).await
+    }
+
+    /// Issue a PD soft reset on the given port, resynchronizing message counters without dropping the contract
+    ///
+    /// Returns `Timeout` if the port partner did not respond to the soft reset.
+    pub async fn soft_reset(&mut self, port: PortId) -> Result<(), Error<B::Error>> {
+        match self
+            .execute_command(port, Command::Srst, SRST_TIMEOUT_MS, None, None)
+            .await?
+        {
+            ReturnValue::Success => Ok(()),
+            ReturnValue::Abort => {
+                error!("Soft reset on port {} not acknowledged by partner", port.0);
+                PdError::Timeout.into()
+            }
+            _ => PdError::Failed.into(),
+        }
+    }
+
+    /// Wrapper for `restore_defaults`, interrupts are disabled while the controller restarts
+    pub async fn restore_defaults(&mut self) -> Result<(), Error<B::Error>> {
+        let _guard = self.disable_all_interrupts_guarded().await;
+        let mut delay = Delay;
+        self.lock_inner().await.restore_defaults(&mut delay).await
+    }
+
+    /// Clear the dead battery flag with the DBfg command
+    ///
+    /// After a dead battery boot the controller sinks with a default contract until the flag is cleared. Once the
+    /// system has enough power to operate normally, clear the flag so the controller applies the full application
+    /// configuration and renegotiates its contract. Check `is_dead_battery` afterwards to confirm.
+    pub async fn clear_dead_battery(&mut self, port: PortId) -> Result<(), Error<B::Error>> {
+        let ret = self
+            .execute_command(port, Command::Dbfg, DBFG_TIMEOUT_MS, None, None)
+            .await?;
+        let ret: Result<(), PdError> = ret.into();
+        ret.map_err(Error::Pd)
+    }
+
     /// Reset the device.
     async fn reset(&mut self, delay: &mut impl DelayNs) -> Result<(), Error<B::Error>> {
         let _guard = self.disable_all_interrupts_guarded().await;
@@ -298,7 +1592,13 @@ impl<'a, M: RawMutex, B: I2c> interrupt::InterruptController for Tps6699x<'a, M,
         &mut self,
         enabled: [bool; MAX_SUPPORTED_PORTS],
     ) -> Result<Self::Guard, Error<Self::BusError>> {
-        Ok(InterruptGuard::new(self.controller, enabled))
+        let guard = InterruptGuard::new(self.controller, enabled);
+        if self.controller.masks_to_restore() {
+            let mut inner = self.controller.inner.lock().await;
+            self.controller.restore_interrupt_masks(&mut inner).await?;
+        }
+
+        Ok(guard)
     }
 }
 
@@ -307,47 +1607,188 @@ pub struct Interrupt<'a, M: RawMutex, B: I2c> {
 }
 
 impl<'a, M: RawMutex, B: I2c> Interrupt<'a, M, B> {
-    async fn lock_inner(&mut self) -> MutexGuard<'_, M, internal::Tps6699x<B>> {
-        self.controller.inner.lock().await
-    }
-
-    /// Process interrupts
+    /// Process interrupts, the interrupt line is an active-low GPIO
     pub async fn process_interrupt(
         &mut self,
         int: &mut impl InputPin,
+    ) -> Result<[IntEventBus1; MAX_SUPPORTED_PORTS], Error<B::Error>> {
+        self.process_interrupt_line(&mut || int.is_low()).await
+    }
+
+    /// Process interrupts, checking whether the interrupt is asserted through the given [`InterruptLine`]
+    ///
+    /// For boards where the interrupt isn't a bare GPIO, see [`Self::process_interrupt`] for the GPIO case.
+    pub async fn process_interrupt_line(
+        &mut self,
+        line: &mut impl InterruptLine,
     ) -> Result<[IntEventBus1; MAX_SUPPORTED_PORTS], Error<B::Error>> {
         let mut flags = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+        #[cfg(feature = "interrupt-stats")]
+        let start = embassy_time::Instant::now();
 
         {
-            let interrupts_enabled = self.controller.interrupts_enabled();
-            let mut inner = self.lock_inner().await;
+            let interrupts_enabled = self.controller.interrupts_serviced();
+            let mask_disabled_ports = self.controller.mask_disabled_ports.load(Ordering::SeqCst);
+            // Lock through the shared controller reference so its other state stays accessible while locked
+            let controller = self.controller;
+            let mut inner = controller.inner.lock().await;
             for port in 0..inner.num_ports() {
                 let port_id = PortId(port as u8);
 
+                // Mask disabled ports in hardware so they can't hold the interrupt line, restore once re-enabled
+                let saved_mask = self.controller.saved_masks.lock(|masks| masks.get()[port]);
+                if !interrupts_enabled[port] && mask_disabled_ports {
+                    if saved_mask.is_none() {
+                        let mask = inner.get_interrupt_mask(port_id).await?;
+                        inner.set_interrupt_mask(port_id, IntEventBus1::new_zero()).await?;
+                        self.controller.set_saved_mask(port, Some(mask));
+                    }
+                } else if let Some(mask) = saved_mask {
+                    inner.set_interrupt_mask(port_id, mask).await?;
+                    self.controller.set_saved_mask(port, None);
+                }
+
                 if !interrupts_enabled[port] {
                     continue;
                 }
 
-                let result = int.is_high();
+                let result = line.is_asserted().await;
                 if result.is_err() {
                     error!("Failed to read interrupt line");
                     return PdError::Failed.into();
                 }
 
                 // Early exit if checking the last port cleared the interrupt
-                if result.unwrap() {
+                if !result.unwrap() {
                     continue;
                 }
 
                 flags[port] = inner.clear_interrupt(port_id).await?;
+                let snapshot = if flags[port].status_updated() {
+                    Some(inner.get_port_snapshot(port_id).await?)
+                } else {
+                    None
+                };
+                let status = match snapshot {
+                    Some(snapshot) => snapshot.status,
+                    None if flags[port].plug_event() || pd_events::needs_status(&flags[port]) => {
+                        inner.get_port_status(port_id).await?
+                    }
+                    None => Status::new_zero(),
+                };
+
+                self.controller.record_interrupt(port, &flags[port], &status, snapshot);
             }
         }
 
-        self.controller.interrupt_waker.signal(flags);
+        #[cfg(feature = "interrupt-stats")]
+        {
+            let pending_ports = flags.iter().filter(|f| **f != IntEventBus1::new_zero()).count();
+            self.controller
+                .interrupt_stats
+                .record(start.elapsed(), pending_ports as u32);
+        }
+
+        self.controller.notify_interrupt(flags);
         Ok(flags)
     }
 }
 
+/// Returns true if the interrupt flags indicate PD message traffic on the port
+fn is_pd_activity(flags: &IntEventBus1) -> bool {
+    flags.hard_reset()
+        || flags.power_swap_completed()
+        || flags.data_swap_completed()
+        || flags.fast_role_swap_completed()
+        || flags.attention_received()
+        || flags.vdm_received()
+        || flags.new_consumer_contract()
+        || flags.new_provider_contract()
+        || flags.source_caps_received()
+        || flags.sink_caps_received()
+        || flags.power_swap_requested()
+        || flags.data_swap_requested()
+        || flags.no_caps_response()
+        || flags.protocol_error()
+        || flags.not_supported_received()
+        || flags.alert_message_received()
+        || flags.chunk_response_received()
+        || flags.chunk_request_received()
+}
+
+/// Returns the ports serviced by interrupt processing
+///
+/// A port with a command in progress is serviced even if its interrupts are disabled, otherwise disabling interrupts
+/// while another task awaits a command's completion interrupt would stall the command until it falls back to polling.
+/// All of the port's pending interrupts are processed and signaled while it's pinned.
+fn serviced_ports(
+    enabled: [bool; MAX_SUPPORTED_PORTS],
+    commands_in_progress: &[AtomicU32; MAX_SUPPORTED_PORTS],
+) -> [bool; MAX_SUPPORTED_PORTS] {
+    let mut serviced = enabled;
+    for (serviced, count) in zip(serviced.iter_mut(), commands_in_progress.iter()) {
+        *serviced |= count.load(Ordering::SeqCst) > 0;
+    }
+
+    serviced
+}
+
+/// Record a port's latest snapshot, returns the previous one or an unattached port if there was none
+fn replace_snapshot<M: RawMutex>(
+    snapshots: &BlockingMutex<M, Cell<[Option<PortSnapshot>; MAX_SUPPORTED_PORTS]>>,
+    port: usize,
+    snapshot: PortSnapshot,
+) -> PortSnapshot {
+    snapshots.lock(|snapshots| {
+        let mut ports = snapshots.get();
+        let previous = ports[port].replace(snapshot);
+        snapshots.set(ports);
+        previous.unwrap_or_default()
+    })
+}
+
+/// Marks a command in progress on a port for as long as it's alive, see [`serviced_ports`]
+///
+/// Dropping the pin also covers commands cancelled by a timeout.
+struct CommandPin<'a> {
+    count: &'a AtomicU32,
+}
+
+impl<'a> CommandPin<'a> {
+    fn new(count: &'a AtomicU32) -> Self {
+        count.fetch_add(1, Ordering::SeqCst);
+        Self { count }
+    }
+}
+
+impl Drop for CommandPin<'_> {
+    fn drop(&mut self) {
+        self.count.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Restores the previous minimum command timeout when dropped
+pub struct CommandTimeoutGuard<'a> {
+    previous_ms: u32,
+    min_timeout_ms: &'a AtomicU32,
+}
+
+impl<'a> CommandTimeoutGuard<'a> {
+    fn new(min_timeout_ms: &'a AtomicU32, timeout_ms: u32) -> Self {
+        let previous_ms = min_timeout_ms.swap(timeout_ms, Ordering::SeqCst);
+        Self {
+            previous_ms,
+            min_timeout_ms,
+        }
+    }
+}
+
+impl Drop for CommandTimeoutGuard<'_> {
+    fn drop(&mut self) {
+        self.min_timeout_ms.store(self.previous_ms, Ordering::SeqCst);
+    }
+}
+
 /// Restores the original interrupt state when dropped
 pub struct InterruptGuard<'a, M: RawMutex, B: I2c> {
     target_state: [bool; MAX_SUPPORTED_PORTS],
@@ -372,3 +1813,1179 @@ impl<M: RawMutex, B: I2c> Drop for InterruptGuard<'_, M, B> {
 }
 
 impl<M: RawMutex, B: I2c> interrupt::InterruptGuard for InterruptGuard<'_, M, B> {}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use std::vec::Vec;
+
+    use embedded_hal_mock::eh1::i2c::Transaction;
+
+    use super::*;
+    use crate::test::{create_register_read, create_register_write, PORT0_ADDR0};
+
+    /// Transactions of a command on port 0 that has already completed once it's confirmed as accepted
+    fn completed_command(cmd: Command, args: &[u8], ret: ReturnValue) -> Vec<Transaction> {
+        let mut result = [0u8; registers::REG_DATA1_LEN];
+        result[0] = ret as u8;
+
+        let mut transactions = Vec::new();
+        // Busy check
+        transactions.push(create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]));
+        if !args.is_empty() {
+            let mut write = std::vec![0x09, args.len() as u8];
+            write.extend_from_slice(args);
+            transactions.push(Transaction::write(PORT0_ADDR0, write));
+        }
+        transactions.push(create_register_write(PORT0_ADDR0, 0x08, (cmd as u32).to_le_bytes()));
+        // Valid command and accepted checks
+        transactions.push(create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]));
+        transactions.push(create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]));
+        // Completion check and result read
+        transactions.push(create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]));
+        transactions.push(create_register_read(PORT0_ADDR0, 0x09, result));
+        transactions
+    }
+
+    #[test]
+    fn test_command_timeout_guard() {
+        let min_timeout_ms = AtomicU32::new(0);
+
+        {
+            let _guard = CommandTimeoutGuard::new(&min_timeout_ms, 5000);
+            assert_eq!(min_timeout_ms.load(Ordering::SeqCst), 5000);
+
+            {
+                let _nested = CommandTimeoutGuard::new(&min_timeout_ms, 10000);
+                assert_eq!(min_timeout_ms.load(Ordering::SeqCst), 10000);
+            }
+
+            assert_eq!(min_timeout_ms.load(Ordering::SeqCst), 5000);
+        }
+
+        assert_eq!(min_timeout_ms.load(Ordering::SeqCst), 0);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_inject_interrupt() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            let mut subscriber = tps.subscribe_interrupts().unwrap();
+
+            let mut flags = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+            flags[1].set_plug_event(true);
+            flags[1].set_new_consumer_contract(true);
+            tps.inject_interrupt(flags);
+
+            assert_eq!(subscriber.try_next_message_pure(), Some(flags));
+            assert_eq!(
+                tps.wait_interrupt(false, |port, flags| port == crate::PORT1 && flags.plug_event())
+                    .await,
+                flags
+            );
+
+            // Events are decoded against an unattached status
+            assert_eq!(tps.next_event(crate::PORT1).await.unwrap(), PdInterruptEvent::PlugEvent);
+            assert_eq!(
+                tps.next_event(crate::PORT1).await.unwrap(),
+                PdInterruptEvent::NewContract { role: PowerRole::Sink }
+            );
+            assert_eq!(tps.get_attach_count(crate::PORT1).unwrap(), 0);
+            assert!(tps.last_interrupt_age(crate::PORT1).unwrap().is_some());
+            assert!(tps.last_interrupt_age(crate::PORT0).unwrap().is_none());
+        }
+
+        // Nothing was sent to the hardware
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_back_to_back_commands() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::test::{create_register_read, create_register_write, PORT0_ADDR0};
+        use crate::PORT0;
+
+        let cmd = (Command::Sryr as u32).to_le_bytes();
+        let mut result = [0u8; registers::REG_DATA1_LEN];
+        result[0] = ReturnValue::Success as u8;
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, mut interrupt) = controller.make_parts();
+            tps.set_command_interrupt_timeout_ms(1).unwrap();
+
+            // First command completes before it's confirmed as accepted
+            tps.controller.inner.lock().await.bus.update_expectations(&[
+                // Busy check
+                create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]),
+                create_register_write(PORT0_ADDR0, 0x08, cmd),
+                // Valid command check
+                create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]),
+                // Accepted check
+                create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]),
+                // Completion check and result read
+                create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]),
+                create_register_read(PORT0_ADDR0, 0x09, result),
+            ]);
+            assert_eq!(
+                tps.execute_command_no_timeout(PORT0, Command::Sryr, None, None)
+                    .await
+                    .unwrap(),
+                ReturnValue::Success
+            );
+            tps.controller.inner.lock().await.bus.done();
+
+            // Its completion interrupt is processed afterwards, leaving a stale snapshot
+            let mut flags = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+            flags[0].set_cmd_1_completed(true);
+            tps.controller.notify_interrupt(flags);
+
+            // Second command is still in progress, its completion is only seen by the fallback poll
+            tps.controller.inner.lock().await.bus.update_expectations(&[
+                // Busy check
+                create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]),
+                create_register_write(PORT0_ADDR0, 0x08, cmd),
+                // Valid command check
+                create_register_read(PORT0_ADDR0, 0x08, cmd),
+                // Accepted check
+                create_register_read(PORT0_ADDR0, 0x08, cmd),
+                // Fallback poll
+                create_register_read(PORT0_ADDR0, 0x08, cmd),
+                // Completion check and result read
+                create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]),
+                create_register_read(PORT0_ADDR0, 0x09, result),
+            ]);
+            assert_eq!(
+                tps.execute_command_no_timeout(PORT0, Command::Sryr, None, None)
+                    .await
+                    .unwrap(),
+                ReturnValue::Success
+            );
+            tps.controller.inner.lock().await.bus.done();
+
+            // The first command's completion is still set in hardware, it's processed while the next command is in
+            // progress and mustn't complete it early
+            tps.set_command_interrupt_timeout_ms(1000).unwrap();
+            let mut completed = IntEventBus1::new_zero();
+            completed.set_cmd_1_completed(true);
+            tps.controller.inner.lock().await.bus.update_expectations(&[
+                // Busy check
+                create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]),
+                create_register_write(PORT0_ADDR0, 0x08, cmd),
+                // Valid command check
+                create_register_read(PORT0_ADDR0, 0x08, cmd),
+                // Accepted check
+                create_register_read(PORT0_ADDR0, 0x08, cmd),
+                // Stale completion, the command is still in progress
+                create_register_read(PORT0_ADDR0, 0x14, completed),
+                create_register_write(PORT0_ADDR0, 0x18, completed),
+                create_register_read(PORT0_ADDR0, 0x08, cmd),
+                // Actual completion, then the completion check and result read
+                create_register_read(PORT0_ADDR0, 0x14, completed),
+                create_register_write(PORT0_ADDR0, 0x18, completed),
+                create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]),
+                create_register_read(PORT0_ADDR0, 0x09, result),
+            ]);
+
+            let process = async {
+                for _ in 0..2 {
+                    Timer::after_millis(1).await;
+                    let mut asserted = [true, false].into_iter();
+                    interrupt
+                        .process_interrupt_line(&mut || Ok::<_, ()>(asserted.next().unwrap()))
+                        .await
+                        .unwrap();
+                }
+            };
+            let (ret, _) = tokio::join!(
+                tps.execute_command_no_timeout(PORT0, Command::Sryr, None, None),
+                process
+            );
+            assert_eq!(ret.unwrap(), ReturnValue::Success);
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_command_completion() {
+        extern crate std;
+
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::test::{create_register_read, create_register_write, PORT0_ADDR0};
+        use crate::PORT0;
+
+        let cmd = (Command::Sryr as u32).to_le_bytes();
+        let mut result = [0u8; registers::REG_DATA1_LEN];
+        result[0] = ReturnValue::Success as u8;
+        let send = [
+            // Busy check
+            create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]),
+            create_register_write(PORT0_ADDR0, 0x08, cmd),
+            // Valid command check
+            create_register_read(PORT0_ADDR0, 0x08, cmd),
+            // Accepted check
+            create_register_read(PORT0_ADDR0, 0x08, cmd),
+        ];
+        let complete = [
+            // Completion check and result read
+            create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]),
+            create_register_read(PORT0_ADDR0, 0x09, result),
+        ];
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            let shared = tps.controller;
+
+            // The completion interrupt arrives well before the command register would be polled
+            tps.set_command_interrupt_timeout_ms(1000).unwrap();
+            shared
+                .inner
+                .lock()
+                .await
+                .bus
+                .update_expectations(&send.iter().chain(&complete).cloned().collect::<std::vec::Vec<_>>());
+
+            let mut flags = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+            flags[0].set_cmd_1_completed(true);
+            let notify = async {
+                Timer::after_millis(1).await;
+                shared.notify_interrupt(flags);
+            };
+            let (ret, _) = tokio::join!(tps.execute_command_no_timeout(PORT0, Command::Sryr, None, None), notify);
+            assert_eq!(ret.unwrap(), ReturnValue::Success);
+            shared.inner.lock().await.bus.done();
+
+            // The interrupt is missed, the command register is polled until the command completes
+            tps.set_command_interrupt_timeout_ms(1).unwrap();
+            let polls = [
+                create_register_read(PORT0_ADDR0, 0x08, cmd),
+                create_register_read(PORT0_ADDR0, 0x08, cmd),
+            ];
+            shared.inner.lock().await.bus.update_expectations(
+                &send
+                    .iter()
+                    .chain(&polls)
+                    .chain(&complete)
+                    .cloned()
+                    .collect::<std::vec::Vec<_>>(),
+            );
+
+            let ret = tps.execute_command_no_timeout(PORT0, Command::Sryr, None, None).await;
+            assert_eq!(ret.unwrap(), ReturnValue::Success);
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_command_keeps_other_port_interrupt() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::test::{create_register_read, create_register_write, PORT0_ADDR0};
+        use crate::{PORT0, PORT1};
+
+        let cmd = (Command::Sryr as u32).to_le_bytes();
+        let mut result = [0u8; registers::REG_DATA1_LEN];
+        result[0] = ReturnValue::Success as u8;
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            tps.set_command_interrupt_timeout_ms(1).unwrap();
+
+            // Interrupt on the other port that no task has waited on yet
+            let mut flags = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+            flags[1].set_plug_event(true);
+            tps.controller.notify_interrupt(flags);
+
+            tps.controller.inner.lock().await.bus.update_expectations(&[
+                // Busy check
+                create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]),
+                create_register_write(PORT0_ADDR0, 0x08, cmd),
+                // Valid command check
+                create_register_read(PORT0_ADDR0, 0x08, cmd),
+                // Accepted check
+                create_register_read(PORT0_ADDR0, 0x08, cmd),
+                // Completion check and result read, on the first fallback poll
+                create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]),
+                create_register_read(PORT0_ADDR0, 0x09, result),
+            ]);
+            assert_eq!(
+                tps.execute_command_no_timeout(PORT0, Command::Sryr, None, None)
+                    .await
+                    .unwrap(),
+                ReturnValue::Success
+            );
+
+            let waited = with_timeout(
+                Duration::from_millis(10),
+                tps.wait_interrupt(false, |port, flags| port == PORT1 && flags.plug_event()),
+            )
+            .await;
+            assert_eq!(waited.unwrap(), flags);
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_app_mode_releases_lock() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::test::{create_register_read, PORT0_ADDR0};
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            let shared = tps.controller;
+            shared.inner.lock().await.bus.update_expectations(&[
+                create_register_read(PORT0_ADDR0, 0x03, Mode::Ptch),
+                // Read by the other task while the first waits to poll again
+                create_register_read(PORT0_ADDR0, 0x0F, 0x1234u32.to_le_bytes()),
+                create_register_read(PORT0_ADDR0, 0x03, Mode::App0),
+            ]);
+
+            let mut other = Tps6699x { controller: shared };
+            let read_version = async {
+                Timer::after_millis(1).await;
+                other.get_fw_version().await
+            };
+            let (result, version) = tokio::join!(tps.wait_for_app_mode(1000), read_version);
+            result.unwrap();
+            assert_eq!(version.unwrap(), 0x1234);
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_read_register_retry_releases_lock() {
+        extern crate std;
+
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+
+        use crate::test::{create_register_read, PORT0_ADDR0};
+        use crate::PORT0;
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            let shared = tps.controller;
+            shared.inner.lock().await.bus.update_expectations(&[
+                // Busy
+                Transaction::write_read(PORT0_ADDR0, std::vec![0x03], std::vec![0; 5]),
+                // Read by the other task while the first waits to retry
+                create_register_read(PORT0_ADDR0, 0x0F, 0x1234u32.to_le_bytes()),
+                create_register_read(PORT0_ADDR0, 0x03, Mode::App0),
+            ]);
+
+            let mut other = Tps6699x { controller: shared };
+            let read_version = async {
+                Timer::after_millis(1).await;
+                other.get_fw_version().await
+            };
+            let mut buf = [0u8; 4];
+            let (result, version) = tokio::join!(
+                tps.read_register_retry(PORT0, Register::Mode, &mut buf, 2, 10),
+                read_version
+            );
+            result.unwrap();
+            assert_eq!(&buf, b"APP0");
+            assert_eq!(version.unwrap(), 0x1234);
+
+            assert!(matches!(
+                tps.read_register_retry(PORT0, Register::Mode, &mut buf, 0, 10).await,
+                Err(Error::Pd(PdError::InvalidParams))
+            ));
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_mask_disabled_ports() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::test::{create_register_read, create_register_write, PORT0_ADDR0};
+        use crate::PORT0;
+
+        let mut mask = IntEventBus1::new_zero();
+        mask.set_plug_event(true);
+        mask.set_cmd_1_completed(true);
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, mut interrupt) = controller.make_parts();
+            let shared = tps.controller;
+            tps.set_mask_disabled_ports(true);
+            let mut line = || Ok::<_, ()>(false);
+
+            // Disabling a port doesn't touch the hardware, it's masked once interrupts are processed
+            let disabled = tps.enable_interrupt_guarded(PORT0, false).await.unwrap();
+            shared.inner.lock().await.bus.update_expectations(&[
+                create_register_read(PORT0_ADDR0, 0x16, mask),
+                create_register_write(PORT0_ADDR0, 0x16, IntEventBus1::new_zero()),
+            ]);
+            interrupt.process_interrupt_line(&mut line).await.unwrap();
+            shared.inner.lock().await.bus.done();
+
+            // Enabling it through a guard restores the mask right away
+            shared
+                .inner
+                .lock()
+                .await
+                .bus
+                .update_expectations(&[create_register_write(PORT0_ADDR0, 0x16, mask)]);
+            let enabled = tps.enable_interrupt_guarded(PORT0, true).await.unwrap();
+            shared.inner.lock().await.bus.done();
+
+            // Dropping the guard disables it again
+            drop(enabled);
+            shared.inner.lock().await.bus.update_expectations(&[
+                create_register_read(PORT0_ADDR0, 0x16, mask),
+                create_register_write(PORT0_ADDR0, 0x16, IntEventBus1::new_zero()),
+            ]);
+            interrupt.process_interrupt_line(&mut line).await.unwrap();
+            shared.inner.lock().await.bus.done();
+
+            // Dropping the guard that disabled it leaves the restore to the next operation
+            drop(disabled);
+            shared.inner.lock().await.bus.update_expectations(&[
+                create_register_write(PORT0_ADDR0, 0x16, mask),
+                create_register_read(PORT0_ADDR0, 0x1A, Status::new_zero()),
+            ]);
+            tps.get_port_status(PORT0).await.unwrap();
+            assert_eq!(
+                shared.saved_masks.lock(|masks| masks.get()),
+                [None; MAX_SUPPORTED_PORTS]
+            );
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_try_get_port_status() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::test::{create_register_read, PORT0_ADDR0};
+        use crate::PORT0;
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            let shared = tps.controller;
+
+            // Skipped while another task holds the lock
+            {
+                let _inner = shared.inner.lock().await;
+                assert_eq!(tps.try_get_port_status(PORT0).await.unwrap(), None);
+            }
+
+            let mut status = Status::new_zero();
+            status.set_plug_present(true);
+            shared
+                .inner
+                .lock()
+                .await
+                .bus
+                .update_expectations(&[create_register_read(PORT0_ADDR0, 0x1A, status)]);
+            assert_eq!(tps.try_get_port_status(PORT0).await.unwrap(), Some(status));
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_next_event() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::{PORT0, PORT1};
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            let shared = tps.controller;
+
+            // Buffered events are returned in priority order
+            let mut flags = IntEventBus1::new_zero();
+            flags.set_vdm_received(true);
+            flags.set_hard_reset(true);
+            shared.buffer_events(0, &pd_events::decode(&flags, &Status::new_zero()));
+            assert_eq!(tps.next_event(PORT0).await.unwrap(), PdInterruptEvent::HardReset);
+            assert_eq!(tps.next_event(PORT0).await.unwrap(), PdInterruptEvent::VdmReceived);
+
+            // Waits for an event on the requested port
+            let notify = async {
+                Timer::after_millis(1).await;
+                let mut flags = IntEventBus1::new_zero();
+                flags.set_plug_event(true);
+                shared.buffer_events(1, &pd_events::decode(&flags, &Status::new_zero()));
+                shared.notify_interrupt([IntEventBus1::new_zero(), flags]);
+            };
+            let (event, _) = tokio::join!(tps.next_event(PORT1), notify);
+            assert_eq!(event.unwrap(), PdInterruptEvent::PlugEvent);
+
+            assert!(matches!(
+                tps.next_event(PortId(2)).await,
+                Err(Error::Pd(PdError::InvalidPort))
+            ));
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_clear_pending_events() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::test::{create_register_read, create_register_write, PORT0_ADDR0};
+        use crate::PORT0;
+
+        let mut stale = IntEventBus1::new_zero();
+        stale.set_plug_event(true);
+        stale.set_hard_reset(true);
+        let mut fresh = IntEventBus1::new_zero();
+        fresh.set_vdm_received(true);
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, mut interrupt) = controller.make_parts();
+            let shared = tps.controller;
+
+            // Events from before the caller was interested, the plug event reads the status for the attach count
+            shared.inner.lock().await.bus.update_expectations(&[
+                create_register_read(PORT0_ADDR0, 0x14, stale),
+                create_register_write(PORT0_ADDR0, 0x18, stale),
+                create_register_read(PORT0_ADDR0, 0x1A, Status::new_zero()),
+            ]);
+            let mut asserted = [true, false].into_iter();
+            interrupt
+                .process_interrupt_line(&mut || Ok::<_, ()>(asserted.next().unwrap()))
+                .await
+                .unwrap();
+            shared.inner.lock().await.bus.done();
+
+            tps.clear_pending_events(PORT0).unwrap();
+
+            shared.inner.lock().await.bus.update_expectations(&[
+                create_register_read(PORT0_ADDR0, 0x14, fresh),
+                create_register_write(PORT0_ADDR0, 0x18, fresh),
+            ]);
+            let mut asserted = [true, false].into_iter();
+            interrupt
+                .process_interrupt_line(&mut || Ok::<_, ()>(asserted.next().unwrap()))
+                .await
+                .unwrap();
+
+            // Only the event processed after clearing is returned
+            assert_eq!(tps.next_event(PORT0).await.unwrap(), PdInterruptEvent::VdmReceived);
+            assert_eq!(shared.take_event(0), None);
+
+            assert!(matches!(
+                tps.clear_pending_events(PortId(2)),
+                Err(Error::Pd(PdError::InvalidPort))
+            ));
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_status_updated_events() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::registers::field_sets::ActiveRdoContract;
+        use crate::registers::port_config::PowerRole;
+        use crate::registers::PlugMode;
+        use crate::test::{create_register_read, create_register_write, PORT0_ADDR0};
+        use crate::PORT0;
+
+        let mut flags = IntEventBus1::new_zero();
+        flags.set_status_updated(true);
+        flags.set_new_consumer_contract(true);
+        let mut status = Status::new_zero();
+        status.set_plug_present(true);
+        status.set_connection_state(PlugMode::Connected);
+        let mut rdo = ActiveRdoContract::new_zero();
+        rdo.set_active_rdo(0x1304b12c);
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, mut interrupt) = controller.make_parts();
+            let shared = tps.controller;
+
+            // Only port 0 has a pending interrupt, the status is diffed against an unattached port
+            shared.inner.lock().await.bus.update_expectations(&[
+                create_register_read(PORT0_ADDR0, 0x14, flags),
+                create_register_write(PORT0_ADDR0, 0x18, flags),
+                create_register_read(PORT0_ADDR0, 0x1A, status),
+                create_register_read(PORT0_ADDR0, 0x35, rdo),
+            ]);
+            let mut asserted = [true, false].into_iter();
+            let mut line = || Ok::<_, ()>(asserted.next().unwrap());
+            interrupt.process_interrupt_line(&mut line).await.unwrap();
+
+            // The contract reported by both the flags and the diff is only returned once
+            assert_eq!(tps.next_event(PORT0).await.unwrap(), PdInterruptEvent::Attached);
+            assert_eq!(
+                tps.next_event(PORT0).await.unwrap(),
+                PdInterruptEvent::ConnectionChanged(PlugMode::Connected)
+            );
+            assert_eq!(
+                tps.next_event(PORT0).await.unwrap(),
+                PdInterruptEvent::NewContract { role: PowerRole::Sink }
+            );
+            assert_eq!(shared.take_event(0), None);
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_partner_alert() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::{PORT0, PORT1};
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            let shared = tps.controller;
+            let mut flags = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+            flags[1].set_alert_message_received(true);
+            let notify = async {
+                Timer::after_millis(1).await;
+                shared.notify_interrupt(flags);
+            };
+
+            // An alert on the other port doesn't count
+            let (result, _) = tokio::join!(tps.wait_for_partner_alert(PORT0, 10), notify);
+            assert!(matches!(result, Err(Error::Pd(PdError::Timeout))));
+
+            let notify = async {
+                Timer::after_millis(1).await;
+                shared.notify_interrupt(flags);
+            };
+            let (result, _) = tokio::join!(tps.wait_for_partner_alert(PORT1, 1000), notify);
+            result.unwrap();
+
+            assert!(matches!(
+                tps.wait_for_partner_alert(PortId(2), 10).await,
+                Err(Error::Pd(PdError::InvalidPort))
+            ));
+        }
+
+        // Nothing was sent to the hardware
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_request_partner_source_caps() {
+        extern crate std;
+
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::pdo::SourcePdo;
+        use crate::registers::rx_caps::REG_RX_SOURCE_CAPS_LEN;
+        use crate::test::{create_register_read, create_register_write, PORT0_ADDR0};
+        use crate::PORT0;
+
+        let cmd = (Command::Gsrc as u32).to_le_bytes();
+        let mut result = [0u8; registers::REG_DATA1_LEN];
+        result[0] = ReturnValue::Success as u8;
+        let command = [
+            // Busy check
+            create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]),
+            create_register_write(PORT0_ADDR0, 0x08, cmd),
+            // Valid command check
+            create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]),
+            // Accepted check
+            create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]),
+            // Completion check and result read
+            create_register_read(PORT0_ADDR0, 0x08, [0u8; 4]),
+            create_register_read(PORT0_ADDR0, 0x09, result),
+        ];
+
+        let mut source = [0u8; REG_RX_SOURCE_CAPS_LEN];
+        source[0] = 1;
+        source[1..5].copy_from_slice(&0x0001_912Cu32.to_le_bytes());
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            let shared = tps.controller;
+
+            // The partner responds
+            let mut transactions = std::vec::Vec::from(command.clone());
+            transactions.push(create_register_read(PORT0_ADDR0, 0x30, source));
+            shared.inner.lock().await.bus.update_expectations(&transactions);
+
+            let mut flags = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+            flags[0].set_source_caps_received(true);
+            let notify = async {
+                Timer::after_millis(1).await;
+                shared.notify_interrupt(flags);
+            };
+            let (caps, _) = tokio::join!(tps.request_partner_source_caps(PORT0), notify);
+            assert_eq!(
+                caps.unwrap().pdos(),
+                &[SourcePdo::Fixed {
+                    voltage_mv: 5000,
+                    max_current_ma: 3000,
+                }]
+            );
+            shared.inner.lock().await.bus.done();
+
+            // The controller reports the partner didn't respond, the stale capabilities aren't read
+            shared.inner.lock().await.bus.update_expectations(&command);
+
+            let mut flags = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+            flags[0].set_no_caps_response(true);
+            let notify = async {
+                Timer::after_millis(1).await;
+                shared.notify_interrupt(flags);
+            };
+            let (caps, _) = tokio::join!(tps.request_partner_source_caps(PORT0), notify);
+            assert!(matches!(caps, Err(Error::Pd(PdError::Failed))));
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_soft_reset() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::PORT0;
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            let shared = tps.controller;
+
+            for (ret, expected) in [
+                (ReturnValue::Success, Ok(())),
+                // The partner didn't respond
+                (ReturnValue::Abort, Err(PdError::Timeout)),
+                (ReturnValue::Rejected, Err(PdError::Failed)),
+            ] {
+                shared
+                    .inner
+                    .lock()
+                    .await
+                    .bus
+                    .update_expectations(&completed_command(Command::Srst, &[], ret));
+                let result = tps.soft_reset(PORT0).await.map_err(|e| match e {
+                    Error::Pd(e) => e,
+                    Error::Bus(_) => panic!("Unexpected bus error"),
+                });
+                assert_eq!(result, expected);
+                shared.inner.lock().await.bus.done();
+            }
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_clear_dead_battery() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::PORT0;
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            let shared = tps.controller;
+
+            shared.inner.lock().await.bus.update_expectations(&completed_command(
+                Command::Dbfg,
+                &[],
+                ReturnValue::Success,
+            ));
+            tps.clear_dead_battery(PORT0).await.unwrap();
+            shared.inner.lock().await.bus.done();
+
+            shared.inner.lock().await.bus.update_expectations(&completed_command(
+                Command::Dbfg,
+                &[],
+                ReturnValue::Rejected,
+            ));
+            assert!(matches!(
+                tps.clear_dead_battery(PORT0).await,
+                Err(Error::Pd(PdError::Failed))
+            ));
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_bist_mode() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::PORT0;
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            let shared = tps.controller;
+
+            for mode in [BistMode::Carrier, BistMode::TestData, BistMode::SharedTest] {
+                shared.inner.lock().await.bus.update_expectations(&completed_command(
+                    Command::Bist,
+                    &[mode.into()],
+                    ReturnValue::Success,
+                ));
+                tps.set_bist_mode(PORT0, mode).await.unwrap();
+                shared.inner.lock().await.bus.done();
+            }
+
+            shared.inner.lock().await.bus.update_expectations(&completed_command(
+                Command::Bist,
+                &[BIST_EXIT_SHARED_TEST],
+                ReturnValue::Success,
+            ));
+            tps.exit_bist_mode(PORT0).await.unwrap();
+            shared.inner.lock().await.bus.done();
+
+            // Rejected without an explicit contract
+            shared.inner.lock().await.bus.update_expectations(&completed_command(
+                Command::Bist,
+                &[BistMode::Carrier.into()],
+                ReturnValue::Rejected,
+            ));
+            assert!(matches!(
+                tps.set_bist_mode(PORT0, BistMode::Carrier).await,
+                Err(Error::Pd(PdError::Failed))
+            ));
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_detach() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::PORT0;
+
+        let mut attached = Status::new_zero();
+        attached.set_plug_present(true);
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            let shared = tps.controller;
+
+            // Already detached
+            shared
+                .inner
+                .lock()
+                .await
+                .bus
+                .update_expectations(&[create_register_read(PORT0_ADDR0, 0x1A, Status::new_zero())]);
+            tps.wait_for_detach(PORT0).await.unwrap();
+            shared.inner.lock().await.bus.done();
+
+            // A plug event on the other port is ignored, the status is only read again after one on this port
+            shared.inner.lock().await.bus.update_expectations(&[
+                create_register_read(PORT0_ADDR0, 0x1A, attached),
+                create_register_read(PORT0_ADDR0, 0x1A, Status::new_zero()),
+            ]);
+            let mut other = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+            other[1].set_plug_event(true);
+            let mut detach = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+            detach[0].set_plug_event(true);
+            let notify = async {
+                Timer::after_millis(1).await;
+                shared.notify_interrupt(other);
+                Timer::after_millis(1).await;
+                shared.notify_interrupt(detach);
+            };
+            let (result, _) = tokio::join!(tps.wait_for_detach(PORT0), notify);
+            result.unwrap();
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_last_activity_instant() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::{PORT0, PORT1};
+
+        let mut command = IntEventBus1::new_zero();
+        command.set_cmd_1_completed(true);
+        let mut vdm = IntEventBus1::new_zero();
+        vdm.set_vdm_received(true);
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (tps, mut interrupt) = controller.make_parts();
+            let shared = tps.controller;
+            assert_eq!(tps.last_activity_instant(PORT0).unwrap(), None);
+
+            // Command completion isn't PD activity
+            shared.inner.lock().await.bus.update_expectations(&[
+                create_register_read(PORT0_ADDR0, 0x14, command),
+                create_register_write(PORT0_ADDR0, 0x18, command),
+            ]);
+            let mut asserted = [true, false].into_iter();
+            interrupt
+                .process_interrupt_line(&mut || Ok::<_, ()>(asserted.next().unwrap()))
+                .await
+                .unwrap();
+            shared.inner.lock().await.bus.done();
+            assert_eq!(tps.last_activity_instant(PORT0).unwrap(), None);
+
+            let before = Instant::now();
+            shared.inner.lock().await.bus.update_expectations(&[
+                create_register_read(PORT0_ADDR0, 0x14, vdm),
+                create_register_write(PORT0_ADDR0, 0x18, vdm),
+            ]);
+            let mut asserted = [true, false].into_iter();
+            interrupt
+                .process_interrupt_line(&mut || Ok::<_, ()>(asserted.next().unwrap()))
+                .await
+                .unwrap();
+            assert!(tps.last_activity_instant(PORT0).unwrap().unwrap() >= before);
+            assert_eq!(tps.last_activity_instant(PORT1).unwrap(), None);
+
+            assert!(matches!(
+                tps.last_activity_instant(PortId(2)),
+                Err(Error::Pd(PdError::InvalidPort))
+            ));
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_last_interrupt_age() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::{PORT0, PORT1};
+
+        let mut command = IntEventBus1::new_zero();
+        command.set_cmd_1_completed(true);
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (tps, mut interrupt) = controller.make_parts();
+            let shared = tps.controller;
+            assert_eq!(tps.last_interrupt_age(PORT0).unwrap(), None);
+
+            // Any interrupt counts, not only PD activity
+            let before = Instant::now();
+            shared.inner.lock().await.bus.update_expectations(&[
+                create_register_read(PORT0_ADDR0, 0x14, command),
+                create_register_write(PORT0_ADDR0, 0x18, command),
+            ]);
+            let mut asserted = [true, false].into_iter();
+            interrupt
+                .process_interrupt_line(&mut || Ok::<_, ()>(asserted.next().unwrap()))
+                .await
+                .unwrap();
+
+            Timer::after_millis(5).await;
+            let age = tps.last_interrupt_age(PORT0).unwrap().unwrap();
+            assert!(age >= Duration::from_millis(5));
+            assert!(age <= before.elapsed());
+            assert_eq!(tps.last_interrupt_age(PORT1).unwrap(), None);
+
+            assert!(matches!(
+                tps.last_interrupt_age(PortId(2)),
+                Err(Error::Pd(PdError::InvalidPort))
+            ));
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_interrupt_subscribers() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::PORT0;
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            let mut subscribers: Vec<_> = (0..MAX_INTERRUPT_SUBSCRIBERS)
+                .map(|_| tps.subscribe_interrupts().unwrap())
+                .collect();
+            assert!(matches!(tps.subscribe_interrupts(), Err(Error::Pd(PdError::Busy))));
+
+            // Every subscriber sees every snapshot, and a waiter doesn't take them from subscribers
+            let mut plug = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+            plug[0].set_plug_event(true);
+            let mut vdm = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+            vdm[1].set_vdm_received(true);
+            tps.controller.notify_interrupt(plug);
+            assert_eq!(
+                tps.wait_interrupt(false, |port, flags| port == PORT0 && flags.plug_event())
+                    .await,
+                plug
+            );
+            tps.controller.notify_interrupt(vdm);
+
+            for subscriber in subscribers.iter_mut() {
+                assert_eq!(subscriber.try_next_message_pure(), Some(plug));
+                assert_eq!(subscriber.try_next_message_pure(), Some(vdm));
+                assert_eq!(subscriber.try_next_message_pure(), None);
+            }
+
+            // Dropping a subscriber frees its slot
+            subscribers.pop();
+            tps.subscribe_interrupts().unwrap();
+        }
+
+        // Nothing was sent to the hardware
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_negotiate_with_fallback() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embedded_hal_mock::eh1::i2c::Mock;
+
+        use crate::registers::tx_sink_caps::REG_TX_SINK_CAPS_LEN;
+        use crate::PORT0;
+
+        let high: &[(u16, u16)] = &[(5000, 3000), (20000, 5000)];
+        let low: &[(u16, u16)] = &[(5000, 3000)];
+
+        // Detached, so each policy is programmed as is
+        let mut high_raw = [0u8; REG_TX_SINK_CAPS_LEN];
+        high_raw[0] = 2;
+        high_raw[1..5].copy_from_slice(&0x0001_912Cu32.to_le_bytes());
+        high_raw[5..9].copy_from_slice(&0x0006_41F4u32.to_le_bytes());
+        let mut low_raw = [0u8; REG_TX_SINK_CAPS_LEN];
+        low_raw[0] = 1;
+        low_raw[1..5].copy_from_slice(&0x0001_912Cu32.to_le_bytes());
+
+        let mut transactions = Vec::new();
+        for raw in [high_raw, low_raw] {
+            transactions.push(create_register_read(PORT0_ADDR0, 0x1A, Status::new_zero()));
+            transactions.push(create_register_write(PORT0_ADDR0, 0x33, raw));
+            transactions.extend(completed_command(Command::Gsrc, &[], ReturnValue::Success));
+        }
+
+        let mut controller: controller::Controller<NoopRawMutex, Mock> =
+            controller::Controller::new_tps66994(Mock::new(&transactions), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            let shared = tps.controller;
+
+            // The source doesn't respond to the first request, then accepts the second
+            let (result, _) = tokio::join!(tps.negotiate_with_fallback(PORT0, &[high, low]), async {
+                let mut no_response = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+                no_response[0].set_no_caps_response(true);
+                let mut contract = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+                contract[0].set_new_consumer_contract(true);
+
+                Timer::after_millis(1).await;
+                shared.notify_interrupt(no_response);
+                Timer::after_millis(1).await;
+                shared.notify_interrupt(contract);
+            });
+            assert_eq!(result.unwrap(), 1);
+
+            // No policies to try
+            let r = tps.negotiate_with_fallback(PORT0, &[]).await;
+            assert!(matches!(r, Err(Error::Pd(PdError::InvalidParams))));
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[test]
+    fn test_command_pin_races_interrupt_guard() {
+        let commands_in_progress = [const { AtomicU32::new(0) }; MAX_SUPPORTED_PORTS];
+
+        // Command on port 0 starts, then another task disables interrupts on both ports
+        let pin = CommandPin::new(&commands_in_progress[0]);
+        let disabled = [false; MAX_SUPPORTED_PORTS];
+        assert_eq!(serviced_ports(disabled, &commands_in_progress), [true, false]);
+
+        // Port 0 stays disabled once the command completes
+        drop(pin);
+        assert_eq!(serviced_ports(disabled, &commands_in_progress), [false, false]);
+
+        // Pins don't affect enabled ports
+        let _pin = CommandPin::new(&commands_in_progress[1]);
+        assert_eq!(serviced_ports([true, true], &commands_in_progress), [true, true]);
+    }
+}
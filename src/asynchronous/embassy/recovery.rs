@@ -0,0 +1,271 @@
+//! Automatic recovery from a wedged controller
+//!
+//! With a [`RecoveryPolicy`] set, command timeouts are counted and once enough happen in a row the controller is
+//! reset and the startup sequence is run again, see [`Tps6699x::set_recovery_policy`].
+use core::sync::atomic::Ordering;
+
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_time::Delay;
+use embedded_hal_async::i2c::I2c;
+use embedded_usb_pd::{Error, PdError};
+
+use super::init::{InitConfig, InitStep};
+use super::Tps6699x;
+use crate::{error, warn};
+
+/// Callback invoked after every recovery attempt
+pub type RecoveryHook = fn(RecoveryOutcome);
+
+/// Result of a recovery attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RecoveryOutcome {
+    /// The controller was reset and initialized
+    Recovered,
+    /// The reset command couldn't be sent
+    ResetFailed,
+    /// The controller was reset but the startup sequence failed at the given step
+    InitFailed(InitStep),
+}
+
+/// Automatic recovery policy
+#[derive(Debug, Clone, Copy)]
+pub struct RecoveryPolicy {
+    /// Number of consecutive command timeouts that trigger a recovery, must be non-zero
+    pub timeout_threshold: u32,
+    /// Configuration for the startup sequence run after the reset
+    pub init_config: InitConfig<'static>,
+    /// Called after every recovery attempt, e.g. to log it
+    pub on_recovery: Option<RecoveryHook>,
+}
+
+impl<M: RawMutex, B: I2c> Tps6699x<'_, M, B> {
+    /// Returns the automatic recovery policy, `None` if recovery is disabled
+    pub fn recovery_policy(&self) -> Option<RecoveryPolicy> {
+        self.controller.recovery_policy.lock(|policy| policy.get())
+    }
+
+    /// Set the automatic recovery policy, `None` disables recovery
+    ///
+    /// Once [`RecoveryPolicy::timeout_threshold`] commands in a row time out, the controller is assumed wedged. The
+    /// task whose command hit the threshold resets the controller and runs [`Self::initialize`] with the policy's
+    /// configuration before that command returns `Timeout`. While recovery is in progress, new commands on any port
+    /// return `Busy` and other operations wait on the driver lock. Commands that were already waiting when recovery
+    /// started will most likely time out, those timeouts aren't counted towards the next recovery. A command that
+    /// completes resets the count, bus errors and other failures leave it unchanged.
+    ///
+    /// Recovery hides a hang from the rest of the system, which can also hide a real fault: a controller that wedges
+    /// repeatedly gets reset over and over, and any state not restored by the startup sequence is lost every time,
+    /// e.g. an explicit contract or alternate mode. Use [`RecoveryPolicy::on_recovery`] to keep track of how often it
+    /// happens. Returns `InvalidParams` if the threshold is zero.
+    pub fn set_recovery_policy(&mut self, policy: Option<RecoveryPolicy>) -> Result<(), Error<B::Error>> {
+        if policy.is_some_and(|policy| policy.timeout_threshold == 0) {
+            return PdError::InvalidParams.into();
+        }
+
+        self.controller.recovery_policy.lock(|current| current.set(policy));
+        self.controller.consecutive_timeouts.store(0, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Returns the number of consecutive command timeouts counted towards the recovery threshold
+    pub fn consecutive_command_timeouts(&self) -> u32 {
+        self.controller.consecutive_timeouts.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of recoveries attempted since the driver started
+    pub fn recovery_count(&self) -> u32 {
+        self.controller.recoveries.load(Ordering::SeqCst)
+    }
+
+    /// Returns `Busy` if a recovery is in progress, otherwise the recovery count to pass to [`Self::command_finished`]
+    pub(super) fn command_started(&self) -> Result<u32, Error<B::Error>> {
+        if self.controller.recovering.load(Ordering::SeqCst) {
+            return PdError::Busy.into();
+        }
+
+        Ok(self.controller.recoveries.load(Ordering::SeqCst))
+    }
+
+    /// Count the outcome of a command started at recovery count `started`, recovers once the threshold is reached
+    pub(super) async fn command_finished(&mut self, started: u32, timed_out: bool) {
+        let controller = self.controller;
+        if !timed_out {
+            controller.consecutive_timeouts.store(0, Ordering::SeqCst);
+            return;
+        }
+
+        // The controller was reset while this command was waiting, the timeout says nothing about its current state
+        if controller.recoveries.load(Ordering::SeqCst) != started {
+            return;
+        }
+
+        let timeouts = controller.consecutive_timeouts.fetch_add(1, Ordering::SeqCst) + 1;
+        let Some(policy) = self.recovery_policy() else {
+            return;
+        };
+
+        if timeouts < policy.timeout_threshold
+            || controller
+                .recovering
+                .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+                .is_err()
+        {
+            return;
+        }
+
+        warn!("{} consecutive command timeouts, recovering controller", timeouts);
+        let outcome = self.recover(&policy.init_config).await;
+        controller.consecutive_timeouts.store(0, Ordering::SeqCst);
+        controller.recoveries.fetch_add(1, Ordering::SeqCst);
+        controller.recovering.store(false, Ordering::SeqCst);
+
+        if let Some(hook) = policy.on_recovery {
+            hook(outcome);
+        }
+    }
+
+    /// Reset the controller and run the startup sequence
+    async fn recover(&mut self, config: &InitConfig<'_>) -> RecoveryOutcome {
+        let mut delay = Delay;
+        if self.reset(&mut delay).await.is_err() {
+            error!("Recovery reset failed");
+            return RecoveryOutcome::ResetFailed;
+        }
+
+        match self.initialize(config).await {
+            Ok(()) => RecoveryOutcome::Recovered,
+            Err((step, _)) => RecoveryOutcome::InitFailed(step),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use embedded_hal_mock::eh1::i2c::Mock;
+
+    use super::*;
+    use crate::asynchronous::embassy::controller::Controller;
+
+    #[tokio::test]
+    async fn test_timeout_counting() {
+        let mut controller: Controller<NoopRawMutex, Mock> =
+            Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            let policy = RecoveryPolicy {
+                timeout_threshold: 3,
+                init_config: InitConfig::default(),
+                on_recovery: None,
+            };
+            assert!(matches!(
+                tps.set_recovery_policy(Some(RecoveryPolicy {
+                    timeout_threshold: 0,
+                    ..policy
+                })),
+                Err(Error::Pd(PdError::InvalidParams))
+            ));
+            tps.set_recovery_policy(Some(policy)).unwrap();
+
+            // Completed commands reset the count
+            let started = tps.command_started().unwrap();
+            tps.command_finished(started, true).await;
+            tps.command_finished(started, true).await;
+            assert_eq!(tps.consecutive_command_timeouts(), 2);
+            tps.command_finished(started, false).await;
+            assert_eq!(tps.consecutive_command_timeouts(), 0);
+
+            // Commands started before a recovery aren't counted
+            tps.controller.recoveries.fetch_add(1, Ordering::SeqCst);
+            tps.command_finished(started, true).await;
+            assert_eq!(tps.consecutive_command_timeouts(), 0);
+
+            // Commands are rejected while recovering
+            tps.controller.recovering.store(true, Ordering::SeqCst);
+            assert!(matches!(tps.command_started(), Err(Error::Pd(PdError::Busy))));
+        }
+
+        // Nothing was sent to the hardware
+        controller.inner.get_mut().bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_recovery() {
+        extern crate std;
+        use std::sync::Mutex;
+        use std::vec::Vec;
+
+        use embedded_hal::i2c::ErrorKind;
+        use embedded_hal_mock::eh1::i2c::Transaction;
+
+        use crate::command::Command;
+        use crate::test::{create_register_read, create_register_write, PORT0_ADDR0};
+        use crate::Mode;
+
+        static OUTCOMES: Mutex<Vec<RecoveryOutcome>> = Mutex::new(Vec::new());
+        fn on_recovery(outcome: RecoveryOutcome) {
+            OUTCOMES.lock().unwrap().push(outcome);
+        }
+
+        let reset = [
+            create_register_write(PORT0_ADDR0, 0x09, [0u8; 2]),
+            create_register_write(PORT0_ADDR0, 0x08, (Command::Gaid as u32).to_le_bytes()),
+        ];
+
+        let mut controller: Controller<NoopRawMutex, Mock> =
+            Controller::new_tps66994(Mock::new(&[]), crate::ADDR0).unwrap();
+
+        {
+            let (mut tps, _interrupt) = controller.make_parts();
+            let shared = tps.controller;
+            tps.set_recovery_policy(Some(RecoveryPolicy {
+                timeout_threshold: 2,
+                init_config: InitConfig::default(),
+                on_recovery: Some(on_recovery),
+            }))
+            .unwrap();
+
+            // Below the threshold nothing is sent
+            let started = tps.command_started().unwrap();
+            tps.command_finished(started, true).await;
+            assert_eq!(tps.recovery_count(), 0);
+            assert!(OUTCOMES.lock().unwrap().is_empty());
+
+            // Reaching it resets the controller and runs the startup sequence
+            let mut transactions = Vec::from(reset.clone());
+            transactions.push(create_register_read(PORT0_ADDR0, 0x03, Mode::App0));
+            transactions.push(create_register_read(PORT0_ADDR0, 0x0F, 0x1234u32.to_le_bytes()));
+            shared.inner.lock().await.bus.update_expectations(&transactions);
+
+            tps.command_finished(started, true).await;
+            shared.inner.lock().await.bus.done();
+            assert_eq!(tps.recovery_count(), 1);
+            assert_eq!(tps.consecutive_command_timeouts(), 0);
+            assert_eq!(OUTCOMES.lock().unwrap().as_slice(), &[RecoveryOutcome::Recovered]);
+
+            // A failing startup step is reported to the hook
+            let mut transactions = Vec::from(reset.clone());
+            transactions.push(create_register_read(PORT0_ADDR0, 0x03, Mode::App0));
+            transactions.push(
+                Transaction::write_read(PORT0_ADDR0, std::vec![0x0F], std::vec![0; 5]).with_error(ErrorKind::Other),
+            );
+            shared.inner.lock().await.bus.update_expectations(&transactions);
+
+            let started = tps.command_started().unwrap();
+            tps.command_finished(started, true).await;
+            tps.command_finished(started, true).await;
+            assert_eq!(tps.recovery_count(), 2);
+            assert_eq!(
+                OUTCOMES.lock().unwrap().as_slice(),
+                &[
+                    RecoveryOutcome::Recovered,
+                    RecoveryOutcome::InitFailed(InitStep::ReadFwVersion)
+                ]
+            );
+        }
+
+        controller.inner.get_mut().bus.done();
+    }
+}
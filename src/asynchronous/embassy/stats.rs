@@ -0,0 +1,57 @@
+//! Interrupt processing statistics, used to profile the interrupt task
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use embassy_time::Duration;
+
+/// Snapshot of interrupt processing statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InterruptStats {
+    /// Number of times `process_interrupt` has run
+    pub passes: u32,
+    /// Duration of the most recent pass in microseconds
+    pub last_duration_us: u32,
+    /// Longest pass in microseconds
+    pub max_duration_us: u32,
+    /// Number of ports that had pending interrupts on the most recent pass
+    pub last_pending_ports: u32,
+}
+
+/// Interrupt statistics shared between the interrupt processor and readers
+pub(super) struct InterruptStatsRecorder {
+    passes: AtomicU32,
+    last_duration_us: AtomicU32,
+    max_duration_us: AtomicU32,
+    last_pending_ports: AtomicU32,
+}
+
+impl InterruptStatsRecorder {
+    pub(super) const fn new() -> Self {
+        Self {
+            passes: AtomicU32::new(0),
+            last_duration_us: AtomicU32::new(0),
+            max_duration_us: AtomicU32::new(0),
+            last_pending_ports: AtomicU32::new(0),
+        }
+    }
+
+    /// Record a single pass over all ports
+    pub(super) fn record(&self, duration: Duration, pending_ports: u32) {
+        let duration_us = u32::try_from(duration.as_micros()).unwrap_or(u32::MAX);
+
+        self.passes.fetch_add(1, Ordering::SeqCst);
+        self.last_duration_us.store(duration_us, Ordering::SeqCst);
+        self.max_duration_us.fetch_max(duration_us, Ordering::SeqCst);
+        self.last_pending_ports.store(pending_ports, Ordering::SeqCst);
+    }
+
+    /// Returns a snapshot of the current statistics
+    pub(super) fn snapshot(&self) -> InterruptStats {
+        InterruptStats {
+            passes: self.passes.load(Ordering::SeqCst),
+            last_duration_us: self.last_duration_us.load(Ordering::SeqCst),
+            max_duration_us: self.max_duration_us.load(Ordering::SeqCst),
+            last_pending_ports: self.last_pending_ports.load(Ordering::SeqCst),
+        }
+    }
+}
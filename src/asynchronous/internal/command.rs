@@ -6,8 +6,9 @@ use embedded_hal_async::delay::DelayNs;
 use embedded_hal_async::i2c::I2c;
 use embedded_usb_pd::{Error, PdError, PortId};
 
-use super::Tps6699x;
+use super::{Tps6699x, MAX_COMMAND_DATA_LEN};
 use crate::command::*;
+use crate::registers::Register;
 use crate::{error, registers as regs, Mode, PORT0};
 
 impl<B: I2c> Tps6699x<B> {
@@ -23,7 +24,7 @@ impl<B: I2c> Tps6699x<B> {
         if let Some(data) = data {
             registers
                 .interface()
-                .write_register(regs::REG_DATA1, (data.len() * 8) as u32, data)
+                .write_register(Register::Data1.addr(), (data.len() * 8) as u32, data)
                 .await?;
         }
 
@@ -42,7 +43,7 @@ impl<B: I2c> Tps6699x<B> {
     ) -> Result<(), Error<B::Error>> {
         self.send_command_unchecked(port, cmd, data).await?;
 
-        delay.delay_us(cmd.valid_check_delay_us()).await;
+        delay.delay_us(self.scale_delay_us(cmd.valid_check_delay_us())).await;
         if Command::Invalid
             == self
                 .borrow_port(port)?
@@ -66,39 +67,119 @@ impl<B: I2c> Tps6699x<B> {
         Ok(Command::Success == status)
     }
 
+    /// Check if a command is in progress, e.g. one sent by another task or host
+    ///
+    /// The command register reads back zero once a command completes and `!CMD` if it wasn't recognized, anything else
+    /// is a command still being processed.
+    pub async fn is_command_busy(&mut self, port: PortId) -> Result<bool, Error<B::Error>> {
+        let mut registers = self.borrow_port(port)?.into_registers();
+        let status = registers.cmd_1().read_async().await?.command();
+
+        Ok(Command::Success != status && Command::Invalid != status)
+    }
+
+    /// Check the event register for a command completion, clearing only the completion event if it's set
+    ///
+    /// For hosts that don't have the interrupt line wired, other pending events are left for the caller to handle.
+    pub async fn poll_command_complete(&mut self, port: PortId) -> Result<bool, Error<B::Error>> {
+        let mut registers = self.borrow_port(port)?.into_registers();
+        if !registers.int_event_bus_1().read_async().await?.cmd_1_completed() {
+            return Ok(false);
+        }
+
+        let mut clear = regs::field_sets::IntEventBus1::new_zero();
+        clear.set_cmd_1_completed(true);
+        registers.int_clear_bus_1().write_async(|r| *r = clear).await?;
+        Ok(true)
+    }
+
     /// Read the result of a command
     pub async fn read_command_result(
         &mut self,
         port: PortId,
         data: Option<&mut [u8]>,
     ) -> Result<ReturnValue, Error<B::Error>> {
+        let data = data.unwrap_or(&mut []);
+        if data.len() > regs::REG_DATA1_LEN - 1 {
+            // Data length too long
+            return PdError::InvalidParams.into();
+        }
+
+        self.read_command_result_sized(port, data).await.map(|(ret, _)| ret)
+    }
+
+    /// Read the result of a command, returns the length of the data the controller reported
+    ///
+    /// The controller frames every register read with a length byte, for the data register it covers the return value
+    /// followed by the data, so the data length is one less. At least [`regs::REG_DATA1_LEN`] bytes are read, more if
+    /// `data` needs them. Only as much of the data as fits is copied into `data`, a reported length larger than
+    /// `data` means the data was truncated. Returns `InvalidParams` if `data` is longer than [`MAX_COMMAND_DATA_LEN`].
+    pub async fn read_command_result_sized(
+        &mut self,
+        port: PortId,
+        data: &mut [u8],
+    ) -> Result<(ReturnValue, usize), Error<B::Error>> {
         if !self.check_command_complete(port).await? {
             return PdError::Busy.into();
         }
 
-        if let Some(ref data) = data {
-            if data.len() > regs::REG_DATA1_LEN - 1 {
-                // Data length too long
-                return PdError::InvalidParams.into();
-            }
+        if data.len() > MAX_COMMAND_DATA_LEN {
+            return PdError::InvalidParams.into();
         }
 
         // Read and return value and data
-        let mut buf = [0u8; regs::REG_DATA1_LEN];
-        self.borrow_port(port)?
-            .into_registers()
-            .interface()
-            .read_register(regs::REG_DATA1, (regs::REG_DATA1_LEN * 8) as u32, &mut buf)
+        let mut buf = [0u8; MAX_COMMAND_DATA_LEN + 1];
+        let read_len = (data.len() + 1).max(regs::REG_DATA1_LEN);
+        let len = self
+            .borrow_port(port)?
+            .read_register_len(Register::Data1.addr(), &mut buf[..read_len])
             .await?;
 
         let ret = ReturnValue::try_from(buf[0]).map_err(Error::Pd)?;
 
-        // Overwrite return value
-        if let Some(data) = data {
-            data.copy_from_slice(&buf[1..=data.len()]);
+        // The length is never zero here, that's reported as busy
+        let data_len = len - 1;
+        let copy_len = data_len.min(data.len());
+        data[..copy_len].copy_from_slice(&buf[1..copy_len + 1]);
+        Ok((ret, data_len))
+    }
+
+    /// Execute a command, polling the command register for completion
+    async fn execute_command_polled(
+        &mut self,
+        delay: &mut impl DelayNs,
+        entry: &BatchEntry<'_>,
+    ) -> Result<(), Error<B::Error>> {
+        self.send_command(delay, entry.port, entry.cmd, entry.data).await?;
+
+        let mut elapsed_ms = 0;
+        while !self.check_command_complete(entry.port).await? {
+            if elapsed_ms >= entry.timeout_ms {
+                error!("Command {:#?} timed out", entry.cmd);
+                return PdError::Timeout.into();
+            }
+
+            delay.delay_ms(BATCH_POLL_DELAY_MS).await;
+            elapsed_ms += BATCH_POLL_DELAY_MS;
         }
 
-        Ok(ret)
+        let ret: Result<(), PdError> = self.read_command_result(entry.port, None).await?.into();
+        ret.map_err(Error::Pd)
+    }
+
+    /// Execute a batch of commands in order, stopping at the first failure
+    ///
+    /// On failure, returns the index of the failing command along with its error.
+    pub async fn execute_batch<const N: usize>(
+        &mut self,
+        delay: &mut impl DelayNs,
+        batch: &CommandBatch<'_, N>,
+    ) -> Result<(), (usize, Error<B::Error>)> {
+        for (i, entry) in batch.entries().iter().enumerate() {
+            self.execute_command_polled(delay, entry).await.map_err(|e| (i, e))?;
+        }
+
+        Ok(())
     }
 
     /// Reset the controller
@@ -159,11 +240,40 @@ impl<B: I2c> Tps6699x<B> {
 
         Ok(())
     }
+
+    /// Restore the controller configuration to the defaults in the application image
+    ///
+    /// Configuration registers are volatile, the controller loads them from the application image on boot and nothing
+    /// written by the host is persisted. This resets the controller to discard host configuration, no further reset is
+    /// needed but any host configuration must be reapplied afterwards. Attached port partners see a disconnect.
+    ///
+    /// Returns `InvalidMode` if the controller isn't running the application firmware, either before or after the reset.
+    pub async fn restore_defaults(&mut self, delay: &mut impl DelayNs) -> Result<(), Error<B::Error>> {
+        let mode = self.get_mode().await?;
+        if mode != Mode::App0 && mode != Mode::App1 {
+            error!("Can't restore defaults outside of app mode, mode: {:?}", mode);
+            return PdError::InvalidMode.into();
+        }
+
+        self.reset(delay, &ResetArgs::default()).await?;
+
+        // Confirm the application firmware came back up
+        let mode = self.get_mode().await?;
+        if mode != Mode::App0 && mode != Mode::App1 {
+            error!(
+                "Failed to return to app mode after restoring defaults, mode: {:?}",
+                mode
+            );
+            return PdError::InvalidMode.into();
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use embedded_hal_mock::eh1::i2c::Mock;
+    use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
     use regs::REG_DATA1;
 
     use crate::asynchronous::internal::Tps6699x;
@@ -242,6 +352,206 @@ mod test {
         .await;
     }
 
+    async fn test_poll_command_complete(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8) {
+        use regs::field_sets::IntEventBus1;
+
+        // Unrelated events are left pending
+        let mut pending = IntEventBus1::new_zero();
+        pending.set_plug_event(true);
+        tps6699x
+            .bus
+            .update_expectations(&[create_register_read(expected_addr, 0x14, pending)]);
+        assert!(!tps6699x.poll_command_complete(PORT0).await.unwrap());
+        tps6699x.bus.done();
+
+        // Only the completion event is cleared
+        let mut completed = pending;
+        completed.set_cmd_1_completed(true);
+        let mut clear = IntEventBus1::new_zero();
+        clear.set_cmd_1_completed(true);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x14, completed));
+        transactions.push(create_register_write(expected_addr, 0x18, clear));
+        tps6699x.bus.update_expectations(&transactions);
+        assert!(tps6699x.poll_command_complete(PORT0).await.unwrap());
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_poll_command_complete_0() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        test_poll_command_complete(&mut tps6699x, PORT0_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_poll_command_complete_1() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR1);
+        test_poll_command_complete(&mut tps6699x, PORT0_ADDR1).await;
+    }
+
+    async fn test_is_command_busy(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8) {
+        for (status, expected) in [
+            (Command::Success as u32, false),
+            (Command::Invalid as u32, false),
+            (Command::Sryr as u32, true),
+        ] {
+            tps6699x
+                .bus
+                .update_expectations(&[create_register_read(expected_addr, 0x08, status.to_le_bytes())]);
+            assert_eq!(tps6699x.is_command_busy(PORT0).await.unwrap(), expected);
+            tps6699x.bus.done();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_is_command_busy_0() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        test_is_command_busy(&mut tps6699x, PORT0_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_is_command_busy_1() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR1);
+        test_is_command_busy(&mut tps6699x, PORT0_ADDR1).await;
+    }
+
+    async fn test_read_command_result_sized(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8) {
+        let mut result = [0u8; regs::REG_DATA1_LEN];
+        result[0] = ReturnValue::Success as u8;
+        result[1..5].copy_from_slice(&[1, 2, 3, 4]);
+
+        // Buffer shorter than the reported data
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x08, [0u8; 4]));
+        transactions.push(create_register_read(expected_addr, REG_DATA1, result));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let mut data = [0u8; 2];
+        let (ret, len) = tps6699x.read_command_result_sized(PORT0, &mut data).await.unwrap();
+        assert_eq!(ret, ReturnValue::Success);
+        assert_eq!(len, regs::REG_DATA1_LEN - 1);
+        assert_eq!(data, [1, 2]);
+        tps6699x.bus.done();
+
+        // Firmware with a larger data register
+        let mut result = [0u8; 101];
+        result[0] = ReturnValue::Success as u8;
+        result[100] = 0xAB;
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x08, [0u8; 4]));
+        transactions.push(create_register_read(expected_addr, REG_DATA1, result));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let mut data = [0u8; 100];
+        let (_, len) = tps6699x.read_command_result_sized(PORT0, &mut data).await.unwrap();
+        assert_eq!(len, 100);
+        assert_eq!(data[99], 0xAB);
+        tps6699x.bus.done();
+
+        // Controller reports less data than was read
+        let mut response = std::vec![0u8; regs::REG_DATA1_LEN + 1];
+        response[0] = 3;
+        response[1] = ReturnValue::Success as u8;
+        response[2..4].copy_from_slice(&[5, 6]);
+        response[4] = 0xFF;
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x08, [0u8; 4]));
+        transactions.push(Transaction::write_read(expected_addr, std::vec![REG_DATA1], response));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let mut data = [0u8; 4];
+        let (_, len) = tps6699x.read_command_result_sized(PORT0, &mut data).await.unwrap();
+        assert_eq!(len, 2);
+        assert_eq!(data, [5, 6, 0, 0]);
+        tps6699x.bus.done();
+
+        // Too long for the length byte
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x08, [0u8; 4]));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let mut data = [0u8; MAX_COMMAND_DATA_LEN + 1];
+        assert!(matches!(
+            tps6699x.read_command_result_sized(PORT0, &mut data).await,
+            Err(Error::Pd(PdError::InvalidParams))
+        ));
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_read_command_result_sized_0() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        test_read_command_result_sized(&mut tps6699x, PORT0_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_read_command_result_sized_1() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR1);
+        test_read_command_result_sized(&mut tps6699x, PORT0_ADDR1).await;
+    }
+
+    /// Create the transactions for a batched command that completes immediately with the given return value
+    fn create_batch_transactions(
+        transactions: &mut Vec<Transaction>,
+        expected_addr: u8,
+        cmd: Command,
+        ret: ReturnValue,
+    ) {
+        let mut result = [0u8; regs::REG_DATA1_LEN];
+        result[0] = ret as u8;
+
+        transactions.push(create_register_write(expected_addr, 0x08, (cmd as u32).to_le_bytes()));
+        // Valid command check
+        transactions.push(create_register_read(expected_addr, 0x08, [0u8; 4]));
+        // Completion poll
+        transactions.push(create_register_read(expected_addr, 0x08, [0u8; 4]));
+        // Completion check and result read
+        transactions.push(create_register_read(expected_addr, 0x08, [0u8; 4]));
+        transactions.push(create_register_read(expected_addr, REG_DATA1, result));
+    }
+
+    async fn test_execute_batch(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8) {
+        let mut delay = Delay {};
+        let mut batch: CommandBatch<'_, 2> = CommandBatch::new();
+        batch
+            .push(PORT0, Command::Sryr, None, SRYR_TIMEOUT_MS)
+            .unwrap()
+            .push(PORT0, Command::Sryr, None, SRYR_TIMEOUT_MS)
+            .unwrap();
+
+        // All commands succeed
+        let mut transactions = Vec::new();
+        create_batch_transactions(&mut transactions, expected_addr, Command::Sryr, ReturnValue::Success);
+        create_batch_transactions(&mut transactions, expected_addr, Command::Sryr, ReturnValue::Success);
+        tps6699x.bus.update_expectations(&transactions);
+
+        tps6699x.execute_batch(&mut delay, &batch).await.unwrap();
+        tps6699x.bus.done();
+
+        // Second command is rejected
+        let mut transactions = Vec::new();
+        create_batch_transactions(&mut transactions, expected_addr, Command::Sryr, ReturnValue::Success);
+        create_batch_transactions(&mut transactions, expected_addr, Command::Sryr, ReturnValue::Rejected);
+        tps6699x.bus.update_expectations(&transactions);
+
+        let result = tps6699x.execute_batch(&mut delay, &batch).await;
+        assert!(matches!(result, Err((1, Error::Pd(PdError::Failed)))));
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_execute_batch_0() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        test_execute_batch(&mut tps6699x, PORT0_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_execute_batch_1() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR1);
+        test_execute_batch(&mut tps6699x, PORT0_ADDR1).await;
+    }
+
     async fn test_reset(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8, expected_args: ResetArgs) {
         let mut delay = Delay {};
         let mut transactions = Vec::new();
@@ -306,7 +616,7 @@ mod test {
         transactions.push(create_register_read(
             expected_addr,
             0x03,
-            (Mode::F211 as u32).to_le_bytes(),
+            u32::from(Mode::F211).to_le_bytes(),
         ));
         tps6699x.bus.update_expectations(&transactions);
 
@@ -344,7 +654,7 @@ mod test {
         transactions.push(create_register_read(
             expected_addr,
             0x03,
-            (Mode::App0 as u32).to_le_bytes(),
+            u32::from(Mode::App0).to_le_bytes(),
         ));
         tps6699x.bus.update_expectations(&transactions);
 
@@ -363,4 +673,53 @@ mod test {
         let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR1);
         test_execute_tfuc(&mut tps6699x, PORT0_ADDR1).await;
     }
+
+    async fn test_restore_defaults(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8) {
+        let mut delay = Delay {};
+        let mut transactions = Vec::new();
+
+        transactions.push(create_register_read(
+            expected_addr,
+            0x03,
+            u32::from(Mode::App1).to_le_bytes(),
+        ));
+        transactions.push(create_register_write(expected_addr, REG_DATA1, [0u8; RESET_ARGS_LEN]));
+        transactions.push(create_register_write(
+            expected_addr,
+            0x08,
+            (Command::Gaid as u32).to_le_bytes(),
+        ));
+        transactions.push(create_register_read(
+            expected_addr,
+            0x03,
+            u32::from(Mode::App0).to_le_bytes(),
+        ));
+        tps6699x.bus.update_expectations(&transactions);
+
+        tps6699x.restore_defaults(&mut delay).await.unwrap();
+        tps6699x.bus.done();
+
+        // No reset outside of app mode
+        tps6699x.bus.update_expectations(&[create_register_read(
+            expected_addr,
+            0x03,
+            u32::from(Mode::F211).to_le_bytes(),
+        )]);
+
+        let result = tps6699x.restore_defaults(&mut delay).await;
+        assert!(matches!(result, Err(Error::Pd(PdError::InvalidMode))));
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_restore_defaults_0() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        test_restore_defaults(&mut tps6699x, PORT0_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_restore_defaults_1() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR1);
+        test_restore_defaults(&mut tps6699x, PORT0_ADDR1).await;
+    }
 }
@@ -1,25 +1,137 @@
 //! Asynchronous, low-level TPS6699x driver. This module provides a low-level interface
+use device_driver::AsyncRegisterInterface;
+use embedded_hal_async::delay::DelayNs;
 use embedded_hal_async::i2c::I2c;
 use embedded_usb_pd::{Error, PdError, PortId};
 
-use crate::registers::{self};
-use crate::{Mode, MAX_SUPPORTED_PORTS, PORT0, PORT1, TPS66993_NUM_PORTS, TPS66994_NUM_PORTS};
+use crate::collections::{BuildDescription, DeviceInfo, SvidList};
+use crate::pdo::{PartnerInfo, PpsRange, Rdo, SourcePdo};
+use crate::registers::boot_flags::FirmwareRegions;
+use crate::registers::cc_status::{CcStatus, Termination};
+use crate::registers::chip_id::ChipId;
+use crate::registers::controller_config::{ControllerConfig, PortSettings};
+use crate::registers::discovered_svids::{DiscoveredSvids, REG_DISCOVERED_SVIDS_LEN};
+use crate::registers::pd_events::{self, PdInterruptEvents};
+use crate::registers::port_config::{PowerRole, RolePolicy, RolePreference};
+use crate::registers::port_control::{self, AutoVdmConfig};
+use crate::registers::power_path::OvercurrentStatus;
+use crate::registers::reset_cause::ResetCause;
+use crate::registers::rx_caps::{self, SourceCapabilities, REG_RX_SINK_CAPS_LEN, REG_RX_SOURCE_CAPS_LEN};
+use crate::registers::status_change::PortSnapshot;
+use crate::registers::telemetry::PortTelemetry;
+use crate::registers::tx_identity::{TxIdentity, REG_TX_IDENTITY_LEN};
+use crate::registers::tx_sink_caps::{SinkCapabilities, REG_TX_SINK_CAPS_LEN};
+use crate::registers::usb_status::UsbStatus;
+use crate::registers::{self, Register};
+use crate::{
+    ascii_to_string, error, trace, Mode, DEFAULT_BUS_SPEED_HZ, MAX_BUS_SPEED_HZ, MAX_SUPPORTED_PORTS, MIN_BUS_SPEED_HZ,
+    PORT0, PORT1, TPS66993_NUM_PORTS, TPS66994_NUM_PORTS,
+};
 
 mod command;
 
+/// Initial delay between mode reads while waiting for app mode
+const APP_MODE_POLL_INITIAL_DELAY_MS: u32 = 10;
+/// Maximum delay between mode reads while waiting for app mode
+const APP_MODE_POLL_MAX_DELAY_MS: u32 = 100;
+
+/// Exponential backoff between reads of a booting controller
+pub(super) struct Backoff {
+    /// Delay before the next read
+    delay_ms: u32,
+    /// Maximum delay between reads
+    max_delay_ms: u32,
+    /// Time left to wait in total, `None` if there's no limit
+    remaining_ms: Option<u32>,
+}
+
+impl Backoff {
+    /// Backoff used while waiting for app mode, bounded by the given timeout
+    pub(super) fn app_mode(timeout_ms: u32) -> Self {
+        Self {
+            delay_ms: APP_MODE_POLL_INITIAL_DELAY_MS,
+            max_delay_ms: APP_MODE_POLL_MAX_DELAY_MS,
+            remaining_ms: Some(timeout_ms),
+        }
+    }
+
+    /// Backoff used between register read retries, doubling from `delay_ms` with no limit on the total
+    pub(super) fn retry(delay_ms: u32) -> Self {
+        Self {
+            delay_ms,
+            max_delay_ms: APP_MODE_POLL_MAX_DELAY_MS.max(delay_ms),
+            remaining_ms: None,
+        }
+    }
+
+    /// Returns the delay before the next read, `None` once the timeout is spent
+    pub(super) fn next_delay_ms(&mut self) -> Option<u32> {
+        let delay_ms = match self.remaining_ms {
+            Some(0) => return None,
+            Some(remaining_ms) => {
+                let delay_ms = self.delay_ms.min(remaining_ms);
+                self.remaining_ms = Some(remaining_ms - delay_ms);
+                delay_ms
+            }
+            None => self.delay_ms,
+        };
+
+        self.delay_ms = self.delay_ms.saturating_mul(2).min(self.max_delay_ms);
+        Some(delay_ms)
+    }
+}
+
+/// Maximum command output data length, the register length byte also covers the return value
+pub const MAX_COMMAND_DATA_LEN: usize = 254;
+
+/// Hook used to translate bus errors before they're returned
+pub type BusErrorHook<E> = fn(E) -> Error<E>;
+
 /// Wrapper to allow implementing device_driver traits on our I2C bus
 pub struct Port<'a, B: I2c> {
     bus: &'a mut B,
     addr: u8,
+    map_bus_error: BusErrorHook<B::Error>,
 }
 
 impl<'a, B: I2c> Port<'a, B> {
     pub fn into_registers(self) -> registers::Registers<Port<'a, B>> {
         registers::Registers::new(self)
     }
+
+    /// Read a register, returns the length the controller reported in the length byte
+    ///
+    /// Unlike [`AsyncRegisterInterface::read_register`], the reported length doesn't have to match `data`. As much of
+    /// the register as fits is copied into `data`, the rest of `data` is left unchanged. Returns `Busy` if the
+    /// controller can't respond.
+    pub async fn read_register_len(&mut self, address: u8, data: &mut [u8]) -> Result<usize, Error<B::Error>> {
+        // Sized to accommodate length byte + up to 255 bytes of data
+        let mut buf = [0u8; 256];
+        let full_len = data.len() + 1;
+        let reg = [address];
+
+        if data.is_empty() || full_len > buf.len() {
+            return Err(PdError::InvalidParams.into());
+        }
+
+        self.bus
+            .write_read(self.addr, &reg, &mut buf[..full_len])
+            .await
+            .map_err(self.map_bus_error)?;
+
+        let len = buf[0] as usize;
+        if len == 0xff || len == 0 {
+            // Controller is busy and can't respond
+            return PdError::Busy.into();
+        }
+
+        let copy_len = len.min(data.len());
+        data[..copy_len].copy_from_slice(&buf[1..copy_len + 1]);
+        Ok(len)
+    }
 }
 
-impl<B: I2c> device_driver::AsyncRegisterInterface for Port<'_, B> {
+impl<B: I2c> AsyncRegisterInterface for Port<'_, B> {
     type Error = Error<B::Error>;
 
     type AddressType = u8;
@@ -45,7 +157,7 @@ impl<B: I2c> device_driver::AsyncRegisterInterface for Port<'_, B> {
         self.bus
             .write(self.addr, &buf[..data.len() + 2])
             .await
-            .map_err(Error::Bus)
+            .map_err(self.map_bus_error)
     }
 
     async fn read_register(
@@ -66,7 +178,7 @@ impl<B: I2c> device_driver::AsyncRegisterInterface for Port<'_, B> {
         self.bus
             .write_read(self.addr, &reg, &mut buf[..full_len])
             .await
-            .map_err(Error::Bus)?;
+            .map_err(self.map_bus_error)?;
 
         let len = buf[0] as usize;
         if len > data.len() {
@@ -81,17 +193,50 @@ impl<B: I2c> device_driver::AsyncRegisterInterface for Port<'_, B> {
     }
 }
 
+/// Validate a mailbox access, returns the exclusive end of the range
+fn mailbox_range_end<BE>(offset: usize, len: usize) -> Result<usize, Error<BE>> {
+    match offset.checked_add(len) {
+        Some(end) if len > 0 && end <= registers::REG_MAILBOX_LEN => Ok(end),
+        _ => PdError::InvalidParams.into(),
+    }
+}
+
 /// Low-level TSP6699x driver, generic over I2C bus (B)
 pub struct Tps6699x<B: I2c> {
     pub(super) bus: B,
     /// I2C addresses for ports
     addr: [u8; MAX_SUPPORTED_PORTS],
     num_ports: usize,
+    /// I2C bus speed hint, used to scale command delays
+    bus_speed_hz: u32,
+    /// Translates bus errors before they're returned
+    map_bus_error: BusErrorHook<B::Error>,
+    /// Ports seen in EPR mode by [`Self::is_epr_keepalive_ok`] since they were last seen detached
+    epr_seen: [bool; MAX_SUPPORTED_PORTS],
 }
 
 impl<B: I2c> Tps6699x<B> {
     pub(super) fn new(bus: B, addr: [u8; MAX_SUPPORTED_PORTS], num_ports: usize) -> Self {
-        Self { bus, addr, num_ports }
+        Self {
+            bus,
+            addr,
+            num_ports,
+            bus_speed_hz: DEFAULT_BUS_SPEED_HZ,
+            map_bus_error: Error::Bus,
+            epr_seen: [false; MAX_SUPPORTED_PORTS],
+        }
+    }
+
+    /// Set a hook to translate bus errors, e.g. to log them or convert arbitration loss into `PdError::Busy`
+    ///
+    /// The default hook wraps the error in `Error::Bus` unchanged.
+    pub fn set_bus_error_hook(&mut self, hook: BusErrorHook<B::Error>) {
+        self.map_bus_error = hook;
+    }
+
+    /// Translate a bus error with the configured hook
+    pub(crate) fn map_bus_error(&self, error: B::Error) -> Error<B::Error> {
+        (self.map_bus_error)(error)
     }
 
     pub fn new_tps66993(bus: B, addr: u8) -> Self {
@@ -116,12 +261,37 @@ impl<B: I2c> Tps6699x<B> {
         self.num_ports
     }
 
+    /// Returns the I2C bus speed hint
+    pub fn bus_speed_hz(&self) -> u32 {
+        self.bus_speed_hz
+    }
+
+    /// Set the I2C bus speed hint
+    ///
+    /// Delays between sending a command and checking its result are scaled relative to [`DEFAULT_BUS_SPEED_HZ`].
+    /// Speeds outside of [`MIN_BUS_SPEED_HZ`] and [`MAX_BUS_SPEED_HZ`] are clamped when scaling.
+    pub fn set_bus_speed_hz(&mut self, bus_speed_hz: u32) -> Result<(), Error<B::Error>> {
+        if bus_speed_hz == 0 {
+            return PdError::InvalidParams.into();
+        }
+
+        self.bus_speed_hz = bus_speed_hz;
+        Ok(())
+    }
+
+    /// Scale a delay tuned for the default bus speed to the configured bus speed
+    fn scale_delay_us(&self, delay_us: u32) -> u32 {
+        let bus_speed_hz = self.bus_speed_hz.clamp(MIN_BUS_SPEED_HZ, MAX_BUS_SPEED_HZ) as u64;
+        ((delay_us as u64 * DEFAULT_BUS_SPEED_HZ as u64) / bus_speed_hz) as u32
+    }
+
     /// Borrows the given port, providing exclusive access to it and therefore the underlying bus object
     pub fn borrow_port(&mut self, port: PortId) -> Result<Port<'_, B>, Error<B::Error>> {
         let addr = self.port_addr(port)?;
         Ok(Port {
             bus: &mut self.bus,
             addr,
+            map_bus_error: self.map_bus_error,
         })
     }
 
@@ -142,11 +312,152 @@ impl<B: I2c> Tps6699x<B> {
         Ok(flags)
     }
 
+    /// Clear interrupts on a port, returns asserted interrupts along with any that are still pending afterwards
+    ///
+    /// Interrupts still pending after the clear either failed to clear or were re-asserted by a continuous event source.
+    pub async fn clear_interrupt_verified(
+        &mut self,
+        port: PortId,
+    ) -> Result<(registers::field_sets::IntEventBus1, registers::field_sets::IntEventBus1), Error<B::Error>> {
+        let flags = self.clear_interrupt(port).await?;
+        let pending = self.peek_interrupt(port).await?;
+        Ok((flags, pending))
+    }
+
+    /// Read pending interrupts on a port without clearing them
+    pub async fn peek_interrupt(
+        &mut self,
+        port: PortId,
+    ) -> Result<registers::field_sets::IntEventBus1, Error<B::Error>> {
+        self.borrow_port(port)?
+            .into_registers()
+            .int_event_bus_1()
+            .read_async()
+            .await
+    }
+
+    /// Read pending interrupts on all ports without clearing them and decode them, see [`pd_events::decode`]
+    ///
+    /// The port status is also read for ports where decoding depends on it. Entries for ports that aren't present are
+    /// empty. Nothing is diffed against a previous status, so status change events aren't reported.
+    pub async fn interrupt_report(&mut self) -> Result<[PdInterruptEvents; MAX_SUPPORTED_PORTS], Error<B::Error>> {
+        let mut report = [const { PdInterruptEvents::new() }; MAX_SUPPORTED_PORTS];
+        for (port, events) in report.iter_mut().enumerate().take(self.num_ports()) {
+            let port = PortId(port as u8);
+            let flags = self.peek_interrupt(port).await?;
+            let status = if pd_events::needs_status(&flags) {
+                self.get_port_status(port).await?
+            } else {
+                registers::field_sets::Status::new_zero()
+            };
+            *events = pd_events::decode(&flags, &status);
+        }
+
+        Ok(report)
+    }
+
+    /// Get the interrupt mask, events set in the mask assert the interrupt line and wake the host
+    pub async fn get_interrupt_mask(
+        &mut self,
+        port: PortId,
+    ) -> Result<registers::field_sets::IntEventBus1, Error<B::Error>> {
+        self.borrow_port(port)?
+            .into_registers()
+            .int_mask_bus_1()
+            .read_async()
+            .await
+    }
+
+    /// Read-modify-write the interrupt mask, returns the new mask
+    ///
+    /// The controller handles attach, detach, and contract negotiation autonomously, only events in the mask wake the
+    /// host. The reset mask enables plug, contract, and command completion events among others. Command completion
+    /// must remain enabled, returns `InvalidParams` otherwise.
+    pub async fn modify_interrupt_mask(
+        &mut self,
+        port: PortId,
+        f: impl FnOnce(&mut registers::field_sets::IntEventBus1),
+    ) -> Result<registers::field_sets::IntEventBus1, Error<B::Error>> {
+        let mut mask = self.get_interrupt_mask(port).await?;
+        f(&mut mask);
+
+        if !mask.cmd_1_completed() {
+            return PdError::InvalidParams.into();
+        }
+
+        self.set_interrupt_mask(port, mask).await?;
+        Ok(mask)
+    }
+
+    /// Set the interrupt mask without validation
+    ///
+    /// Unlike [`Self::modify_interrupt_mask`] this allows masking command completion, command execution then relies on
+    /// polling the command register.
+    pub async fn set_interrupt_mask(
+        &mut self,
+        port: PortId,
+        mask: registers::field_sets::IntEventBus1,
+    ) -> Result<(), Error<B::Error>> {
+        self.borrow_port(port)?
+            .into_registers()
+            .int_mask_bus_1()
+            .write_async(|r| *r = mask)
+            .await
+    }
+
     /// Get port status
     pub async fn get_port_status(&mut self, port: PortId) -> Result<registers::field_sets::Status, Error<B::Error>> {
         self.borrow_port(port)?.into_registers().status().read_async().await
     }
 
+    /// Returns true if the port booted from a dead battery and is sinking to power the system
+    pub async fn is_dead_battery(&mut self, port: PortId) -> Result<bool, Error<B::Error>> {
+        Ok(self.get_port_status(port).await?.legacy() == registers::LegacyMode::LegacySinkDeadBattery)
+    }
+
+    /// Returns whether the port is still in EPR mode, `None` if it hasn't been in EPR mode since it attached
+    ///
+    /// The controller firmware sends EPR keepalive messages autonomously while in an EPR contract, there's nothing for the
+    /// host to configure or schedule on any TPS6699x SKU. A missed keepalive makes the source exit EPR, after which the
+    /// port renegotiates an SPR contract of at most 100 W. Poll this, or check it on a contract change, to detect that.
+    ///
+    /// The status register only reports whether EPR mode is active, so whether the port was in EPR mode is tracked by
+    /// this function: `Some(true)` while in EPR mode, `Some(false)` once it has left EPR mode while still attached, and
+    /// `None` when detached or before EPR mode is first seen. A detach and reattach between calls isn't seen, so poll
+    /// at least as often as the port can reattach.
+    pub async fn is_epr_keepalive_ok(&mut self, port: PortId) -> Result<Option<bool>, Error<B::Error>> {
+        let status = self.get_port_status(port).await?;
+        let seen = &mut self.epr_seen[port.0 as usize];
+        if !status.plug_present() {
+            *seen = false;
+            return Ok(None);
+        }
+
+        *seen |= status.erp_mode();
+        Ok(seen.then_some(status.erp_mode()))
+    }
+
+    /// Returns true if VBUS is present on the port, whether or not there's a PD contract
+    ///
+    /// Derived from the status register's VBUS status, VBUS is present unless it's within the vSafe0V range of at most
+    /// 0.8 V.
+    pub async fn is_vbus_present(&mut self, port: PortId) -> Result<bool, Error<B::Error>> {
+        Ok(self.get_port_status(port).await?.vbus_status() != registers::VbusMode::AtVsafe0)
+    }
+
+    /// Get CC line status
+    pub async fn get_cc_status(&mut self, port: PortId) -> Result<CcStatus, Error<B::Error>> {
+        Ok(self.get_port_status(port).await?.into())
+    }
+
+    /// Get the CC termination presented by the port
+    pub async fn get_termination(&mut self, port: PortId) -> Result<Termination, Error<B::Error>> {
+        let status = self.get_port_status(port).await?;
+        let config = self.get_port_config(port).await?;
+        let control = self.get_port_control(port).await?;
+        Termination::new(&status, &config, &control).map_err(Error::Pd)
+    }
+
     /// Get active PDO contract
     pub async fn get_active_pdo_contract(
         &mut self,
@@ -171,12 +482,172 @@ impl<B: I2c> Tps6699x<B> {
             .await
     }
 
+    /// Get the capabilities advertised by the source partner, `None` unless there is a contract with the port as sink
+    pub async fn get_partner_info(&mut self, port: PortId) -> Result<Option<PartnerInfo>, Error<B::Error>> {
+        let status = self.get_port_status(port).await?;
+        let contract = self.get_active_pdo_contract(port).await?;
+        Ok(PartnerInfo::new(&status, &contract))
+    }
+
+    /// Get the raw PDO of the active contract, for forwarding to external PD decoders
+    pub async fn get_active_pdo_raw(&mut self, port: PortId) -> Result<u32, Error<B::Error>> {
+        Ok(self.get_active_pdo_contract(port).await?.active_pdo())
+    }
+
+    /// Get the raw RDO of the active contract, for forwarding to external PD decoders
+    pub async fn get_active_rdo_raw(&mut self, port: PortId) -> Result<u32, Error<B::Error>> {
+        Ok(self.get_active_rdo_contract(port).await?.active_rdo())
+    }
+
+    /// Get the position of the PDO selected by the active contract, starting at 1. Zero if there is no contract.
+    pub async fn get_selected_pdo_index(&mut self, port: PortId) -> Result<u8, Error<B::Error>> {
+        let contract = self.get_active_rdo_contract(port).await?;
+        Ok(Rdo(contract.active_rdo()).object_position())
+    }
+
+    /// Get the operating current in mA of the active contract, reads the active PDO and RDO contract registers
+    ///
+    /// Returns 0 if there is no contract or the contract isn't for a fixed or variable supply.
+    pub async fn get_contract_current_ma(&mut self, port: PortId) -> Result<u16, Error<B::Error>> {
+        let pdo = self.get_active_pdo_raw(port).await?;
+        let rdo = Rdo(self.get_active_rdo_raw(port).await?);
+        Ok(rdo.operating_current_ma(pdo).unwrap_or(0))
+    }
+
+    /// Get how much more current in mA could be drawn under the active contract, reads the active PDO and RDO contract
+    /// registers
+    ///
+    /// The headroom is the maximum current of the contracted PDO minus the operating current requested in the RDO.
+    /// There's no VBUS current measurement in the register map, so this is the headroom of the contract rather than of
+    /// the present draw. Returns 0 if there is no contract or the contract isn't for a fixed or variable supply.
+    pub async fn get_available_headroom_ma(&mut self, port: PortId) -> Result<u16, Error<B::Error>> {
+        let pdo = self.get_active_pdo_raw(port).await?;
+        let rdo = Rdo(self.get_active_rdo_raw(port).await?);
+        let max_current_ma = match SourcePdo::try_from(pdo) {
+            Ok(SourcePdo::Fixed { max_current_ma, .. }) | Ok(SourcePdo::Variable { max_current_ma, .. }) => {
+                max_current_ma
+            }
+            _ => 0,
+        };
+
+        Ok(rdo.operating_current_ma(pdo).map_or(0, |operating_current_ma| {
+            max_current_ma.saturating_sub(operating_current_ma)
+        }))
+    }
+
+    /// Get the output range of the active PPS contract, returns `InvalidMode` if the contract isn't for a PPS
+    pub async fn get_pps_range(&mut self, port: PortId) -> Result<PpsRange, Error<B::Error>> {
+        let pdo = self.get_active_pdo_raw(port).await?;
+        PpsRange::from_apdo(pdo).ok_or(Error::Pd(PdError::InvalidMode))
+    }
+
+    /// Returns true if the active request couldn't be satisfied by any source PDO, false if there is no contract
+    pub async fn has_capability_mismatch(&mut self, port: PortId) -> Result<bool, Error<B::Error>> {
+        Ok(Rdo(self.get_active_rdo_raw(port).await?).capability_mismatch())
+    }
+
+    /// Get port telemetry, reads the port status and active PDO and RDO contract registers
+    pub async fn get_telemetry(&mut self, port: PortId) -> Result<PortTelemetry, Error<B::Error>> {
+        let status = self.get_port_status(port).await?;
+        let pdo_contract = self.get_active_pdo_contract(port).await?;
+        let rdo_contract = self.get_active_rdo_contract(port).await?;
+        Ok(PortTelemetry::new(status, &pdo_contract, &rdo_contract))
+    }
+
+    /// Get the port status and active contract compared by [`crate::registers::status_change::diff`]
+    pub async fn get_port_snapshot(&mut self, port: PortId) -> Result<PortSnapshot, Error<B::Error>> {
+        let status = self.get_port_status(port).await?;
+        let active_rdo = self.get_active_rdo_raw(port).await?;
+        Ok(PortSnapshot { status, active_rdo })
+    }
+
     /// Get controller operation mode
     pub async fn get_mode(&mut self) -> Result<Mode, Error<B::Error>> {
         // This is a controller-level command, shouldn't matter which port we use
         let mode = self.borrow_port(PORT0)?.into_registers().mode().read_async().await?;
-        let mode = Mode::try_from(mode.mode()).map_err(Error::Pd)?;
-        Ok(mode)
+        Ok(Mode::from_raw(mode.mode()))
+    }
+
+    /// Wait for the controller to reach app mode, polling the mode register with exponential backoff
+    ///
+    /// Errors are expected while the controller boots and are treated as transient. The timeout only accounts for
+    /// time spent waiting between reads. See [`crate::TYPICAL_BOOT_TIME_MS`] for typical boot times.
+    pub async fn wait_for_app_mode(
+        &mut self,
+        delay: &mut impl DelayNs,
+        timeout_ms: u32,
+    ) -> Result<(), Error<B::Error>> {
+        let mut backoff = Backoff::app_mode(timeout_ms);
+        while !self.is_app_mode().await {
+            let Some(wait_ms) = backoff.next_delay_ms() else {
+                return PdError::Timeout.into();
+            };
+
+            delay.delay_ms(wait_ms).await;
+        }
+
+        Ok(())
+    }
+
+    /// Read the mode once, returns true if the controller is in app mode and treats errors as transient
+    pub(super) async fn is_app_mode(&mut self) -> bool {
+        match self.get_mode().await {
+            Ok(Mode::App0) | Ok(Mode::App1) => true,
+            Ok(mode) => {
+                trace!("Waiting for app mode, mode: {:?}", mode);
+                false
+            }
+            Err(_) => {
+                trace!("Waiting for app mode, failed to read mode");
+                false
+            }
+        }
+    }
+
+    /// Read a register, retrying through the window where the controller is rebooting
+    ///
+    /// Reads `buf.len()` bytes. While the controller boots it NAKs or reports itself busy, so every read error is
+    /// treated as transient and the last one is returned once `max_attempts` reads have failed. The delay between
+    /// reads starts at `delay_ms` and doubles up to 100 ms or `delay_ms`, whichever is larger. See
+    /// [`crate::BOOT_READ_RETRY_ATTEMPTS`] and [`crate::BOOT_READ_RETRY_DELAY_MS`] for recommended values.
+    pub async fn read_register_retry(
+        &mut self,
+        port: PortId,
+        reg: Register,
+        buf: &mut [u8],
+        delay: &mut impl DelayNs,
+        max_attempts: u32,
+        delay_ms: u32,
+    ) -> Result<(), Error<B::Error>> {
+        if max_attempts == 0 || buf.is_empty() {
+            return PdError::InvalidParams.into();
+        }
+
+        let mut backoff = Backoff::retry(delay_ms);
+        let mut attempt = 1;
+        loop {
+            match self.read_register_raw(port, reg, buf).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= max_attempts => return Err(e),
+                Err(_) => trace!("Register read failed, attempt {}", attempt),
+            }
+
+            // Can't fail, retries have no limit on the total delay
+            delay.delay_ms(backoff.next_delay_ms().unwrap_or(delay_ms)).await;
+            attempt += 1;
+        }
+    }
+
+    /// Read `buf.len()` bytes of a register once
+    pub(super) async fn read_register_raw(
+        &mut self,
+        port: PortId,
+        reg: Register,
+        buf: &mut [u8],
+    ) -> Result<(), Error<B::Error>> {
+        self.borrow_port(port)?
+            .read_register(reg.addr(), (buf.len() * 8) as u32, buf)
+            .await
     }
 
     /// Get FW version
@@ -190,6 +661,23 @@ impl<B: I2c> Tps6699x<B> {
             .map(|r| r.version())
     }
 
+    /// Get boot flags
+    pub async fn get_boot_flags(&mut self) -> Result<registers::field_sets::BootFlags, Error<B::Error>> {
+        // This is a controller-level register, shouldn't matter which port we use
+        self.borrow_port(PORT0)?
+            .into_registers()
+            .boot_flags()
+            .read_async()
+            .await
+    }
+
+    /// Get the executing and staged firmware regions, see [`FirmwareRegions`] for the region layout
+    pub async fn get_firmware_regions(&mut self) -> Result<FirmwareRegions, Error<B::Error>> {
+        let flags = self.get_boot_flags().await?;
+        let version = self.get_fw_version().await?;
+        Ok(FirmwareRegions::new(&flags, version))
+    }
+
     /// Get customer use value
     pub async fn get_customer_use(&mut self) -> Result<u64, Error<B::Error>> {
         // This is a controller-level command, shouldn't matter which port we use
@@ -201,6 +689,86 @@ impl<B: I2c> Tps6699x<B> {
             .map(|r| r.customer_use())
     }
 
+    /// Get the unique ID of the controller, bytes are in register order, least-significant byte first
+    pub async fn get_uid(&mut self) -> Result<[u8; registers::REG_UID_LEN], Error<B::Error>> {
+        let mut buf = [0u8; registers::REG_UID_LEN];
+        // This is a controller-level register, shouldn't matter which port we use
+        self.borrow_port(PORT0)?
+            .read_register(Register::Uid.addr(), (buf.len() * 8) as u32, &mut buf)
+            .await?;
+        Ok(buf)
+    }
+
+    /// Read `buf.len()` bytes from the mailbox starting at `offset`
+    ///
+    /// The mailbox contents are application-defined, the driver only moves bytes. Returns `InvalidParams` if the range
+    /// doesn't fit in the mailbox.
+    pub async fn read_mailbox(&mut self, port: PortId, offset: usize, buf: &mut [u8]) -> Result<(), Error<B::Error>> {
+        let end = mailbox_range_end(offset, buf.len())?;
+
+        let mut mailbox = [0u8; registers::REG_MAILBOX_LEN];
+        self.borrow_port(port)?
+            .read_register(Register::Mailbox.addr(), (mailbox.len() * 8) as u32, &mut mailbox)
+            .await?;
+
+        buf.copy_from_slice(&mailbox[offset..end]);
+        Ok(())
+    }
+
+    /// Write `data` to the mailbox starting at `offset`, leaving the rest of the mailbox unchanged
+    ///
+    /// The mailbox contents are application-defined, the driver only moves bytes. Returns `InvalidParams` if the range
+    /// doesn't fit in the mailbox.
+    pub async fn write_mailbox(&mut self, port: PortId, offset: usize, data: &[u8]) -> Result<(), Error<B::Error>> {
+        let end = mailbox_range_end(offset, data.len())?;
+
+        let mut mailbox = [0u8; registers::REG_MAILBOX_LEN];
+        let mut port = self.borrow_port(port)?;
+        port.read_register(Register::Mailbox.addr(), (mailbox.len() * 8) as u32, &mut mailbox)
+            .await?;
+
+        mailbox[offset..end].copy_from_slice(data);
+        port.write_register(Register::Mailbox.addr(), (mailbox.len() * 8) as u32, &mailbox)
+            .await
+    }
+
+    /// Get the raw build description bytes
+    pub async fn get_build_description_raw(
+        &mut self,
+    ) -> Result<[u8; registers::REG_BUILD_DESCRIPTION_LEN], Error<B::Error>> {
+        let mut buf = [0u8; registers::REG_BUILD_DESCRIPTION_LEN];
+        // This is a controller-level register, shouldn't matter which port we use
+        self.borrow_port(PORT0)?
+            .read_register(Register::BuildDescription.addr(), (buf.len() * 8) as u32, &mut buf)
+            .await?;
+        Ok(buf)
+    }
+
+    /// Get the build description as a printable string
+    pub async fn get_build_description(&mut self) -> Result<BuildDescription, Error<B::Error>> {
+        Ok(ascii_to_string(&self.get_build_description_raw().await?))
+    }
+
+    /// Get the raw device info bytes
+    pub async fn get_device_info_raw(&mut self) -> Result<[u8; registers::REG_DEVICE_INFO_LEN], Error<B::Error>> {
+        let mut buf = [0u8; registers::REG_DEVICE_INFO_LEN];
+        // This is a controller-level register, shouldn't matter which port we use
+        self.borrow_port(PORT0)?
+            .read_register(Register::DeviceInfo.addr(), (buf.len() * 8) as u32, &mut buf)
+            .await?;
+        Ok(buf)
+    }
+
+    /// Get the device info as a printable string
+    pub async fn get_device_info(&mut self) -> Result<DeviceInfo, Error<B::Error>> {
+        Ok(ascii_to_string(&self.get_device_info_raw().await?))
+    }
+
+    /// Get the part number and silicon revision from the device info
+    pub async fn get_chip_id(&mut self) -> Result<ChipId, Error<B::Error>> {
+        Ok(ChipId::new(&self.get_device_info().await?))
+    }
+
     /// Get power path status
     pub async fn get_power_path_status(
         &mut self,
@@ -214,91 +782,519 @@ impl<B: I2c> Tps6699x<B> {
             .await
     }
 
+    /// Get overcurrent faults on a port's power paths
+    ///
+    /// Only the VCONN trip point is configurable, see [`Self::set_vconn_current_limit_ma`]. The VBUS trip point isn't
+    /// exposed through the host interface.
+    pub async fn get_overcurrent_status(&mut self, port: PortId) -> Result<OvercurrentStatus, Error<B::Error>> {
+        let status = self.get_power_path_status(port).await?;
+        OvercurrentStatus::new(&status, port).map_err(Error::Pd)
+    }
+
     /// Get PD status
     pub async fn get_pd_status(&mut self, port: PortId) -> Result<registers::field_sets::PdStatus, Error<B::Error>> {
         self.borrow_port(port)?.into_registers().pd_status().read_async().await
     }
 
-    /// Get port control
-    pub async fn get_port_control(
-        &mut self,
-        port: PortId,
-    ) -> Result<registers::field_sets::PortControl, Error<B::Error>> {
-        self.borrow_port(port)?
-            .into_registers()
-            .port_control()
-            .read_async()
-            .await
+    /// Get the most severe reset recorded in PD status
+    ///
+    /// Error recovery takes priority over a hard reset, then a data reset, then a soft reset. PD status doesn't record
+    /// which reset happened last, so this isn't necessarily the most recent one.
+    pub async fn get_last_reset_cause(&mut self, port: PortId) -> Result<ResetCause, Error<B::Error>> {
+        let status = self.get_pd_status(port).await?;
+        Ok(ResetCause::new(&status))
     }
 
-    /// Set port control
-    pub async fn set_port_control(
+    /// Get data status
+    pub async fn get_data_status(
         &mut self,
         port: PortId,
-        control: registers::field_sets::PortControl,
-    ) -> Result<(), Error<B::Error>> {
+    ) -> Result<registers::field_sets::DataStatus, Error<B::Error>> {
         self.borrow_port(port)?
             .into_registers()
-            .port_control()
-            .write_async(|r| *r = control)
-            .await
-    }
-
-    /// Get global system config
-    pub async fn get_system_config(&mut self) -> Result<registers::field_sets::SystemConfig, Error<B::Error>> {
-        // This is a controller-level command, shouldn't matter which port we use
-        self.borrow_port(PORT0)?
-            .into_registers()
-            .system_config()
+            .data_status()
             .read_async()
             .await
     }
 
-    /// Set global system config
-    pub async fn set_system_config(
-        &mut self,
-        config: registers::field_sets::SystemConfig,
-    ) -> Result<(), Error<B::Error>> {
-        // This is a controller-level command, shouldn't matter which port we use
-        self.borrow_port(PORT0)?
-            .into_registers()
-            .system_config()
-            .write_async(|r| *r = config)
-            .await
+    /// Get USB status, decoded from the port status and data status registers
+    pub async fn get_usb_status(&mut self, port: PortId) -> Result<UsbStatus, Error<B::Error>> {
+        let status = self.get_port_status(port).await?;
+        let data_status = self.get_data_status(port).await?;
+        Ok(UsbStatus::new(&status, &data_status))
     }
 
-    /// Enable/disable sourcing on a given port
-    pub async fn enable_source(&mut self, port: PortId, enable: bool) -> Result<(), Error<B::Error>> {
-        let mut config = self.get_system_config().await?;
+    /// Get SVIDs discovered from the port partner and cable
+    pub async fn get_discovered_svids_full(&mut self, port: PortId) -> Result<DiscoveredSvids, Error<B::Error>> {
+        let mut buf = [0u8; REG_DISCOVERED_SVIDS_LEN];
+        self.borrow_port(port)?
+            .read_register(Register::DiscoveredSvids.addr(), (buf.len() * 8) as u32, &mut buf)
+            .await?;
 
-        let enable = if enable {
-            registers::VbusSwConfig::Source
-        } else {
-            registers::VbusSwConfig::Disabled
-        };
-        match port {
-            PORT0 => config.set_pa_pp_5_v_vbus_sw_config(enable),
-            PORT1 => config.set_pb_pp_5_v_vbus_sw_config(enable),
-            _ => return PdError::InvalidPort.into(),
+        let (svids, _) = bincode::decode_from_slice(&buf, bincode::config::standard().with_fixed_int_encoding())
+            .map_err(|_| Error::Pd(PdError::Serialize))?;
+        Ok(svids)
+    }
+
+    /// Get SVIDs discovered from the port partner
+    ///
+    /// Returns [`PdError::Busy`] if discovery hasn't completed yet
+    pub async fn get_discovered_svids(&mut self, port: PortId) -> Result<SvidList, Error<B::Error>> {
+        let svids = self.get_discovered_svids_full(port).await?;
+        if !svids.sop_complete {
+            return PdError::Busy.into();
         }
 
-        self.set_system_config(config).await?;
-        Ok(())
+        Ok(svids.sop)
     }
-}
 
-#[cfg(test)]
-mod test {
-    extern crate std;
-    use std::vec::Vec;
+    /// Get the source capabilities most recently received from the port partner
+    pub async fn get_rx_source_caps(&mut self, port: PortId) -> Result<SourceCapabilities, Error<B::Error>> {
+        let mut buf = [0u8; REG_RX_SOURCE_CAPS_LEN];
+        self.borrow_port(port)?
+            .read_register(Register::RxSourceCaps.addr(), (buf.len() * 8) as u32, &mut buf)
+            .await?;
+
+        let (caps, _) = bincode::decode_from_slice(&buf, bincode::config::standard().with_fixed_int_encoding())
+            .map_err(|_| Error::Pd(PdError::Serialize))?;
+        Ok(caps)
+    }
+
+    /// Get the number of source PDOs advertised by the port partner without decoding them
+    ///
+    /// Zero if nothing is attached or the partner isn't PD-capable, e.g. a Type-C only source.
+    pub async fn get_source_pdo_count(&mut self, port: PortId) -> Result<u8, Error<B::Error>> {
+        // Received capabilities aren't cleared on detach
+        if !self.get_port_status(port).await?.plug_present() {
+            return Ok(0);
+        }
+
+        let mut buf = [0u8; REG_RX_SOURCE_CAPS_LEN];
+        self.borrow_port(port)?
+            .read_register(Register::RxSourceCaps.addr(), (buf.len() * 8) as u32, &mut buf)
+            .await?;
+        Ok(rx_caps::pdo_count(&buf))
+    }
+
+    /// Get the sink capabilities most recently received from the port partner
+    pub async fn get_rx_sink_caps(&mut self, port: PortId) -> Result<SinkCapabilities, Error<B::Error>> {
+        let mut buf = [0u8; REG_RX_SINK_CAPS_LEN];
+        self.borrow_port(port)?
+            .read_register(Register::RxSinkCaps.addr(), (buf.len() * 8) as u32, &mut buf)
+            .await?;
+
+        let (caps, _) = bincode::decode_from_slice(&buf, bincode::config::standard().with_fixed_int_encoding())
+            .map_err(|_| Error::Pd(PdError::Serialize))?;
+        Ok(caps)
+    }
+
+    /// Get the sink capabilities advertised to the port partner
+    pub async fn get_tx_sink_caps(&mut self, port: PortId) -> Result<SinkCapabilities, Error<B::Error>> {
+        let mut buf = [0u8; REG_TX_SINK_CAPS_LEN];
+        self.borrow_port(port)?
+            .read_register(Register::TxSinkCaps.addr(), (buf.len() * 8) as u32, &mut buf)
+            .await?;
+
+        let (caps, _) = bincode::decode_from_slice(&buf, bincode::config::standard().with_fixed_int_encoding())
+            .map_err(|_| Error::Pd(PdError::Serialize))?;
+        Ok(caps)
+    }
+
+    /// Set the sink capabilities advertised to the port partner
+    pub async fn set_tx_sink_caps(&mut self, port: PortId, caps: &SinkCapabilities) -> Result<(), Error<B::Error>> {
+        let mut buf = [0u8; REG_TX_SINK_CAPS_LEN];
+        bincode::encode_into_slice(caps, &mut buf, bincode::config::standard().with_fixed_int_encoding())
+            .map_err(|_| Error::Pd(PdError::Serialize))?;
+
+        self.borrow_port(port)?
+            .write_register(Register::TxSinkCaps.addr(), (buf.len() * 8) as u32, &buf)
+            .await
+    }
+
+    /// Set the sink policy as `(voltage_mv, max_current_ma)` entries
+    ///
+    /// The policy is programmed as the sink capabilities used by the controller when auto-negotiating. While a source
+    /// is attached, the best matching PDO of its received source capabilities is selected and only it and vSafe5V are
+    /// programmed, so the controller requests that PDO, see [`SinkCapabilities::best_for`] for the selection. The
+    /// selection stays in place until the policy is set again, call this again after attaching to a different source.
+    /// Without an attached source every entry is programmed and the controller requests the highest power PDO at a
+    /// policy voltage. In both cases the controller falls back to vSafe5V if no policy voltage is offered, see
+    /// [`SinkCapabilities::from_policy`]. Takes effect on the next negotiation.
+    pub async fn set_sink_policy(&mut self, port: PortId, policy: &[(u16, u16)]) -> Result<(), Error<B::Error>> {
+        let mut caps = SinkCapabilities::from_policy(policy).map_err(Error::Pd)?;
+
+        // Received capabilities are stale once detached
+        if self.get_port_status(port).await?.plug_present() {
+            let source = self.get_rx_source_caps(port).await?;
+            if !source.pdos().is_empty() {
+                caps = caps.best_for(&source).map_err(Error::Pd)?;
+            }
+        }
+
+        self.set_tx_sink_caps(port, &caps).await
+    }
+
+    /// Get the response to Discover Identity
+    pub async fn get_tx_identity(&mut self, port: PortId) -> Result<TxIdentity, Error<B::Error>> {
+        let mut buf = [0u8; REG_TX_IDENTITY_LEN];
+        self.borrow_port(port)?
+            .read_register(Register::TxIdentity.addr(), (buf.len() * 8) as u32, &mut buf)
+            .await?;
+
+        let (identity, _) = bincode::decode_from_slice(&buf, bincode::config::standard().with_fixed_int_encoding())
+            .map_err(|_| Error::Pd(PdError::Serialize))?;
+        Ok(identity)
+    }
+
+    /// Set the response to Discover Identity
+    ///
+    /// By default the controller ACKs with the identity from the application configuration, this overrides it until
+    /// the next reset. Takes effect on the next Discover Identity request from the partner.
+    pub async fn set_tx_identity(&mut self, port: PortId, identity: &TxIdentity) -> Result<(), Error<B::Error>> {
+        let mut buf = [0u8; REG_TX_IDENTITY_LEN];
+        bincode::encode_into_slice(
+            identity,
+            &mut buf,
+            bincode::config::standard().with_fixed_int_encoding(),
+        )
+        .map_err(|_| Error::Pd(PdError::Serialize))?;
+
+        self.borrow_port(port)?
+            .write_register(Register::TxIdentity.addr(), (buf.len() * 8) as u32, &buf)
+            .await
+    }
+
+    /// Get port config
+    pub async fn get_port_config(
+        &mut self,
+        port: PortId,
+    ) -> Result<registers::field_sets::PortConfig, Error<B::Error>> {
+        self.borrow_port(port)?
+            .into_registers()
+            .port_config()
+            .read_async()
+            .await
+    }
+
+    /// Set port config
+    pub async fn set_port_config(
+        &mut self,
+        port: PortId,
+        config: registers::field_sets::PortConfig,
+    ) -> Result<(), Error<B::Error>> {
+        self.borrow_port(port)?
+            .into_registers()
+            .port_config()
+            .write_async(|r| *r = config)
+            .await
+    }
+
+    /// Get dual-role port preference
+    pub async fn get_role_preference(&mut self, port: PortId) -> Result<RolePreference, Error<B::Error>> {
+        let config = self.get_port_config(port).await?;
+        config.typec_support_options().try_into().map_err(Error::Pd)
+    }
+
+    /// Set dual-role port preference, takes effect on the next attach
+    pub async fn set_role_preference(
+        &mut self,
+        port: PortId,
+        preference: RolePreference,
+    ) -> Result<(), Error<B::Error>> {
+        let mut config = self.get_port_config(port).await?;
+        config.set_typec_support_options(preference.into());
+        self.set_port_config(port, config).await
+    }
+
+    /// Get the power role the port starts in on attach
+    pub async fn get_default_power_role(&mut self, port: PortId) -> Result<PowerRole, Error<B::Error>> {
+        let config = self.get_port_config(port).await?;
+        config.typec_state_machine().try_into().map_err(Error::Pd)
+    }
+
+    /// Set the power role the port starts in on attach, takes effect on the next attach
+    ///
+    /// The configuration is read back to confirm the controller accepted it, some SKUs don't support every role.
+    pub async fn set_default_power_role(&mut self, port: PortId, role: PowerRole) -> Result<(), Error<B::Error>> {
+        let mut config = self.get_port_config(port).await?;
+        config.set_typec_state_machine(role.into());
+        self.set_port_config(port, config).await?;
+
+        if self.get_default_power_role(port).await? != role {
+            error!("Port {} rejected power role {:?}", port.0, role);
+            return PdError::InvalidParams.into();
+        }
+
+        Ok(())
+    }
+
+    /// Set the autonomous power role policy
+    ///
+    /// Sets the power role the Type-C state machine runs in and disables the role swaps the policy forbids. If the
+    /// port is connected in a role the policy forbids, the Type-C state machine is disabled before the new role is
+    /// written, which detaches the partner and tears down the active contract. The partner then re-attaches under the
+    /// new policy. Returns `InvalidMode` without changing anything if that would tear down a dead battery sink
+    /// contract, since it's powering the system. The configuration is read back to confirm the controller accepted it.
+    pub async fn set_port_role_policy(&mut self, port: PortId, policy: RolePolicy) -> Result<(), Error<B::Error>> {
+        let status = self.get_port_status(port).await?;
+        let teardown = status.plug_present() && !policy.allows(status.port_role());
+        if teardown && status.legacy() == registers::LegacyMode::LegacySinkDeadBattery {
+            error!("Port {} can't leave the dead battery sink contract", port.0);
+            return PdError::InvalidMode.into();
+        }
+
+        let mut control = self.get_port_control(port).await?;
+        policy.apply_swaps(&mut control);
+        self.set_port_control(port, control).await?;
+
+        let mut config = self.get_port_config(port).await?;
+        if teardown {
+            let mut disabled = config;
+            disabled.set_typec_state_machine(registers::TypecStateMachine::Disabled);
+            self.set_port_config(port, disabled).await?;
+        }
+
+        let role = PowerRole::from(policy);
+        config.set_typec_state_machine(role.into());
+        self.set_port_config(port, config).await?;
+
+        if self.get_default_power_role(port).await? != role {
+            error!("Port {} rejected role policy {:?}", port.0, policy);
+            return PdError::InvalidParams.into();
+        }
+
+        Ok(())
+    }
+
+    /// Get port control
+    pub async fn get_port_control(
+        &mut self,
+        port: PortId,
+    ) -> Result<registers::field_sets::PortControl, Error<B::Error>> {
+        self.borrow_port(port)?
+            .into_registers()
+            .port_control()
+            .read_async()
+            .await
+    }
+
+    /// Set port control
+    pub async fn set_port_control(
+        &mut self,
+        port: PortId,
+        control: registers::field_sets::PortControl,
+    ) -> Result<(), Error<B::Error>> {
+        self.borrow_port(port)?
+            .into_registers()
+            .port_control()
+            .write_async(|r| *r = control)
+            .await
+    }
+
+    /// Get automatic discovery and VDM configuration
+    pub async fn get_auto_vdm_config(&mut self, port: PortId) -> Result<AutoVdmConfig, Error<B::Error>> {
+        Ok(self.get_port_control(port).await?.into())
+    }
+
+    /// Set automatic discovery and VDM configuration
+    pub async fn set_auto_vdm_config(&mut self, port: PortId, config: AutoVdmConfig) -> Result<(), Error<B::Error>> {
+        let mut control = self.get_port_control(port).await?;
+        config.apply(&mut control);
+        self.set_port_control(port, control).await
+    }
+
+    /// Get the VCONN over-current limit in mA
+    pub async fn get_vconn_current_limit_ma(&mut self, port: PortId) -> Result<u16, Error<B::Error>> {
+        let control = self.get_port_control(port).await?;
+        Ok(port_control::vconn_current_limit_to_ma(control.vconn_current_limit()))
+    }
+
+    /// Set the VCONN over-current limit in mA, rounded down to a supported limit
+    ///
+    /// The limit must be within [`port_control::VCONN_CURRENT_LIMIT_RANGE_MA`], see the range for the default and the
+    /// supported limits. A VCONN over-current is reported by [`Self::get_overcurrent_status`].
+    pub async fn set_vconn_current_limit_ma(&mut self, port: PortId, ma: u16) -> Result<(), Error<B::Error>> {
+        let limit = port_control::vconn_current_limit_from_ma(ma).ok_or(Error::Pd(PdError::InvalidParams))?;
+
+        let mut control = self.get_port_control(port).await?;
+        control.set_vconn_current_limit(limit);
+        self.set_port_control(port, control).await
+    }
+
+    /// Enable or disable USB data
+    ///
+    /// The controller has a single USB data enable, so USB2 and USB3 are enabled or disabled together.
+    /// [`registers::field_sets::PortControl::set_force_usb_3_gen_1`] is the closest option for flaky USB3 cables.
+    /// Disabling USB data doesn't affect DisplayPort alt mode, but returns `InvalidMode` while Thunderbolt is connected
+    /// since USB is tunnelled through it.
+    pub async fn set_usb_data_enable(&mut self, port: PortId, enable: bool) -> Result<(), Error<B::Error>> {
+        if self.get_data_status(port).await?.tbt_connection() {
+            return PdError::InvalidMode.into();
+        }
+
+        let mut control = self.get_port_control(port).await?;
+        control.set_usb_disable(!enable);
+        self.set_port_control(port, control).await
+    }
+
+    /// Get global system config
+    pub async fn get_system_config(&mut self) -> Result<registers::field_sets::SystemConfig, Error<B::Error>> {
+        // This is a controller-level command, shouldn't matter which port we use
+        self.borrow_port(PORT0)?
+            .into_registers()
+            .system_config()
+            .read_async()
+            .await
+    }
+
+    /// Set global system config
+    pub async fn set_system_config(
+        &mut self,
+        config: registers::field_sets::SystemConfig,
+    ) -> Result<(), Error<B::Error>> {
+        // This is a controller-level command, shouldn't matter which port we use
+        self.borrow_port(PORT0)?
+            .into_registers()
+            .system_config()
+            .write_async(|r| *r = config)
+            .await
+    }
+
+    /// Enable/disable sourcing on a given port
+    pub async fn enable_source(&mut self, port: PortId, enable: bool) -> Result<(), Error<B::Error>> {
+        let mut config = self.get_system_config().await?;
+
+        let enable = if enable {
+            registers::VbusSwConfig::Source
+        } else {
+            registers::VbusSwConfig::Disabled
+        };
+        match port {
+            PORT0 => config.set_pa_pp_5_v_vbus_sw_config(enable),
+            PORT1 => config.set_pb_pp_5_v_vbus_sw_config(enable),
+            _ => return PdError::InvalidPort.into(),
+        }
+
+        self.set_system_config(config).await?;
+        Ok(())
+    }
+
+    /// Inhibit or allow sourcing on a port, leaving the data path connected
+    ///
+    /// Inhibiting disables the port's PP5V source switch through [`Self::enable_source`], USB data isn't affected.
+    /// Cutting VBUS under an active source contract would violate it, so this returns `InvalidMode` without changing
+    /// anything while the port is sourcing under a contract, swap to sink or end the contract first. It also returns
+    /// `InvalidMode` if the port's PPEXT switch is configured to source, that path can't be gated without also
+    /// disabling sinking through it. Allowing sourcing again re-enables the PP5V switch.
+    pub async fn set_source_inhibit(&mut self, port: PortId, inhibit: bool) -> Result<(), Error<B::Error>> {
+        if inhibit {
+            let config = self.get_system_config().await?;
+            let ppext = match port {
+                PORT0 => config.pa_ppext_vbus_sw_config(),
+                PORT1 => config.pb_ppext_vbus_sw_config(),
+                _ => return PdError::InvalidPort.into(),
+            };
+            if matches!(
+                ppext,
+                registers::PpextVbusSwConfig::Source
+                    | registers::PpextVbusSwConfig::BiDirectional
+                    | registers::PpextVbusSwConfig::BiDirectionalWaitSrdy
+            ) {
+                return PdError::InvalidMode.into();
+            }
+
+            let status = self.get_port_status(port).await?;
+            if status.plug_present() && status.port_role() && self.get_active_rdo_raw(port).await? != 0 {
+                return PdError::InvalidMode.into();
+            }
+        }
+
+        self.enable_source(port, !inhibit).await
+    }
+
+    /// Get the I2C bus timeout
+    pub async fn get_i2c_timeout(&mut self) -> Result<registers::I2cTimeout, Error<B::Error>> {
+        Ok(self.get_system_config().await?.i_2_c_timeout())
+    }
+
+    /// Set the I2C bus timeout, the default is [`registers::I2cTimeout::Timeout125ms`]
+    ///
+    /// The controller abandons a transaction that stalls for longer than the timeout, e.g. while a host clock stretches
+    /// or is preempted mid-transfer. A timeout shorter than the worst-case stall aborts transactions partway through,
+    /// so prefer longer timeouts on hosts with long interrupt latencies. The timeout can't be disabled, use
+    /// [`registers::I2cTimeout::Timeout1000ms`] for the most tolerance. Returns `InvalidParams` if the controller
+    /// doesn't accept the new timeout.
+    pub async fn set_i2c_timeout(&mut self, timeout: registers::I2cTimeout) -> Result<(), Error<B::Error>> {
+        let mut config = self.get_system_config().await?;
+        config.set_i_2_c_timeout(timeout);
+        self.set_system_config(config).await?;
+
+        if self.get_i2c_timeout().await? != timeout {
+            error!("Controller rejected I2C timeout {:?}", timeout);
+            return PdError::InvalidParams.into();
+        }
+
+        Ok(())
+    }
+
+    /// Read the configuration registers listed in [`registers::controller_config`] into a single snapshot
+    pub async fn export_config(&mut self) -> Result<ControllerConfig, Error<B::Error>> {
+        let mut ports = heapless::Vec::new();
+        for port in 0..self.num_ports() {
+            let port = PortId(port as u8);
+            let settings = PortSettings {
+                port_config: self.get_port_config(port).await?,
+                port_control: self.get_port_control(port).await?,
+                interrupt_mask: self.get_interrupt_mask(port).await?,
+                tx_sink_caps: self.get_tx_sink_caps(port).await?,
+                tx_identity: self.get_tx_identity(port).await?,
+            };
+            // Can't fail, there are at most as many ports as the capacity
+            let _ = ports.push(settings);
+        }
+
+        Ok(ControllerConfig {
+            system_config: self.get_system_config().await?,
+            ports,
+        })
+    }
+
+    /// Write a snapshot from [`Self::export_config`], possibly taken on another unit
+    ///
+    /// Controller-level registers are written first, then each port's registers with the interrupt mask last. Like the
+    /// individual setters, this applies until the next reset and each setting takes effect as it would when written
+    /// on its own. Returns `InvalidParams` without writing anything if the snapshot is for a different number of
+    /// ports, on failure the registers written before remain applied.
+    pub async fn apply_config(&mut self, config: &ControllerConfig) -> Result<(), Error<B::Error>> {
+        if config.ports.len() != self.num_ports() {
+            return PdError::InvalidParams.into();
+        }
+
+        self.set_system_config(config.system_config).await?;
+        for (port, settings) in config.ports.iter().enumerate() {
+            let port = PortId(port as u8);
+            self.set_port_config(port, settings.port_config).await?;
+            self.set_port_control(port, settings.port_control).await?;
+            self.set_tx_sink_caps(port, &settings.tx_sink_caps).await?;
+            self.set_tx_identity(port, &settings.tx_identity).await?;
+            self.set_interrupt_mask(port, settings.interrupt_mask).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use std::vec::Vec;
 
-    use device_driver::AsyncRegisterInterface;
     use embedded_hal_async::i2c::ErrorType;
     use embedded_hal_mock::eh1::i2c::Mock;
 
     use super::*;
     use crate::test::*;
-    use crate::{ADDR0, ADDR1, PORT0, PORT1};
+    use crate::{u32_from_str, ADDR0, ADDR1, PORT0, PORT1};
 
     /// Test firmware version, no particular meaning to this value
     const TEST_FW_VERSION: u32 = 0x12345678;
@@ -310,6 +1306,42 @@ mod test {
         create_register_read(0, 0, [0]);
     }
 
+    #[test]
+    fn test_scale_delay() {
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+
+        // Default speed doesn't scale
+        assert_eq!(tps6699x.scale_delay_us(1000), 1000);
+
+        tps6699x.set_bus_speed_hz(100_000).unwrap();
+        assert_eq!(tps6699x.scale_delay_us(1000), 4000);
+
+        tps6699x.set_bus_speed_hz(1_000_000).unwrap();
+        assert_eq!(tps6699x.scale_delay_us(1000), 400);
+
+        // Out of range speeds are clamped
+        tps6699x.set_bus_speed_hz(10_000).unwrap();
+        assert_eq!(tps6699x.scale_delay_us(1000), 4000);
+
+        tps6699x.set_bus_speed_hz(3_400_000).unwrap();
+        assert_eq!(tps6699x.scale_delay_us(1000), 400);
+
+        assert!(tps6699x.set_bus_speed_hz(0).is_err());
+        tps6699x.bus.done();
+    }
+
+    #[test]
+    fn test_app_mode_backoff() {
+        let mut backoff = Backoff::app_mode(200);
+        let mut delays = Vec::new();
+        while let Some(delay_ms) = backoff.next_delay_ms() {
+            delays.push(delay_ms);
+        }
+
+        // Doubles up to the maximum, the last delay is cut short by the timeout
+        assert_eq!(delays, [10, 20, 40, 80, 50]);
+    }
+
     async fn test_read_port<const N: usize>(
         tps6699x: &mut Tps6699x<Mock>,
         port_id: PortId,
@@ -430,99 +1462,1737 @@ mod test {
         test_clear_interrupt(&mut tps6699x, PORT1, PORT1_ADDR1).await;
     }
 
-    async fn test_get_port_status(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
-        use registers::field_sets::Status;
+    async fn test_peek_interrupt(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::IntEventBus1;
+
+        let mut int = IntEventBus1::new_zero();
+        int.set_plug_event(true);
 
+        // Only the event register is read, nothing is cleared
         let mut transactions = Vec::new();
-        // Read status register
-        transactions.push(create_register_read(expected_addr, 0x1A, Status::new_zero()));
+        transactions.push(create_register_read(expected_addr, 0x14, int));
         tps6699x.bus.update_expectations(&transactions);
 
-        let status = tps6699x.get_port_status(port).await.unwrap();
-        assert_eq!(status, Status::new_zero());
+        assert_eq!(tps6699x.peek_interrupt(port).await.unwrap(), int);
         tps6699x.bus.done();
     }
 
-    /// Test get port status on address set 0
     #[tokio::test]
-    async fn test_get_port_status_0() {
+    async fn test_peek_interrupt_0() {
         let mock = Mock::new(&[]);
         let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
 
-        test_get_port_status(&mut tps6699x, PORT0, PORT0_ADDR0).await;
-        test_get_port_status(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+        test_peek_interrupt(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_peek_interrupt(&mut tps6699x, PORT1, PORT1_ADDR0).await;
     }
 
-    /// Test get port status on address set 1
     #[tokio::test]
-    async fn test_get_port_status_1() {
+    async fn test_peek_interrupt_1() {
         let mock = Mock::new(&[]);
         let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
 
-        test_get_port_status(&mut tps6699x, PORT0, PORT0_ADDR1).await;
-        test_get_port_status(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+        test_peek_interrupt(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_peek_interrupt(&mut tps6699x, PORT1, PORT1_ADDR1).await;
     }
 
-    async fn test_get_active_pdo_contract(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
-        use registers::field_sets::ActivePdoContract;
+    #[tokio::test]
+    async fn test_interrupt_report() {
+        use registers::field_sets::{IntEventBus1, Status};
+        use registers::pd_events::PdInterruptEvent;
+
+        let mut port0 = IntEventBus1::new_zero();
+        port0.set_plug_event(true);
+        port0.set_cmd_1_completed(true);
+        let mut port1 = IntEventBus1::new_zero();
+        port1.set_new_consumer_contract(true);
+        let mut source = Status::new_zero();
+        source.set_port_role(true);
+
+        // Only the port with a new contract needs its status for the role
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(PORT0_ADDR0, 0x14, port0));
+        transactions.push(create_register_read(PORT1_ADDR0, 0x14, port1));
+        transactions.push(create_register_read(PORT1_ADDR0, 0x1A, source));
+        let mock = Mock::new(&transactions);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
 
+        let report = tps6699x.interrupt_report().await.unwrap();
+        assert_eq!(report[0].as_slice(), &[PdInterruptEvent::PlugEvent]);
+        assert_eq!(
+            report[1].as_slice(),
+            &[PdInterruptEvent::NewContract {
+                role: PowerRole::Source
+            }]
+        );
+        tps6699x.bus.done();
+
+        // Single port part only reads the port that's present
         let mut transactions = Vec::new();
-        // Read status register
-        transactions.push(create_register_read(expected_addr, 0x34, ActivePdoContract::new_zero()));
+        transactions.push(create_register_read(PORT0_ADDR0, 0x14, port0));
+        let mock = Mock::new(&transactions);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66993(mock, PORT0_ADDR0);
+
+        let report = tps6699x.interrupt_report().await.unwrap();
+        assert_eq!(report[0].as_slice(), &[PdInterruptEvent::PlugEvent]);
+        assert!(report[1].is_empty());
+        tps6699x.bus.done();
+    }
+
+    async fn test_clear_interrupt_verified(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::IntEventBus1;
+
+        let int = !IntEventBus1::new_zero();
+        // Simulate a sticky interrupt
+        let mut pending = IntEventBus1::new_zero();
+        pending.set_plug_event(true);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x14, int));
+        transactions.push(create_register_write(expected_addr, 0x18, int));
+        transactions.push(create_register_read(expected_addr, 0x14, pending));
         tps6699x.bus.update_expectations(&transactions);
 
-        let active_pdo_contract = tps6699x.get_active_pdo_contract(port).await.unwrap();
-        assert_eq!(active_pdo_contract, ActivePdoContract::new_zero());
+        assert_eq!(tps6699x.clear_interrupt_verified(port).await.unwrap(), (int, pending));
         tps6699x.bus.done();
     }
 
+    /// Test verified interrupt clearing with address set 0
     #[tokio::test]
-    async fn test_get_active_pdo_contract_ports_0() {
+    async fn test_clear_interrupt_verified_0() {
         let mock = Mock::new(&[]);
         let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
 
-        test_get_active_pdo_contract(&mut tps6699x, PORT0, PORT0_ADDR0).await;
-        test_get_active_pdo_contract(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+        test_clear_interrupt_verified(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_clear_interrupt_verified(&mut tps6699x, PORT1, PORT1_ADDR0).await;
     }
 
+    /// Test verified interrupt clearing with address set 1
     #[tokio::test]
-    async fn test_get_active_pdo_contract_ports_1() {
+    async fn test_clear_interrupt_verified_1() {
         let mock = Mock::new(&[]);
         let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
 
-        test_get_active_pdo_contract(&mut tps6699x, PORT0, PORT0_ADDR1).await;
-        test_get_active_pdo_contract(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+        test_clear_interrupt_verified(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_clear_interrupt_verified(&mut tps6699x, PORT1, PORT1_ADDR1).await;
     }
 
-    async fn test_get_active_rdo_contract(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
-        use registers::field_sets::ActiveRdoContract;
+    async fn test_is_dead_battery(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::Status;
+        use registers::LegacyMode;
+
+        let mut status = Status::new_zero();
+        status.set_legacy(LegacyMode::LegacySinkDeadBattery);
 
         let mut transactions = Vec::new();
-        // Read status register
+        transactions.push(create_register_read(expected_addr, 0x1A, status));
+        status.set_legacy(LegacyMode::LegacySink);
+        transactions.push(create_register_read(expected_addr, 0x1A, status));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert!(tps6699x.is_dead_battery(port).await.unwrap());
+        assert!(!tps6699x.is_dead_battery(port).await.unwrap());
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_is_dead_battery_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_is_dead_battery(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_is_dead_battery(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_is_dead_battery_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_is_dead_battery(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_is_dead_battery(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_get_termination(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use embedded_usb_pd::type_c;
+        use registers::field_sets::{PortConfig, PortControl, Status};
+
+        let mut status = Status::new_zero();
+        status.set_plug_present(true);
+        status.set_connection_state(registers::PlugMode::Connected);
+        status.set_port_role(true);
+        let mut control = PortControl::new_zero();
+        control.set_typec_current(registers::TypecCurrent::Current1A5);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x1A, status));
+        transactions.push(create_register_read(expected_addr, 0x28, PortConfig::new_zero()));
+        transactions.push(create_register_read(expected_addr, 0x29, control));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(
+            tps6699x.get_termination(port).await.unwrap(),
+            Termination::Rp(type_c::Current::Current1A5)
+        );
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_termination_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_get_termination(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_termination(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_termination_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_get_termination(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_termination(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_vconn_current_limit(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::PortControl;
+
+        let control = PortControl::new();
+        let mut expected = control;
+        expected.set_vconn_current_limit(registers::VconnCurrentLimit::Current410ma);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x29, control));
+        transactions.push(create_register_read(expected_addr, 0x29, control));
+        transactions.push(create_register_write(expected_addr, 0x29, expected));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(tps6699x.get_vconn_current_limit_ma(port).await.unwrap(), 590);
+        tps6699x.set_vconn_current_limit_ma(port, 500).await.unwrap();
+        tps6699x.bus.done();
+
+        // Out of range limits don't touch the bus
+        let r = tps6699x.set_vconn_current_limit_ma(port, 300).await;
+        assert!(matches!(r, Err(Error::Pd(PdError::InvalidParams))));
+        let r = tps6699x.set_vconn_current_limit_ma(port, 1000).await;
+        assert!(matches!(r, Err(Error::Pd(PdError::InvalidParams))));
+    }
+
+    #[tokio::test]
+    async fn test_vconn_current_limit_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_vconn_current_limit(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_vconn_current_limit(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_vconn_current_limit_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_vconn_current_limit(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_vconn_current_limit(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_get_contract_current_ma(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::{ActivePdoContract, ActiveRdoContract};
+
+        let mut pdo = ActivePdoContract::new_zero();
+        pdo.set_active_pdo(0x0001_912C);
+        let mut rdo = ActiveRdoContract::new_zero();
+        rdo.set_active_rdo(0x1004_B12C);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x34, pdo));
+        transactions.push(create_register_read(expected_addr, 0x35, rdo));
+        // No contract
+        transactions.push(create_register_read(expected_addr, 0x34, ActivePdoContract::new_zero()));
+        transactions.push(create_register_read(expected_addr, 0x35, ActiveRdoContract::new_zero()));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(tps6699x.get_contract_current_ma(port).await.unwrap(), 3000);
+        assert_eq!(tps6699x.get_contract_current_ma(port).await.unwrap(), 0);
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_contract_current_ma_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_get_contract_current_ma(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_contract_current_ma(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_contract_current_ma_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_get_contract_current_ma(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_contract_current_ma(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_get_available_headroom_ma(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::{ActivePdoContract, ActiveRdoContract};
+
+        // 5V/3A PDO, 1.5A operating current
+        let mut pdo = ActivePdoContract::new_zero();
+        pdo.set_active_pdo(0x0001_912C);
+        let mut rdo = ActiveRdoContract::new_zero();
+        rdo.set_active_rdo(0x1002_592C);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x34, pdo));
+        transactions.push(create_register_read(expected_addr, 0x35, rdo));
+        // No contract
+        transactions.push(create_register_read(expected_addr, 0x34, ActivePdoContract::new_zero()));
+        transactions.push(create_register_read(expected_addr, 0x35, ActiveRdoContract::new_zero()));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(tps6699x.get_available_headroom_ma(port).await.unwrap(), 1500);
+        assert_eq!(tps6699x.get_available_headroom_ma(port).await.unwrap(), 0);
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_available_headroom_ma_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_get_available_headroom_ma(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_available_headroom_ma(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_available_headroom_ma_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_get_available_headroom_ma(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_available_headroom_ma(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_get_pps_range(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::ActivePdoContract;
+
+        let mut pdo = ActivePdoContract::new_zero();
+        pdo.set_active_pdo(0xC1A4_213C);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x34, pdo));
+        transactions.push(create_register_read(expected_addr, 0x34, ActivePdoContract::new_zero()));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(
+            tps6699x.get_pps_range(port).await.unwrap(),
+            PpsRange {
+                min_voltage_mv: 3300,
+                max_voltage_mv: 21000,
+                max_current_ma: 3000,
+            }
+        );
+        let r = tps6699x.get_pps_range(port).await;
+        assert!(matches!(r, Err(Error::Pd(PdError::InvalidMode))));
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_pps_range_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_get_pps_range(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_pps_range(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_pps_range_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_get_pps_range(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_pps_range(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_has_capability_mismatch(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::ActiveRdoContract;
+
+        let mut rdo = ActiveRdoContract::new_zero();
+        rdo.set_active_rdo(0x1404_B12C);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x35, rdo));
         transactions.push(create_register_read(expected_addr, 0x35, ActiveRdoContract::new_zero()));
         tps6699x.bus.update_expectations(&transactions);
 
-        let active_rdo_contract = tps6699x.get_active_rdo_contract(port).await.unwrap();
-        assert_eq!(active_rdo_contract, ActiveRdoContract::new_zero());
+        assert!(tps6699x.has_capability_mismatch(port).await.unwrap());
+        assert!(!tps6699x.has_capability_mismatch(port).await.unwrap());
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_has_capability_mismatch_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_has_capability_mismatch(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_has_capability_mismatch(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_has_capability_mismatch_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_has_capability_mismatch(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_has_capability_mismatch(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_get_telemetry(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use crate::pdo::Rdo;
+        use registers::field_sets::{ActivePdoContract, ActiveRdoContract, Status};
+
+        let mut status = Status::new_zero();
+        status.set_plug_present(true);
+        let mut pdo = ActivePdoContract::new_zero();
+        pdo.set_active_pdo(0x0001_912C);
+        let mut rdo = ActiveRdoContract::new_zero();
+        rdo.set_active_rdo(0x1004_B12C);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x1A, status));
+        transactions.push(create_register_read(expected_addr, 0x34, pdo));
+        transactions.push(create_register_read(expected_addr, 0x35, rdo));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(
+            tps6699x.get_telemetry(port).await.unwrap(),
+            PortTelemetry {
+                status,
+                active_pdo: 0x0001_912C,
+                active_rdo: Rdo(0x1004_B12C),
+                contract_current_ma: 3000,
+            }
+        );
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_telemetry_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_get_telemetry(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_telemetry(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_telemetry_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_get_telemetry(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_telemetry(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_get_partner_info(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::{ActivePdoContract, Status};
+
+        let mut status = Status::new_zero();
+        status.set_plug_present(true);
+        let mut contract = ActivePdoContract::new_zero();
+        contract.set_active_pdo(0x0A01_912C);
+        // Unconstrained and USB comms capable
+        contract.set_first_pdo_control(0xC0);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x1A, status));
+        transactions.push(create_register_read(expected_addr, 0x34, contract));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(
+            tps6699x.get_partner_info(port).await.unwrap(),
+            Some(PartnerInfo {
+                unconstrained_power: true,
+                usb_comm: true,
+                ..Default::default()
+            })
+        );
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_partner_info_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_get_partner_info(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_partner_info(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_partner_info_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_get_partner_info(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_partner_info(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_is_epr_keepalive_ok(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::Status;
+
+        for (plug_present, epr_mode, expected) in [
+            // Not in EPR mode yet
+            (true, false, None),
+            (true, true, Some(true)),
+            // Left EPR mode
+            (true, false, Some(false)),
+            // Detached, EPR mode must be seen again after the next attach
+            (false, true, None),
+            (true, false, None),
+        ] {
+            let mut status = Status::new_zero();
+            status.set_plug_present(plug_present);
+            status.set_erp_mode(epr_mode);
+
+            let mut transactions = Vec::new();
+            transactions.push(create_register_read(expected_addr, 0x1A, status));
+            tps6699x.bus.update_expectations(&transactions);
+
+            assert_eq!(tps6699x.is_epr_keepalive_ok(port).await.unwrap(), expected);
+            tps6699x.bus.done();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_is_epr_keepalive_ok_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_is_epr_keepalive_ok(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_is_epr_keepalive_ok(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_is_epr_keepalive_ok_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_is_epr_keepalive_ok(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_is_epr_keepalive_ok(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_is_vbus_present(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::Status;
+        use registers::VbusMode;
+
+        for (vbus, expected) in [
+            (VbusMode::AtVsafe0, false),
+            (VbusMode::Atvsafe5, true),
+            (VbusMode::Normal, true),
+            (VbusMode::Other, true),
+        ] {
+            // No plug or contract, e.g. a Type-C only attach that hasn't debounced yet
+            let mut status = Status::new_zero();
+            status.set_vbus_status(vbus);
+
+            let mut transactions = Vec::new();
+            transactions.push(create_register_read(expected_addr, 0x1A, status));
+            tps6699x.bus.update_expectations(&transactions);
+
+            assert_eq!(tps6699x.is_vbus_present(port).await.unwrap(), expected);
+            tps6699x.bus.done();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_is_vbus_present_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_is_vbus_present(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_is_vbus_present(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_is_vbus_present_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_is_vbus_present(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_is_vbus_present(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_get_last_reset_cause(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::PdStatus;
+        use registers::PdHardResetDetails;
+
+        let mut status = PdStatus::new_zero();
+        status.set_hard_reset_details(PdHardResetDetails::NoResponseTimeout);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x40, PdStatus::new_zero()));
+        transactions.push(create_register_read(expected_addr, 0x40, status));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(tps6699x.get_last_reset_cause(port).await.unwrap(), ResetCause::None);
+        assert_eq!(
+            tps6699x.get_last_reset_cause(port).await.unwrap(),
+            ResetCause::HardReset(PdHardResetDetails::NoResponseTimeout)
+        );
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_last_reset_cause_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_get_last_reset_cause(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_last_reset_cause(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_last_reset_cause_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_get_last_reset_cause(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_last_reset_cause(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_mailbox(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        let mut mailbox = [0u8; registers::REG_MAILBOX_LEN];
+        for (i, byte) in mailbox.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x4F, mailbox));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let mut buf = [0u8; 4];
+        tps6699x.read_mailbox(port, 8, &mut buf).await.unwrap();
+        assert_eq!(buf, [8, 9, 10, 11]);
+        tps6699x.bus.done();
+
+        let mut expected = mailbox;
+        expected[60..].copy_from_slice(&[0xAA, 0xBB, 0xCC, 0xDD]);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x4F, mailbox));
+        transactions.push(create_register_write(expected_addr, 0x4F, expected));
+        tps6699x.bus.update_expectations(&transactions);
+
+        tps6699x
+            .write_mailbox(port, 60, &[0xAA, 0xBB, 0xCC, 0xDD])
+            .await
+            .unwrap();
+        tps6699x.bus.done();
+    }
+
+    async fn test_mailbox_out_of_bounds(tps6699x: &mut Tps6699x<Mock>, port: PortId) {
+        // Rejected before touching the bus
+        tps6699x.bus.update_expectations(&[]);
+
+        let mut buf = [0u8; 4];
+        let result = tps6699x
+            .read_mailbox(port, registers::REG_MAILBOX_LEN - 3, &mut buf)
+            .await;
+        assert!(matches!(result, Err(Error::Pd(PdError::InvalidParams))));
+
+        let result = tps6699x.write_mailbox(port, usize::MAX, &[0]).await;
+        assert!(matches!(result, Err(Error::Pd(PdError::InvalidParams))));
+
+        let result = tps6699x.write_mailbox(port, 0, &[]).await;
+        assert!(matches!(result, Err(Error::Pd(PdError::InvalidParams))));
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_mailbox_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_mailbox(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_mailbox(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+        test_mailbox_out_of_bounds(&mut tps6699x, PORT0).await;
+    }
+
+    #[tokio::test]
+    async fn test_mailbox_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_mailbox(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_mailbox(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+        test_mailbox_out_of_bounds(&mut tps6699x, PORT1).await;
+    }
+
+    async fn test_get_cc_status(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::cc_status::{Accessory, CcPin, Orientation};
+        use registers::field_sets::Status;
+
+        let mut status = Status::new_zero();
+        status.set_plug_present(true);
+        status.set_connection_state(registers::PlugMode::Connected);
+        status.set_plug_orientation(true);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x1A, status));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let cc = tps6699x.get_cc_status(port).await.unwrap();
+        assert_eq!(cc.active_cc, Some(CcPin::Cc2));
+        assert_eq!(cc.orientation, Orientation::Flipped);
+        assert_eq!(cc.accessory, Accessory::None);
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_cc_status_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_get_cc_status(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_cc_status(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_cc_status_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_get_cc_status(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_cc_status(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_get_overcurrent_status(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::PowerPathStatus;
+
+        let mut status = PowerPathStatus::new_zero();
+        status.set_pa_int_vbus_oc(true);
+        status.set_pb_int_vbus_oc(true);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x26, status));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let oc = tps6699x.get_overcurrent_status(port).await.unwrap();
+        assert!(oc.vbus);
+        assert!(!oc.vconn);
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_overcurrent_status_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_get_overcurrent_status(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_overcurrent_status(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_overcurrent_status_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_get_overcurrent_status(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_overcurrent_status(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_modify_interrupt_mask(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::IntEventBus1;
+
+        let mut mask = IntEventBus1::new_zero();
+        mask.set_cmd_1_completed(true);
+        let mut expected = mask;
+        expected.set_plug_event(true);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x16, mask));
+        transactions.push(create_register_write(expected_addr, 0x16, expected));
+        tps6699x.bus.update_expectations(&transactions);
+        assert_eq!(
+            tps6699x
+                .modify_interrupt_mask(port, |m| m.set_plug_event(true))
+                .await
+                .unwrap(),
+            expected
+        );
+        tps6699x.bus.done();
+
+        // Disabling command completion is rejected
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x16, mask));
+        tps6699x.bus.update_expectations(&transactions);
+        assert!(matches!(
+            tps6699x
+                .modify_interrupt_mask(port, |m| m.set_cmd_1_completed(false))
+                .await,
+            Err(Error::Pd(PdError::InvalidParams))
+        ));
+        tps6699x.bus.done();
+
+        // Masking everything is allowed without validation
+        tps6699x
+            .bus
+            .update_expectations(&[create_register_write(expected_addr, 0x16, IntEventBus1::new_zero())]);
+        tps6699x
+            .set_interrupt_mask(port, IntEventBus1::new_zero())
+            .await
+            .unwrap();
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_modify_interrupt_mask_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_modify_interrupt_mask(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_modify_interrupt_mask(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_modify_interrupt_mask_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_modify_interrupt_mask(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_modify_interrupt_mask(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_get_port_status(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::Status;
+
+        let mut transactions = Vec::new();
+        // Read status register
+        transactions.push(create_register_read(expected_addr, 0x1A, Status::new_zero()));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let status = tps6699x.get_port_status(port).await.unwrap();
+        assert_eq!(status, Status::new_zero());
+        tps6699x.bus.done();
+    }
+
+    /// Test get port status on address set 0
+    #[tokio::test]
+    async fn test_get_port_status_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_get_port_status(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_port_status(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    /// Test get port status on address set 1
+    #[tokio::test]
+    async fn test_get_port_status_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_get_port_status(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_port_status(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_get_port_snapshot(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::{ActiveRdoContract, Status};
+
+        let mut status = Status::new_zero();
+        status.set_plug_present(true);
+        let mut rdo = ActiveRdoContract::new_zero();
+        rdo.set_active_rdo(0x1304b12c);
+
+        tps6699x.bus.update_expectations(&[
+            create_register_read(expected_addr, 0x1A, status),
+            create_register_read(expected_addr, 0x35, rdo),
+        ]);
+        assert_eq!(
+            tps6699x.get_port_snapshot(port).await.unwrap(),
+            PortSnapshot {
+                status,
+                active_rdo: 0x1304b12c
+            }
+        );
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_port_snapshot_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_get_port_snapshot(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_port_snapshot(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_port_snapshot_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_get_port_snapshot(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_port_snapshot(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_get_active_pdo_contract(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::ActivePdoContract;
+
+        let mut transactions = Vec::new();
+        // Read status register
+        transactions.push(create_register_read(expected_addr, 0x34, ActivePdoContract::new_zero()));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let active_pdo_contract = tps6699x.get_active_pdo_contract(port).await.unwrap();
+        assert_eq!(active_pdo_contract, ActivePdoContract::new_zero());
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_active_pdo_contract_ports_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_get_active_pdo_contract(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_active_pdo_contract(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_active_pdo_contract_ports_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_get_active_pdo_contract(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_active_pdo_contract(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_get_active_rdo_contract(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::ActiveRdoContract;
+
+        let mut transactions = Vec::new();
+        // Read status register
+        transactions.push(create_register_read(expected_addr, 0x35, ActiveRdoContract::new_zero()));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let active_rdo_contract = tps6699x.get_active_rdo_contract(port).await.unwrap();
+        assert_eq!(active_rdo_contract, ActiveRdoContract::new_zero());
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_active_rdo_contract_ports_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_get_active_rdo_contract(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_active_rdo_contract(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_active_rdo_contract_ports_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_get_active_rdo_contract(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_active_rdo_contract(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_get_active_contract_raw(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::{ActivePdoContract, ActiveRdoContract};
+
+        // Programmable supply APDO, uses the top bits of the PDO
+        const PDO: u32 = 0xC8DC_213C;
+        const RDO: u32 = 0x1234_5678;
+
+        let mut pdo = ActivePdoContract::new_zero();
+        pdo.set_active_pdo(PDO);
+        let mut rdo = ActiveRdoContract::new_zero();
+        rdo.set_active_rdo(RDO);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x34, pdo));
+        transactions.push(create_register_read(expected_addr, 0x35, rdo));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(tps6699x.get_active_pdo_raw(port).await.unwrap(), PDO);
+        assert_eq!(tps6699x.get_active_rdo_raw(port).await.unwrap(), RDO);
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_active_contract_raw_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_get_active_contract_raw(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_active_contract_raw(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_active_contract_raw_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_get_active_contract_raw(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_active_contract_raw(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_get_data_status(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::DataStatus;
+
+        let mut data_status = DataStatus::new_zero();
+        data_status.set_data_connection(true);
+        data_status.set_usb_2_connection(true);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x5F, data_status));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(tps6699x.get_data_status(port).await.unwrap(), data_status);
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_data_status_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_get_data_status(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_data_status(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_data_status_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_get_data_status(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_data_status(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_get_usb_status(
+        tps6699x: &mut Tps6699x<Mock>,
+        port: PortId,
+        expected_addr: u8,
+        status: registers::field_sets::Status,
+        data_status: registers::field_sets::DataStatus,
+        expected: UsbStatus,
+    ) {
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x1A, status));
+        transactions.push(create_register_read(expected_addr, 0x5F, data_status));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(tps6699x.get_usb_status(port).await.unwrap(), expected);
+        tps6699x.bus.done();
+    }
+
+    async fn test_get_usb_statuses(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::{DataStatus, Status};
+        use registers::UsbHostMode;
+
+        // Nothing connected
+        test_get_usb_status(
+            tps6699x,
+            port,
+            expected_addr,
+            Status::new_zero(),
+            DataStatus::new_zero(),
+            UsbStatus::default(),
+        )
+        .await;
+
+        // Connected to a USB host, we're the UFP
+        let mut status = Status::new_zero();
+        status.set_usb_host(UsbHostMode::HostPresent);
+        let mut data_status = DataStatus::new_zero();
+        data_status.set_data_connection(true);
+        data_status.set_usb_data_role(true);
+        data_status.set_usb_2_connection(true);
+        data_status.set_usb_3_connection(true);
+        test_get_usb_status(
+            tps6699x,
+            port,
+            expected_addr,
+            status,
+            data_status,
+            UsbStatus {
+                host_present: true,
+                device_present: false,
+                usb2_enabled: true,
+                usb3_enabled: true,
+            },
+        )
+        .await;
+
+        // Connected to a USB2 device, we're the DFP
+        let mut data_status = DataStatus::new_zero();
+        data_status.set_data_connection(true);
+        data_status.set_usb_2_connection(true);
+        test_get_usb_status(
+            tps6699x,
+            port,
+            expected_addr,
+            Status::new_zero(),
+            data_status,
+            UsbStatus {
+                host_present: false,
+                device_present: true,
+                usb2_enabled: true,
+                usb3_enabled: false,
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_get_usb_status_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_get_usb_statuses(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_usb_statuses(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_usb_status_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_get_usb_statuses(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_usb_statuses(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_rx_caps(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use crate::pdo::SinkPdo;
+
+        let mut source = [0u8; REG_RX_SOURCE_CAPS_LEN];
+        source[0] = 1;
+        source[1..5].copy_from_slice(&0x0801_912Cu32.to_le_bytes());
+        let mut sink = [0u8; REG_RX_SINK_CAPS_LEN];
+        sink[0] = 1;
+        sink[1..5].copy_from_slice(&0x0001_912Cu32.to_le_bytes());
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x30, source));
+        transactions.push(create_register_read(expected_addr, 0x31, sink));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(
+            tps6699x.get_rx_source_caps(port).await.unwrap().pdos(),
+            &[SourcePdo::Fixed {
+                voltage_mv: 5000,
+                max_current_ma: 3000,
+            }]
+        );
+        assert_eq!(
+            tps6699x.get_rx_sink_caps(port).await.unwrap().pdos(),
+            &[SinkPdo::Fixed {
+                voltage_mv: 5000,
+                operational_current_ma: 3000,
+                dual_role_power: false,
+                higher_capability: false,
+                unconstrained_power: false,
+                usb_comm: false,
+                dual_role_data: false,
+            }]
+        );
+        tps6699x.bus.done();
+    }
+
+    async fn test_get_source_pdo_count(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::Status;
+
+        let mut status = Status::new_zero();
+        status.set_plug_present(true);
+        let mut source = [0u8; REG_RX_SOURCE_CAPS_LEN];
+        source[0] = 3;
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x1A, status));
+        transactions.push(create_register_read(expected_addr, 0x30, source));
+        // Stale capabilities aren't read once detached
+        transactions.push(create_register_read(expected_addr, 0x1A, Status::new_zero()));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(tps6699x.get_source_pdo_count(port).await.unwrap(), 3);
+        assert_eq!(tps6699x.get_source_pdo_count(port).await.unwrap(), 0);
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_source_pdo_count_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_get_source_pdo_count(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_source_pdo_count(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_source_pdo_count_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_get_source_pdo_count(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_source_pdo_count(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    #[tokio::test]
+    async fn test_rx_caps_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_rx_caps(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_rx_caps(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_rx_caps_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_rx_caps(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_rx_caps(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_tx_sink_caps(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use crate::pdo::SinkPdo;
+
+        let caps = SinkCapabilities::new(&[
+            SinkPdo::Fixed {
+                voltage_mv: 5000,
+                operational_current_ma: 3000,
+                dual_role_power: true,
+                higher_capability: false,
+                unconstrained_power: false,
+                usb_comm: true,
+                dual_role_data: true,
+            },
+            SinkPdo::Battery {
+                max_voltage_mv: 12000,
+                min_voltage_mv: 9000,
+                operational_power_mw: 15000,
+            },
+        ])
+        .unwrap();
+
+        let mut raw = [0u8; REG_TX_SINK_CAPS_LEN];
+        raw[0] = 2;
+        raw[1..5].copy_from_slice(&0x2601_912Cu32.to_le_bytes());
+        raw[5..9].copy_from_slice(&0x4F02_D03Cu32.to_le_bytes());
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_write(expected_addr, 0x33, raw));
+        transactions.push(create_register_read(expected_addr, 0x33, raw));
+        tps6699x.bus.update_expectations(&transactions);
+
+        tps6699x.set_tx_sink_caps(port, &caps).await.unwrap();
+        assert_eq!(tps6699x.get_tx_sink_caps(port).await.unwrap(), caps);
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_tx_sink_caps_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_tx_sink_caps(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_tx_sink_caps(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_tx_sink_caps_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_tx_sink_caps(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_tx_sink_caps(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_set_sink_policy(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::Status;
+
+        let policy = [(5000, 3000), (9000, 3000), (20000, 5000)];
+
+        // Detached, every entry is programmed
+        let mut raw = [0u8; REG_TX_SINK_CAPS_LEN];
+        raw[0] = 3;
+        raw[1..5].copy_from_slice(&0x0001_912Cu32.to_le_bytes());
+        raw[5..9].copy_from_slice(&0x0002_D12Cu32.to_le_bytes());
+        raw[9..13].copy_from_slice(&0x0006_41F4u32.to_le_bytes());
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x1A, Status::new_zero()));
+        transactions.push(create_register_write(expected_addr, 0x33, raw));
+        tps6699x.bus.update_expectations(&transactions);
+
+        tps6699x.set_sink_policy(port, &policy).await.unwrap();
+        tps6699x.bus.done();
+
+        // Attached to a 5V/3A, 9V/3A, 20V/3A source, 20V is selected and limited to the source's current
+        let mut status = Status::new_zero();
+        status.set_plug_present(true);
+        let mut source = [0u8; REG_RX_SOURCE_CAPS_LEN];
+        source[0] = 3;
+        source[1..5].copy_from_slice(&0x0001_912Cu32.to_le_bytes());
+        source[5..9].copy_from_slice(&0x0002_D12Cu32.to_le_bytes());
+        source[9..13].copy_from_slice(&0x0006_412Cu32.to_le_bytes());
+        let mut raw = [0u8; REG_TX_SINK_CAPS_LEN];
+        raw[0] = 2;
+        raw[1..5].copy_from_slice(&0x0001_912Cu32.to_le_bytes());
+        raw[5..9].copy_from_slice(&0x0006_412Cu32.to_le_bytes());
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x1A, status));
+        transactions.push(create_register_read(expected_addr, 0x30, source));
+        transactions.push(create_register_write(expected_addr, 0x33, raw));
+        tps6699x.bus.update_expectations(&transactions);
+
+        tps6699x.set_sink_policy(port, &policy).await.unwrap();
+        tps6699x.bus.done();
+
+        // Invalid policies don't touch the bus
+        let r = tps6699x.set_sink_policy(port, &[(9000, 2000), (9000, 3000)]).await;
+        assert!(matches!(r, Err(Error::Pd(PdError::InvalidParams))));
+    }
+
+    #[tokio::test]
+    async fn test_set_sink_policy_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_set_sink_policy(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_set_sink_policy(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_set_sink_policy_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_set_sink_policy(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_set_sink_policy(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_tx_identity(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        // Host identity with a DFP VDO
+        let identity = TxIdentity::new(&[0x2080_04B4, 0, 0x0001_0000, 0x2000_0000]).unwrap();
+        let mut raw = [0u8; REG_TX_IDENTITY_LEN];
+        raw[0] = 4;
+        raw[1..5].copy_from_slice(&0x2080_04B4u32.to_le_bytes());
+        raw[9..13].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+        raw[13..17].copy_from_slice(&0x2000_0000u32.to_le_bytes());
+
+        let mut nak = [0u8; REG_TX_IDENTITY_LEN];
+        nak[0] = 0x80;
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_write(expected_addr, 0x47, raw));
+        transactions.push(create_register_read(expected_addr, 0x47, raw));
+        transactions.push(create_register_write(expected_addr, 0x47, nak));
+        transactions.push(create_register_read(expected_addr, 0x47, nak));
+        tps6699x.bus.update_expectations(&transactions);
+
+        tps6699x.set_tx_identity(port, &identity).await.unwrap();
+        assert_eq!(tps6699x.get_tx_identity(port).await.unwrap(), identity);
+        tps6699x.set_tx_identity(port, &TxIdentity::nak()).await.unwrap();
+        assert!(tps6699x.get_tx_identity(port).await.unwrap().is_nak());
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_tx_identity_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_tx_identity(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_tx_identity(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_tx_identity_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_tx_identity(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_tx_identity(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_role_preference(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::PortConfig;
+
+        let mut config = PortConfig::new();
+        config.set_typec_support_options(registers::TypecSupportOptions::TrySnk);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x28, config));
+        tps6699x.bus.update_expectations(&transactions);
+        assert_eq!(
+            tps6699x.get_role_preference(port).await.unwrap(),
+            RolePreference::TrySnk
+        );
+        tps6699x.bus.done();
+
+        let mut expected = config;
+        expected.set_typec_support_options(registers::TypecSupportOptions::TrySrc);
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x28, config));
+        transactions.push(create_register_write(expected_addr, 0x28, expected));
+        tps6699x.bus.update_expectations(&transactions);
+        tps6699x
+            .set_role_preference(port, RolePreference::TrySrc)
+            .await
+            .unwrap();
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_role_preference_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_role_preference(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_role_preference(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_role_preference_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_role_preference(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_role_preference(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_default_power_role(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::PortConfig;
+        use registers::TypecStateMachine;
+
+        let config = PortConfig::new();
+        let mut sink = config;
+        sink.set_typec_state_machine(TypecStateMachine::Sink);
+
+        // Role is accepted
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x28, config));
+        transactions.push(create_register_write(expected_addr, 0x28, sink));
+        transactions.push(create_register_read(expected_addr, 0x28, sink));
+        tps6699x.bus.update_expectations(&transactions);
+        tps6699x.set_default_power_role(port, PowerRole::Sink).await.unwrap();
+        tps6699x.bus.done();
+
+        // Role is rejected, the controller keeps its previous configuration
+        let mut source = sink;
+        source.set_typec_state_machine(TypecStateMachine::Source);
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x28, sink));
+        transactions.push(create_register_write(expected_addr, 0x28, source));
+        transactions.push(create_register_read(expected_addr, 0x28, sink));
+        tps6699x.bus.update_expectations(&transactions);
+        assert!(matches!(
+            tps6699x.set_default_power_role(port, PowerRole::Source).await,
+            Err(Error::Pd(PdError::InvalidParams))
+        ));
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_default_power_role_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_default_power_role(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_default_power_role(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_default_power_role_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_default_power_role(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_default_power_role(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_set_port_role_policy(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::{PortConfig, PortControl, Status};
+        use registers::{LegacyMode, TypecStateMachine};
+
+        let config = PortConfig::new();
+        let mut sink = config;
+        sink.set_typec_state_machine(TypecStateMachine::Sink);
+        let mut disabled = config;
+        disabled.set_typec_state_machine(TypecStateMachine::Disabled);
+        let mut control = PortControl::new();
+        RolePolicy::SinkOnly.apply_swaps(&mut control);
+
+        // Not connected, only the configuration changes
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x1A, Status::new_zero()));
+        transactions.push(create_register_read(expected_addr, 0x29, PortControl::new()));
+        transactions.push(create_register_write(expected_addr, 0x29, control));
+        transactions.push(create_register_read(expected_addr, 0x28, config));
+        transactions.push(create_register_write(expected_addr, 0x28, sink));
+        transactions.push(create_register_read(expected_addr, 0x28, sink));
+        tps6699x.bus.update_expectations(&transactions);
+        tps6699x.set_port_role_policy(port, RolePolicy::SinkOnly).await.unwrap();
+        tps6699x.bus.done();
+
+        // Connected as source, the partner is detached before the new role is written
+        let mut status = Status::new_zero();
+        status.set_plug_present(true);
+        status.set_port_role(true);
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x1A, status));
+        transactions.push(create_register_read(expected_addr, 0x29, PortControl::new()));
+        transactions.push(create_register_write(expected_addr, 0x29, control));
+        transactions.push(create_register_read(expected_addr, 0x28, config));
+        transactions.push(create_register_write(expected_addr, 0x28, disabled));
+        transactions.push(create_register_write(expected_addr, 0x28, sink));
+        transactions.push(create_register_read(expected_addr, 0x28, sink));
+        tps6699x.bus.update_expectations(&transactions);
+        tps6699x.set_port_role_policy(port, RolePolicy::SinkOnly).await.unwrap();
+        tps6699x.bus.done();
+
+        // Connected as a dead battery sink, nothing changes
+        let mut status = Status::new_zero();
+        status.set_plug_present(true);
+        status.set_legacy(LegacyMode::LegacySinkDeadBattery);
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x1A, status));
+        tps6699x.bus.update_expectations(&transactions);
+        assert!(matches!(
+            tps6699x.set_port_role_policy(port, RolePolicy::SourceOnly).await,
+            Err(Error::Pd(PdError::InvalidMode))
+        ));
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_set_port_role_policy_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_set_port_role_policy(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_set_port_role_policy(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_set_port_role_policy_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_set_port_role_policy(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_set_port_role_policy(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_set_usb_data_enable(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::{DataStatus, PortControl};
+
+        for enable in [false, true] {
+            let mut control = PortControl::new();
+            control.set_usb_disable(!enable);
+
+            let mut transactions = Vec::new();
+            transactions.push(create_register_read(expected_addr, 0x5F, DataStatus::new_zero()));
+            transactions.push(create_register_read(expected_addr, 0x29, PortControl::new()));
+            transactions.push(create_register_write(expected_addr, 0x29, control));
+            tps6699x.bus.update_expectations(&transactions);
+
+            tps6699x.set_usb_data_enable(port, enable).await.unwrap();
+            tps6699x.bus.done();
+        }
+
+        // USB is tunnelled through Thunderbolt
+        let mut data_status = DataStatus::new_zero();
+        data_status.set_tbt_connection(true);
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x5F, data_status));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let r = tps6699x.set_usb_data_enable(port, false).await;
+        assert!(matches!(r, Err(Error::Pd(PdError::InvalidMode))));
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_set_usb_data_enable_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_set_usb_data_enable(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_set_usb_data_enable(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_set_usb_data_enable_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_set_usb_data_enable(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_set_usb_data_enable(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_set_auto_vdm_config(
+        tps6699x: &mut Tps6699x<Mock>,
+        port: PortId,
+        expected_addr: u8,
+        config: AutoVdmConfig,
+        expected: registers::field_sets::PortControl,
+    ) {
+        use registers::field_sets::PortControl;
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x29, PortControl::new()));
+        transactions.push(create_register_write(expected_addr, 0x29, expected));
+        tps6699x.bus.update_expectations(&transactions);
+
+        tps6699x.set_auto_vdm_config(port, config).await.unwrap();
+        tps6699x.bus.done();
+    }
+
+    async fn test_auto_vdm_config(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        use registers::field_sets::PortControl;
+
+        // Read back defaults
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x29, PortControl::new()));
+        tps6699x.bus.update_expectations(&transactions);
+        let config = tps6699x.get_auto_vdm_config(port).await.unwrap();
+        assert_eq!(config, AutoVdmConfig::default());
+        assert!(config.discover_identity);
+        assert!(config.discover_modes);
+        tps6699x.bus.done();
+
+        // Writing the defaults shouldn't change anything
+        test_set_auto_vdm_config(tps6699x, port, expected_addr, config, PortControl::new()).await;
+
+        // Toggle each bit independently
+        let mut expected = PortControl::new();
+        expected.set_automatic_id_request(false);
+        test_set_auto_vdm_config(
+            tps6699x,
+            port,
+            expected_addr,
+            AutoVdmConfig {
+                discover_identity: false,
+                discover_modes: true,
+            },
+            expected,
+        )
+        .await;
+
+        let mut expected = PortControl::new();
+        expected.set_am_intrusive_mode(true);
+        test_set_auto_vdm_config(
+            tps6699x,
+            port,
+            expected_addr,
+            AutoVdmConfig {
+                discover_identity: true,
+                discover_modes: false,
+            },
+            expected,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_auto_vdm_config_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_auto_vdm_config(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_auto_vdm_config(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_auto_vdm_config_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_auto_vdm_config(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_auto_vdm_config(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_get_selected_pdo_index(
+        tps6699x: &mut Tps6699x<Mock>,
+        port: PortId,
+        expected_addr: u8,
+        rdo: u32,
+        expected: u8,
+    ) {
+        use registers::field_sets::ActiveRdoContract;
+
+        let mut contract = ActiveRdoContract::new_zero();
+        contract.set_active_rdo(rdo);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x35, contract));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(tps6699x.get_selected_pdo_index(port).await.unwrap(), expected);
         tps6699x.bus.done();
     }
 
+    async fn test_get_selected_pdo_indices(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        // No contract
+        test_get_selected_pdo_index(tps6699x, port, expected_addr, 0, 0).await;
+        test_get_selected_pdo_index(tps6699x, port, expected_addr, 0x2012_c0c8, 2).await;
+        test_get_selected_pdo_index(tps6699x, port, expected_addr, 0x9000_0000, 9).await;
+    }
+
     #[tokio::test]
-    async fn test_get_active_rdo_contract_ports_0() {
+    async fn test_get_selected_pdo_index_0() {
         let mock = Mock::new(&[]);
         let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
 
-        test_get_active_rdo_contract(&mut tps6699x, PORT0, PORT0_ADDR0).await;
-        test_get_active_rdo_contract(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+        test_get_selected_pdo_indices(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_selected_pdo_indices(&mut tps6699x, PORT1, PORT1_ADDR0).await;
     }
 
     #[tokio::test]
-    async fn test_get_active_rdo_contract_ports_1() {
+    async fn test_get_selected_pdo_index_1() {
         let mock = Mock::new(&[]);
         let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
 
-        test_get_active_rdo_contract(&mut tps6699x, PORT0, PORT0_ADDR1).await;
-        test_get_active_rdo_contract(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+        test_get_selected_pdo_indices(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_selected_pdo_indices(&mut tps6699x, PORT1, PORT1_ADDR1).await;
+    }
+
+    async fn test_get_discovered_svids(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8) {
+        let mut raw = [0u8; REG_DISCOVERED_SVIDS_LEN];
+        // Two SOP SVIDs, discovery complete
+        raw[0] = 0x02;
+        raw[1] = 0x01;
+        raw[2..6].copy_from_slice(&[0x01, 0xFF, 0x87, 0x80]);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x21, raw));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let svids = tps6699x.get_discovered_svids(port).await.unwrap();
+        assert_eq!(svids.as_slice(), &[0xFF01, 0x8087]);
+        tps6699x.bus.done();
+
+        // Discovery not complete
+        raw[1] = 0x00;
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x21, raw));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert!(matches!(
+            tps6699x.get_discovered_svids(port).await,
+            Err(Error::Pd(PdError::Busy))
+        ));
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_discovered_svids_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        test_get_discovered_svids(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        test_get_discovered_svids(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_discovered_svids_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+
+        test_get_discovered_svids(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_get_discovered_svids(&mut tps6699x, PORT1, PORT1_ADDR1).await;
     }
 
     async fn test_get_mode(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8, expected_mode: Mode) {
@@ -541,6 +3211,9 @@ mod test {
         test_get_mode(tps6699x, expected_addr, Mode::App0).await;
         test_get_mode(tps6699x, expected_addr, Mode::App1).await;
         test_get_mode(tps6699x, expected_addr, Mode::Wtpr).await;
+        test_get_mode(tps6699x, expected_addr, Mode::Ptch).await;
+        // Unrecognized modes are passed through
+        test_get_mode(tps6699x, expected_addr, Mode::Unknown(u32_from_str("XYZW"))).await;
     }
 
     #[tokio::test]
@@ -557,6 +3230,104 @@ mod test {
         test_get_modes(&mut tps6699x, PORT0_ADDR1).await;
     }
 
+    async fn test_wait_for_app_mode(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8) {
+        use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+        use embedded_hal_mock::eh1::i2c::Transaction;
+
+        let mut delay = Delay {};
+        let mut transactions = Vec::new();
+        // Controller doesn't respond at all at first
+        transactions.push(
+            Transaction::write_read(expected_addr, std::vec![0x03], std::vec![0; 5])
+                .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+        );
+        transactions.push(create_register_read(expected_addr, 0x03, Mode::Boot));
+        transactions.push(create_register_read(expected_addr, 0x03, Mode::Boot));
+        transactions.push(create_register_read(expected_addr, 0x03, Mode::App0));
+        tps6699x.bus.update_expectations(&transactions);
+
+        tps6699x.wait_for_app_mode(&mut delay, 1000).await.unwrap();
+        tps6699x.bus.done();
+    }
+
+    async fn test_read_register_retry(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8) {
+        use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+        use embedded_hal_mock::eh1::i2c::Transaction;
+
+        let mut delay = Delay {};
+        let mut transactions = Vec::new();
+        // NAK, then busy, then the mode
+        transactions.push(
+            Transaction::write_read(expected_addr, std::vec![0x03], std::vec![0; 5])
+                .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+        );
+        transactions.push(Transaction::write_read(expected_addr, std::vec![0x03], std::vec![0; 5]));
+        transactions.push(create_register_read(expected_addr, 0x03, Mode::App0));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let mut buf = [0u8; 4];
+        tps6699x
+            .read_register_retry(PORT0, Register::Mode, &mut buf, &mut delay, 3, 1)
+            .await
+            .unwrap();
+        assert_eq!(&buf, b"APP0");
+        tps6699x.bus.done();
+
+        // Last error is returned once attempts are exhausted
+        let mut transactions = Vec::new();
+        transactions.push(
+            Transaction::write_read(expected_addr, std::vec![0x03], std::vec![0; 5])
+                .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+        );
+        transactions.push(Transaction::write_read(expected_addr, std::vec![0x03], std::vec![0; 5]));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let r = tps6699x
+            .read_register_retry(PORT0, Register::Mode, &mut buf, &mut delay, 2, 1)
+            .await;
+        assert!(matches!(r, Err(Error::Pd(PdError::Busy))));
+        tps6699x.bus.done();
+
+        let r = tps6699x
+            .read_register_retry(PORT0, Register::Mode, &mut buf, &mut delay, 0, 1)
+            .await;
+        assert!(matches!(r, Err(Error::Pd(PdError::InvalidParams))));
+    }
+
+    async fn test_wait_for_app_mode_timeout(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8) {
+        let mut delay = Delay {};
+        let mut transactions = Vec::new();
+        // Reads at 0, 10, and 30 ms
+        transactions.push(create_register_read(expected_addr, 0x03, Mode::Boot));
+        transactions.push(create_register_read(expected_addr, 0x03, Mode::Boot));
+        transactions.push(create_register_read(expected_addr, 0x03, Mode::Boot));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert!(matches!(
+            tps6699x.wait_for_app_mode(&mut delay, 30).await,
+            Err(Error::Pd(PdError::Timeout))
+        ));
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_app_mode_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_wait_for_app_mode(&mut tps6699x, PORT0_ADDR0).await;
+        test_read_register_retry(&mut tps6699x, PORT0_ADDR0).await;
+        test_wait_for_app_mode_timeout(&mut tps6699x, PORT0_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_app_mode_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_wait_for_app_mode(&mut tps6699x, PORT0_ADDR1).await;
+        test_read_register_retry(&mut tps6699x, PORT0_ADDR1).await;
+        test_wait_for_app_mode_timeout(&mut tps6699x, PORT0_ADDR1).await;
+    }
+
     async fn test_get_fw_version(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8, expected_version: u32) {
         let mut transactions = Vec::new();
         transactions.push(create_register_read(
@@ -585,6 +3356,169 @@ mod test {
         test_get_fw_version(&mut tps6699x, PORT0_ADDR1, TEST_FW_VERSION).await;
     }
 
+    async fn test_get_firmware_regions(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8) {
+        use registers::boot_flags::FirmwareRegion;
+        use registers::field_sets::BootFlags;
+
+        let mut flags = BootFlags::new_zero();
+        flags.set_active_region(true);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x2D, flags));
+        transactions.push(create_register_read(expected_addr, 0x0F, 0x0102_0304u32.to_le_bytes()));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let regions = tps6699x.get_firmware_regions().await.unwrap();
+        assert_eq!(regions.executing, FirmwareRegion::Region1);
+        assert_eq!(regions.staged, FirmwareRegion::Region0);
+        assert_eq!(regions.region1.version, Some(0x0102_0304));
+        assert!(regions.region0.valid && regions.region1.valid);
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_firmware_regions_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_get_firmware_regions(&mut tps6699x, PORT0_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_firmware_regions_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_get_firmware_regions(&mut tps6699x, PORT0_ADDR1).await;
+    }
+
+    async fn test_set_source_inhibit(tps6699x: &mut Tps6699x<Mock>, port: PortId, expected_addr: u8, config_addr: u8) {
+        use registers::field_sets::{ActiveRdoContract, Status, SystemConfig};
+        use registers::{PpextVbusSwConfig, VbusSwConfig};
+
+        let set_pp5v = |config: &mut SystemConfig, value| {
+            if port == PORT0 {
+                config.set_pa_pp_5_v_vbus_sw_config(value);
+            } else {
+                config.set_pb_pp_5_v_vbus_sw_config(value);
+            }
+        };
+        let mut config = SystemConfig::new_zero();
+        set_pp5v(&mut config, VbusSwConfig::Source);
+        let mut inhibited = config;
+        set_pp5v(&mut inhibited, VbusSwConfig::Disabled);
+
+        // Detached, the source switch is disabled
+        tps6699x.bus.update_expectations(&[
+            create_register_read(config_addr, 0x27, config),
+            create_register_read(expected_addr, 0x1A, Status::new_zero()),
+            create_register_read(config_addr, 0x27, config),
+            create_register_write(config_addr, 0x27, inhibited),
+        ]);
+        tps6699x.set_source_inhibit(port, true).await.unwrap();
+        tps6699x.bus.done();
+
+        // Sourcing under a contract is refused
+        let mut sourcing = Status::new_zero();
+        sourcing.set_plug_present(true);
+        sourcing.set_port_role(true);
+        let mut rdo = ActiveRdoContract::new_zero();
+        rdo.set_active_rdo(0x1304b12c);
+        tps6699x.bus.update_expectations(&[
+            create_register_read(config_addr, 0x27, config),
+            create_register_read(expected_addr, 0x1A, sourcing),
+            create_register_read(expected_addr, 0x35, rdo),
+        ]);
+        assert!(matches!(
+            tps6699x.set_source_inhibit(port, true).await,
+            Err(Error::Pd(PdError::InvalidMode))
+        ));
+        tps6699x.bus.done();
+
+        // So is sourcing through PPEXT
+        let mut ppext = config;
+        if port == PORT0 {
+            ppext.set_pa_ppext_vbus_sw_config(PpextVbusSwConfig::BiDirectional);
+        } else {
+            ppext.set_pb_ppext_vbus_sw_config(PpextVbusSwConfig::BiDirectional);
+        }
+        tps6699x
+            .bus
+            .update_expectations(&[create_register_read(config_addr, 0x27, ppext)]);
+        assert!(matches!(
+            tps6699x.set_source_inhibit(port, true).await,
+            Err(Error::Pd(PdError::InvalidMode))
+        ));
+        tps6699x.bus.done();
+
+        // Allowing sourcing doesn't need any checks
+        tps6699x.bus.update_expectations(&[
+            create_register_read(config_addr, 0x27, inhibited),
+            create_register_write(config_addr, 0x27, config),
+        ]);
+        tps6699x.set_source_inhibit(port, false).await.unwrap();
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_set_source_inhibit_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_set_source_inhibit(&mut tps6699x, PORT0, PORT0_ADDR0, PORT0_ADDR0).await;
+        test_set_source_inhibit(&mut tps6699x, PORT1, PORT1_ADDR0, PORT0_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_set_source_inhibit_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_set_source_inhibit(&mut tps6699x, PORT0, PORT0_ADDR1, PORT0_ADDR1).await;
+        test_set_source_inhibit(&mut tps6699x, PORT1, PORT1_ADDR1, PORT0_ADDR1).await;
+    }
+
+    async fn test_i2c_timeout(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8) {
+        use registers::field_sets::SystemConfig;
+        use registers::I2cTimeout;
+
+        let config = SystemConfig::new();
+        let mut expected = config;
+        expected.set_i_2_c_timeout(I2cTimeout::Timeout1000ms);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x27, config));
+        transactions.push(create_register_read(expected_addr, 0x27, config));
+        transactions.push(create_register_write(expected_addr, 0x27, expected));
+        transactions.push(create_register_read(expected_addr, 0x27, expected));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(tps6699x.get_i2c_timeout().await.unwrap(), I2cTimeout::Timeout125ms);
+        tps6699x.set_i2c_timeout(I2cTimeout::Timeout1000ms).await.unwrap();
+        tps6699x.bus.done();
+
+        // Controller ignores the write
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x27, config));
+        transactions.push(create_register_write(expected_addr, 0x27, expected));
+        transactions.push(create_register_read(expected_addr, 0x27, config));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let r = tps6699x.set_i2c_timeout(I2cTimeout::Timeout1000ms).await;
+        assert!(matches!(r, Err(Error::Pd(PdError::InvalidParams))));
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_i2c_timeout_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_i2c_timeout(&mut tps6699x, PORT0_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_i2c_timeout_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_i2c_timeout(&mut tps6699x, PORT0_ADDR1).await;
+    }
+
     async fn test_get_customer_use(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8, expected_value: u64) {
         let mut transactions = Vec::new();
         transactions.push(create_register_read(expected_addr, 0x06, expected_value.to_le_bytes()));
@@ -608,4 +3542,237 @@ mod test {
         let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
         test_get_customer_use(&mut tps6699x, PORT0_ADDR1, TEST_CUSTOMER_USE).await;
     }
+
+    /// Pads an ASCII string with null bytes to the length of the register
+    fn padded_ascii<const N: usize>(value: &[u8]) -> [u8; N] {
+        let mut buf = [0u8; N];
+        buf[..value.len()].copy_from_slice(value);
+        buf
+    }
+
+    async fn test_config(tps6699x: &mut Tps6699x<Mock>, expected_addrs: [u8; 2]) {
+        use registers::field_sets::{IntEventBus1, PortConfig, PortControl, SystemConfig};
+
+        let mut system_config = SystemConfig::new_zero();
+        system_config.set_enable_spm(true);
+        let mut port_config = PortConfig::new_zero();
+        port_config.set_typec_state_machine(registers::TypecStateMachine::Drp);
+        let mut mask = IntEventBus1::new_zero();
+        mask.set_cmd_1_completed(true);
+
+        let mut sink_caps = [0u8; REG_TX_SINK_CAPS_LEN];
+        sink_caps[0] = 1;
+        sink_caps[1..5].copy_from_slice(&0x0001_912Cu32.to_le_bytes());
+        let mut identity = [0u8; REG_TX_IDENTITY_LEN];
+        identity[0] = 3;
+        identity[1..5].copy_from_slice(&0x2080_04B4u32.to_le_bytes());
+
+        let mut transactions = Vec::new();
+        for addr in expected_addrs {
+            transactions.push(create_register_read(addr, 0x28, port_config));
+            transactions.push(create_register_read(addr, 0x29, PortControl::new_zero()));
+            transactions.push(create_register_read(addr, 0x16, mask));
+            transactions.push(create_register_read(addr, 0x33, sink_caps));
+            transactions.push(create_register_read(addr, 0x47, identity));
+        }
+        transactions.push(create_register_read(expected_addrs[0], 0x27, system_config));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let config = tps6699x.export_config().await.unwrap();
+        assert_eq!(config.system_config, system_config);
+        assert_eq!(config.ports.len(), 2);
+        for port in &config.ports {
+            assert_eq!(port.port_config, port_config);
+            assert_eq!(port.interrupt_mask, mask);
+            assert_eq!(
+                port.tx_sink_caps,
+                SinkCapabilities::from_policy(&[(5000, 3000)]).unwrap()
+            );
+            assert_eq!(port.tx_identity, TxIdentity::new(&[0x2080_04B4, 0, 0]).unwrap());
+        }
+        tps6699x.bus.done();
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_write(expected_addrs[0], 0x27, system_config));
+        for addr in expected_addrs {
+            transactions.push(create_register_write(addr, 0x28, port_config));
+            transactions.push(create_register_write(addr, 0x29, PortControl::new_zero()));
+            transactions.push(create_register_write(addr, 0x33, sink_caps));
+            transactions.push(create_register_write(addr, 0x47, identity));
+            transactions.push(create_register_write(addr, 0x16, mask));
+        }
+        tps6699x.bus.update_expectations(&transactions);
+        tps6699x.apply_config(&config).await.unwrap();
+        tps6699x.bus.done();
+
+        // A snapshot for a different number of ports is rejected without touching the bus
+        let mut single = config.clone();
+        single.ports.pop();
+        tps6699x.bus.update_expectations(&[]);
+        assert!(matches!(
+            tps6699x.apply_config(&single).await,
+            Err(Error::Pd(PdError::InvalidParams))
+        ));
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_config_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_config(&mut tps6699x, [PORT0_ADDR0, PORT1_ADDR0]).await;
+    }
+
+    #[tokio::test]
+    async fn test_config_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_config(&mut tps6699x, [PORT0_ADDR1, PORT1_ADDR1]).await;
+    }
+
+    #[tokio::test]
+    async fn test_bus_error_hook() {
+        use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+        use embedded_hal_mock::eh1::i2c::Transaction;
+
+        fn nak_is_busy(error: ErrorKind) -> Error<ErrorKind> {
+            match error {
+                ErrorKind::NoAcknowledge(_) => Error::Pd(PdError::Busy),
+                e => Error::Bus(e),
+            }
+        }
+
+        let mut transactions = Vec::new();
+        transactions.push(
+            Transaction::write_read(PORT0_ADDR0, std::vec![0x03], std::vec![0; 5])
+                .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+        );
+        transactions.push(
+            Transaction::write_read(PORT0_ADDR0, std::vec![0x03], std::vec![0; 5])
+                .with_error(ErrorKind::ArbitrationLoss),
+        );
+
+        let mock = Mock::new(&transactions);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        tps6699x.set_bus_error_hook(nak_is_busy);
+        assert!(matches!(tps6699x.get_mode().await, Err(Error::Pd(PdError::Busy))));
+        assert!(matches!(
+            tps6699x.get_mode().await,
+            Err(Error::Bus(ErrorKind::ArbitrationLoss))
+        ));
+        tps6699x.bus.done();
+    }
+
+    async fn test_get_uid(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8) {
+        let uid: [u8; registers::REG_UID_LEN] = core::array::from_fn(|i| i as u8);
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x05, uid));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(tps6699x.get_uid().await.unwrap(), uid);
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_uid_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_get_uid(&mut tps6699x, PORT0_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_uid_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_get_uid(&mut tps6699x, PORT0_ADDR1).await;
+    }
+
+    async fn test_get_build_description(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8) {
+        // Non-printable characters and trailing whitespace should be dropped
+        let raw: [u8; registers::REG_BUILD_DESCRIPTION_LEN] = padded_ascii(b"TPS6699x\x01 HW0001 FW0010.00.01  ");
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x2E, raw));
+        transactions.push(create_register_read(expected_addr, 0x2E, raw));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(tps6699x.get_build_description_raw().await.unwrap(), raw);
+        assert_eq!(
+            tps6699x.get_build_description().await.unwrap().as_str(),
+            "TPS6699x HW0001 FW0010.00.01"
+        );
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_build_description_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_get_build_description(&mut tps6699x, PORT0_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_build_description_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_get_build_description(&mut tps6699x, PORT0_ADDR1).await;
+    }
+
+    async fn test_get_device_info(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8) {
+        // Anything after the null terminator should be ignored
+        let raw: [u8; registers::REG_DEVICE_INFO_LEN] = padded_ascii(b"TPS66994 \x00garbage");
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x2F, raw));
+        transactions.push(create_register_read(expected_addr, 0x2F, raw));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(tps6699x.get_device_info_raw().await.unwrap(), raw);
+        assert_eq!(tps6699x.get_device_info().await.unwrap().as_str(), "TPS66994");
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_device_info_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_get_device_info(&mut tps6699x, PORT0_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_device_info_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_get_device_info(&mut tps6699x, PORT0_ADDR1).await;
+    }
+
+    async fn test_get_chip_id(tps6699x: &mut Tps6699x<Mock>, expected_addr: u8) {
+        use crate::registers::chip_id::PartNumber;
+
+        let raw: [u8; registers::REG_DEVICE_INFO_LEN] = padded_ascii(b"TPS66994 HW0011");
+
+        let mut transactions = Vec::new();
+        transactions.push(create_register_read(expected_addr, 0x2F, raw));
+        tps6699x.bus.update_expectations(&transactions);
+
+        let id = tps6699x.get_chip_id().await.unwrap();
+        assert_eq!(id.part_number, Some(PartNumber::Tps66994));
+        assert_eq!(id.revision, Some(0x11));
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_chip_id_0() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        test_get_chip_id(&mut tps6699x, PORT0_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_chip_id_1() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR1);
+        test_get_chip_id(&mut tps6699x, PORT0_ADDR1).await;
+    }
 }
@@ -4,6 +4,25 @@ use embedded_usb_pd::{Error, PdError, PortId};
 
 use crate::MAX_SUPPORTED_PORTS;
 
+/// Trait for anything that reports whether the controller's interrupt is asserted
+///
+/// For boards where the interrupt isn't a bare GPIO, e.g. when it's behind a GPIO expander or aggregated with other
+/// interrupt sources. A plain synchronous check can be a closure returning `Result<bool, E>`.
+pub trait InterruptLine {
+    type Error;
+
+    /// Returns true if the interrupt is asserted
+    fn is_asserted(&mut self) -> impl Future<Output = Result<bool, Self::Error>>;
+}
+
+impl<E, F: FnMut() -> Result<bool, E>> InterruptLine for F {
+    type Error = E;
+
+    async fn is_asserted(&mut self) -> Result<bool, Self::Error> {
+        self()
+    }
+}
+
 /// Trait for any type that restores the original interrupt state when dropped
 #[allow(drop_bounds)]
 pub trait InterruptGuard: Drop {}
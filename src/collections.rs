@@ -0,0 +1,61 @@
+//! Collection types returned by getters that produce variable-length data
+//!
+//! Variable-length data is always returned in fixed-capacity [`heapless`] collections so the crate stays `no_std`
+//! and allocation-free. Capacities are the most the controller can report:
+//! - [`BuildDescription`]: [`REG_BUILD_DESCRIPTION_LEN`] characters
+//! - [`DeviceInfo`]: [`REG_DEVICE_INFO_LEN`] characters
+//! - [`SvidList`]: [`MAX_SOP_SVIDS`] SVIDs
+//!
+//! Host tools can enable the `alloc` feature to convert these into `alloc` collections with [`IntoAlloc`].
+use crate::registers::discovered_svids::MAX_SOP_SVIDS;
+use crate::registers::{REG_BUILD_DESCRIPTION_LEN, REG_DEVICE_INFO_LEN};
+
+/// Build description string
+pub type BuildDescription = heapless::String<REG_BUILD_DESCRIPTION_LEN>;
+/// Device info string
+pub type DeviceInfo = heapless::String<REG_DEVICE_INFO_LEN>;
+/// List of SVIDs discovered from the port partner
+pub type SvidList = heapless::Vec<u16, MAX_SOP_SVIDS>;
+
+/// Trait for converting fixed-capacity collections into their `alloc` equivalent
+#[cfg(feature = "alloc")]
+pub trait IntoAlloc {
+    type Output;
+
+    fn into_alloc(self) -> Self::Output;
+}
+
+#[cfg(feature = "alloc")]
+impl<T, const N: usize> IntoAlloc for heapless::Vec<T, N> {
+    type Output = alloc::vec::Vec<T>;
+
+    fn into_alloc(self) -> Self::Output {
+        self.into_iter().collect()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<const N: usize> IntoAlloc for heapless::String<N> {
+    type Output = alloc::string::String;
+
+    fn into_alloc(self) -> Self::Output {
+        alloc::string::String::from(self.as_str())
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_into_alloc() {
+        let mut svids = SvidList::new();
+        svids.push(0xFF01).unwrap();
+        svids.push(0x8087).unwrap();
+        assert_eq!(svids.into_alloc(), alloc::vec![0xFF01, 0x8087]);
+
+        let mut info = DeviceInfo::new();
+        info.push_str("TPS66994").unwrap();
+        assert_eq!(info.into_alloc(), "TPS66994");
+    }
+}
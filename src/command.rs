@@ -2,7 +2,7 @@ use bincode::de::Decoder;
 use bincode::enc::Encoder;
 use bincode::error::{DecodeError, EncodeError};
 use bincode::{Decode, Encode};
-use embedded_usb_pd::PdError;
+use embedded_usb_pd::{PdError, PortId};
 
 /// Length of a command
 const CMD_LEN: usize = 4;
@@ -45,6 +45,20 @@ pub enum Command {
     Srdy = u32_from_str("SRDY"),
     /// SRDY reset
     Sryr = u32_from_str("SRYR"),
+
+    /// PD soft reset
+    Srst = u32_from_str("SRST"),
+
+    /// Clear dead battery flag
+    Dbfg = u32_from_str("DBfg"),
+
+    /// Built-in self test, PD compliance test modes
+    Bist = u32_from_str("BIST"),
+
+    /// Send a Get_Source_Cap message to the port partner
+    Gsrc = u32_from_str("GSrC"),
+    /// Send a Get_Sink_Cap message to the port partner
+    Gskc = u32_from_str("GSkC"),
 }
 
 impl Command {
@@ -378,6 +392,63 @@ pub(crate) const SRDY_TIMEOUT_MS: u32 = 250;
 /// Timeout for completion of SRYR command, determined by experimentation
 #[allow(dead_code)]
 pub(crate) const SRYR_TIMEOUT_MS: u32 = 250;
+/// Timeout for completion of SRST command
+#[allow(dead_code)]
+pub(crate) const SRST_TIMEOUT_MS: u32 = 250;
+/// Timeout for completion of DBfg command
+#[allow(dead_code)]
+pub(crate) const DBFG_TIMEOUT_MS: u32 = 250;
+/// Timeout for completion of BIST command
+#[allow(dead_code)]
+pub(crate) const BIST_TIMEOUT_MS: u32 = 250;
+/// BIST argument to exit the shared test mode
+#[allow(dead_code)]
+pub(crate) const BIST_EXIT_SHARED_TEST: u8 = 0xA;
+/// Timeout for completion of GSrC and GSkC commands, which complete once the request is sent
+#[allow(dead_code)]
+pub(crate) const GET_CAPS_TIMEOUT_MS: u32 = 250;
+/// Time to wait for the partner's capabilities after a GSrC or GSkC command, well above tSenderResponse
+#[allow(dead_code)]
+pub(crate) const PARTNER_CAPS_TIMEOUT_MS: u32 = 500;
+/// Time to wait for a new contract after a GSrC command, covers the partner's capabilities and tPSTransition
+#[allow(dead_code)]
+pub(crate) const CONTRACT_TIMEOUT_MS: u32 = 1000;
+
+/// PD compliance BIST mode, values match the BIST data object mode field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BistMode {
+    /// Transmit a continuous carrier, ends on its own after tBISTContMode
+    Carrier,
+    /// Receive test data frames, the partner sends test frames which the port ignores until a hard reset
+    TestData,
+    /// Enter the shared capacity test mode, lasts until explicitly exited
+    SharedTest,
+}
+
+impl From<BistMode> for u8 {
+    fn from(value: BistMode) -> Self {
+        match value {
+            BistMode::Carrier => 0x5,
+            BistMode::TestData => 0x8,
+            BistMode::SharedTest => 0x9,
+        }
+    }
+}
+
+impl TryFrom<u8> for BistMode {
+    type Error = PdError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x5 => Ok(BistMode::Carrier),
+            0x8 => Ok(BistMode::TestData),
+            0x9 => Ok(BistMode::SharedTest),
+            _ => Err(PdError::InvalidParams),
+        }
+    }
+}
+
 /// Srdy switch to enable
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -421,6 +492,73 @@ pub struct TfudArgs {
     pub broadcast_u16_address: u16,
 }
 
+/// Poll interval while waiting for a batched command to complete
+pub(crate) const BATCH_POLL_DELAY_MS: u32 = 1;
+
+/// A single command in a [`CommandBatch`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BatchEntry<'a> {
+    /// Port to execute the command on
+    pub port: PortId,
+    /// Command to execute
+    pub cmd: Command,
+    /// Data written to the data register before the command is issued
+    pub data: Option<&'a [u8]>,
+    /// Time to wait for the command to complete
+    pub timeout_ms: u32,
+}
+
+/// Sequence of up to `N` commands executed in order, stopping at the first failure
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CommandBatch<'a, const N: usize> {
+    entries: heapless::Vec<BatchEntry<'a>, N>,
+}
+
+impl<'a, const N: usize> CommandBatch<'a, N> {
+    /// Create an empty batch
+    pub fn new() -> Self {
+        Self {
+            entries: heapless::Vec::new(),
+        }
+    }
+
+    /// Append a command to the batch, returns `InvalidParams` if the batch is full
+    pub fn push(
+        &mut self,
+        port: PortId,
+        cmd: Command,
+        data: Option<&'a [u8]>,
+        timeout_ms: u32,
+    ) -> Result<&mut Self, PdError> {
+        self.entries
+            .push(BatchEntry {
+                port,
+                cmd,
+                data,
+                timeout_ms,
+            })
+            .map_err(|_| PdError::InvalidParams)?;
+        Ok(self)
+    }
+
+    /// Returns the commands in the batch
+    pub fn entries(&self) -> &[BatchEntry<'a>] {
+        &self.entries
+    }
+
+    /// Returns the number of commands in the batch
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the batch contains no commands
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use bincode::config;
@@ -546,4 +684,44 @@ mod test {
             bincode::decode_from_slice(&buf, config::standard().with_fixed_int_encoding()).unwrap();
         assert_eq!(decoded, args);
     }
+
+    #[test]
+    fn test_command_batch_push() {
+        let data = [0x02];
+        let mut batch: CommandBatch<'_, 2> = CommandBatch::new();
+        assert!(batch.is_empty());
+
+        batch
+            .push(PortId(0), Command::Srdy, Some(&data), SRDY_TIMEOUT_MS)
+            .unwrap()
+            .push(PortId(1), Command::Sryr, None, SRYR_TIMEOUT_MS)
+            .unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(
+            batch.entries()[0],
+            BatchEntry {
+                port: PortId(0),
+                cmd: Command::Srdy,
+                data: Some(&data),
+                timeout_ms: SRDY_TIMEOUT_MS,
+            }
+        );
+
+        // Batch is full
+        assert_eq!(
+            batch.push(PortId(0), Command::Sryr, None, SRYR_TIMEOUT_MS).err(),
+            Some(PdError::InvalidParams)
+        );
+    }
+
+    #[test]
+    fn test_bist_mode() {
+        for mode in [BistMode::Carrier, BistMode::TestData, BistMode::SharedTest] {
+            assert_eq!(BistMode::try_from(u8::from(mode)), Ok(mode));
+        }
+
+        // Exit and reserved modes aren't entry modes
+        assert_eq!(BistMode::try_from(BIST_EXIT_SHARED_TEST), Err(PdError::InvalidParams));
+        assert_eq!(BistMode::try_from(0x0), Err(PdError::InvalidParams));
+    }
 }
@@ -1,11 +1,19 @@
 #![no_std]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 use embedded_usb_pd::{PdError, PortId};
 
+// Only the raw alert received flag reaches the driver, the decoder is kept for when the data object is readable
+#[allow(dead_code)]
+mod alert;
 pub mod asynchronous;
+pub mod collections;
 pub mod command;
 pub mod fmt;
 pub(crate) mod fw_update;
+pub mod pdo;
 
 /// I2C address set 0
 pub const ADDR0: [u8; 2] = [0x20, 0x24];
@@ -19,6 +27,26 @@ pub const TPS66993_NUM_PORTS: usize = 1;
 /// Maximum number of ports supported by any device
 pub const MAX_SUPPORTED_PORTS: usize = 2;
 
+/// Default I2C bus speed, command delays are tuned for this speed
+pub const DEFAULT_BUS_SPEED_HZ: u32 = 400_000;
+/// Slowest bus speed considered when scaling command delays
+pub const MIN_BUS_SPEED_HZ: u32 = 100_000;
+/// Fastest bus speed considered when scaling command delays
+pub const MAX_BUS_SPEED_HZ: u32 = 1_000_000;
+
+/// Typical time for the controller to reach app mode after power-on or reset
+///
+/// Boot can take significantly longer when the controller has to wait for power or load a patch bundle,
+/// timeouts of at least twice this value are recommended.
+pub const TYPICAL_BOOT_TIME_MS: u32 = 1000;
+
+/// Recommended number of attempts for register reads retried while the controller boots
+///
+/// Combined with [`BOOT_READ_RETRY_DELAY_MS`] this covers twice [`TYPICAL_BOOT_TIME_MS`].
+pub const BOOT_READ_RETRY_ATTEMPTS: u32 = 25;
+/// Recommended initial delay between register reads retried while the controller boots
+pub const BOOT_READ_RETRY_DELAY_MS: u32 = 10;
+
 /// Port 0 constant
 pub const PORT0: PortId = PortId(0);
 /// Port 1 constant
@@ -32,11 +60,139 @@ pub mod registers {
         manifest: "device.yaml"
     );
 
+    pub mod boot_flags;
+    pub mod cc_status;
+    pub mod chip_id;
+    pub mod controller_config;
+    pub mod discovered_svids;
+    pub mod dump;
+    pub mod pd_events;
+    pub mod port_config;
+    pub mod port_control;
+    pub mod power_path;
+    pub mod reset_cause;
+    pub mod rx_caps;
+    pub mod status_change;
+    pub mod telemetry;
+    pub mod tx_identity;
+    pub mod tx_sink_caps;
+    pub mod usb_status;
+
+    /// Register addresses and lengths, single source of truth for registers accessed without device_driver
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    #[repr(u8)]
+    pub enum Register {
+        /// Controller operation mode
+        Mode = 0x03,
+        /// Unique ID, 128-bit die-specific identifier returned least-significant byte first
+        Uid = 0x05,
+        /// Customer use
+        CustomerUse = 0x06,
+        /// Command 1
+        Cmd1 = 0x08,
+        /// Command data 1
+        Data1 = 0x09,
+        /// Boot FW version
+        Version = 0x0F,
+        /// Asserted interrupts for I2C1
+        IntEventBus1 = 0x14,
+        /// Masked interrupts for I2C1
+        IntMaskBus1 = 0x16,
+        /// Interrupt clear for I2C1
+        IntClearBus1 = 0x18,
+        /// Port status
+        Status = 0x1A,
+        /// Discovered SVIDs
+        DiscoveredSvids = 0x21,
+        /// Power path status
+        PowerPathStatus = 0x26,
+        /// Global system configuration
+        SystemConfig = 0x27,
+        /// Port configuration
+        PortConfig = 0x28,
+        /// Port control
+        PortControl = 0x29,
+        /// Boot status
+        BootFlags = 0x2D,
+        /// Build description, ASCII string
+        BuildDescription = 0x2E,
+        /// Device info, ASCII string
+        DeviceInfo = 0x2F,
+        /// Received source capabilities
+        RxSourceCaps = 0x30,
+        /// Received sink capabilities
+        RxSinkCaps = 0x31,
+        /// Transmit sink capabilities
+        TxSinkCaps = 0x33,
+        /// Active PDO contract
+        ActivePdoContract = 0x34,
+        /// Active RDO contract
+        ActiveRdoContract = 0x35,
+        /// PD status
+        PdStatus = 0x40,
+        /// Transmit identity, the response to Discover Identity
+        TxIdentity = 0x47,
+        /// Application-defined mailbox for host and embedded controller communication
+        Mailbox = 0x4F,
+        /// Data status
+        DataStatus = 0x5F,
+    }
+
+    impl Register {
+        /// Register address
+        pub const fn addr(self) -> u8 {
+            self as u8
+        }
+
+        /// Register size in bytes
+        pub const fn size(self) -> usize {
+            match self {
+                Register::Mode | Register::Cmd1 | Register::Version | Register::BootFlags | Register::PdStatus => 4,
+                Register::Uid | Register::ActiveRdoContract => 16,
+                Register::CustomerUse | Register::PortConfig | Register::PortControl => 8,
+                Register::Data1 | Register::Mailbox => 64,
+                Register::IntEventBus1 | Register::IntMaskBus1 | Register::IntClearBus1 => 11,
+                Register::Status | Register::PowerPathStatus | Register::DataStatus => 5,
+                Register::DiscoveredSvids => discovered_svids::REG_DISCOVERED_SVIDS_LEN,
+                Register::SystemConfig => 15,
+                Register::BuildDescription => 49,
+                Register::DeviceInfo => 40,
+                // Received capabilities share the transmit sink capabilities layout
+                Register::RxSourceCaps | Register::RxSinkCaps | Register::TxSinkCaps => {
+                    tx_sink_caps::REG_TX_SINK_CAPS_LEN
+                }
+                Register::ActivePdoContract => 6,
+                Register::TxIdentity => tx_identity::REG_TX_IDENTITY_LEN,
+            }
+        }
+    }
+
     /// Command data 1 register
     /// This register is 512 bits and exceeds the maximum support by device_driver
-    pub const REG_DATA1: u8 = 0x09;
+    pub const REG_DATA1: u8 = Register::Data1.addr();
     // Command data 1 register length
-    pub const REG_DATA1_LEN: usize = 64;
+    pub const REG_DATA1_LEN: usize = Register::Data1.size();
+
+    /// Unique ID register, 128-bit die-specific identifier returned least-significant byte first
+    pub const REG_UID: u8 = Register::Uid.addr();
+    /// Unique ID register length
+    pub const REG_UID_LEN: usize = Register::Uid.size();
+
+    /// Mailbox register, contents are application-defined
+    pub const REG_MAILBOX: u8 = Register::Mailbox.addr();
+    /// Mailbox register length
+    pub const REG_MAILBOX_LEN: usize = Register::Mailbox.size();
+
+    /// Build description register, ASCII string
+    pub const REG_BUILD_DESCRIPTION: u8 = Register::BuildDescription.addr();
+    /// Build description register length
+    pub const REG_BUILD_DESCRIPTION_LEN: usize = Register::BuildDescription.size();
+
+    /// Device info register, ASCII string
+    pub const REG_DEVICE_INFO: u8 = Register::DeviceInfo.addr();
+    /// Device info register length
+    pub const REG_DEVICE_INFO_LEN: usize = Register::DeviceInfo.size();
 
     impl TryFrom<TypecCurrent> for type_c::Current {
         type Error = PdError;
@@ -83,27 +239,128 @@ pub mod registers {
             }
         }
     }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        /// Returns the length of a field set
+        fn field_set_size<const N: usize, T: Into<[u8; N]>>(_: T) -> usize {
+            N
+        }
+
+        #[test]
+        fn test_register_addresses() {
+            let expected = [
+                (Register::Mode, 0x03),
+                (Register::Uid, 0x05),
+                (Register::CustomerUse, 0x06),
+                (Register::Cmd1, 0x08),
+                (Register::Data1, 0x09),
+                (Register::Version, 0x0F),
+                (Register::IntEventBus1, 0x14),
+                (Register::IntMaskBus1, 0x16),
+                (Register::IntClearBus1, 0x18),
+                (Register::Status, 0x1A),
+                (Register::DiscoveredSvids, 0x21),
+                (Register::PowerPathStatus, 0x26),
+                (Register::SystemConfig, 0x27),
+                (Register::PortConfig, 0x28),
+                (Register::PortControl, 0x29),
+                (Register::BootFlags, 0x2D),
+                (Register::BuildDescription, 0x2E),
+                (Register::DeviceInfo, 0x2F),
+                (Register::RxSourceCaps, 0x30),
+                (Register::RxSinkCaps, 0x31),
+                (Register::TxSinkCaps, 0x33),
+                (Register::ActivePdoContract, 0x34),
+                (Register::ActiveRdoContract, 0x35),
+                (Register::PdStatus, 0x40),
+                (Register::TxIdentity, 0x47),
+                (Register::Mailbox, 0x4F),
+                (Register::DataStatus, 0x5F),
+            ];
+
+            for (register, addr) in expected {
+                assert_eq!(register.addr(), addr, "{:?}", register);
+            }
+        }
+
+        #[test]
+        fn test_register_sizes() {
+            use field_sets::*;
+
+            // Registers accessed through device_driver must agree with the manifest
+            assert_eq!(field_set_size(Mode::new()), Register::Mode.size());
+            assert_eq!(field_set_size(CustomerUse::new()), Register::CustomerUse.size());
+            assert_eq!(field_set_size(Cmd1::new()), Register::Cmd1.size());
+            assert_eq!(field_set_size(Version::new()), Register::Version.size());
+            assert_eq!(field_set_size(IntEventBus1::new()), Register::IntEventBus1.size());
+            assert_eq!(field_set_size(IntEventBus1::new()), Register::IntMaskBus1.size());
+            assert_eq!(field_set_size(IntEventBus1::new()), Register::IntClearBus1.size());
+            assert_eq!(field_set_size(Status::new()), Register::Status.size());
+            assert_eq!(field_set_size(PowerPathStatus::new()), Register::PowerPathStatus.size());
+            assert_eq!(field_set_size(SystemConfig::new()), Register::SystemConfig.size());
+            assert_eq!(field_set_size(PortConfig::new()), Register::PortConfig.size());
+            assert_eq!(field_set_size(PortControl::new()), Register::PortControl.size());
+            assert_eq!(field_set_size(BootFlags::new()), Register::BootFlags.size());
+            assert_eq!(
+                field_set_size(ActivePdoContract::new()),
+                Register::ActivePdoContract.size()
+            );
+            assert_eq!(
+                field_set_size(ActiveRdoContract::new()),
+                Register::ActiveRdoContract.size()
+            );
+            assert_eq!(field_set_size(PdStatus::new()), Register::PdStatus.size());
+            assert_eq!(field_set_size(DataStatus::new()), Register::DataStatus.size());
+        }
+    }
 }
 
+/// Raw value of [`Mode::Boot`]
+const MODE_BOOT: u32 = u32_from_str("BOOT");
+/// Raw value of [`Mode::F211`]
+const MODE_F211: u32 = u32_from_str("F211");
+/// Raw value of [`Mode::App0`]
+const MODE_APP0: u32 = u32_from_str("APP0");
+/// Raw value of [`Mode::App1`]
+const MODE_APP1: u32 = u32_from_str("APP1");
+/// Raw value of [`Mode::Wtpr`]
+const MODE_WTPR: u32 = u32_from_str("WTPR");
+/// Raw value of [`Mode::Ptch`]
+const MODE_PTCH: u32 = u32_from_str("PTCH");
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Mode {
     /// Boot mode
-    Boot = u32_from_str("BOOT"),
+    Boot = MODE_BOOT,
     /// Firmware corrupt on both banks
-    F211 = u32_from_str("F211"),
+    F211 = MODE_F211,
     /// Before app config
-    App0 = u32_from_str("APP0"),
+    App0 = MODE_APP0,
     /// After app config
-    App1 = u32_from_str("APP1"),
+    App1 = MODE_APP1,
     /// App FW waiting for power
-    Wtpr = u32_from_str("WTPR"),
+    Wtpr = MODE_WTPR,
+    /// Waiting for a patch bundle to be loaded
+    Ptch = MODE_PTCH,
+    /// Mode not recognized by this driver, e.g. from a newer firmware revision
+    Unknown(u32),
 }
 
 impl PartialEq<u32> for Mode {
     fn eq(&self, other: &u32) -> bool {
-        *self as u32 == *other
+        u32::from(*self) == *other
+    }
+}
+
+impl Mode {
+    /// Converts a raw mode value, mapping unrecognized values to [`Mode::Unknown`]
+    pub fn from_raw(value: u32) -> Self {
+        Mode::try_from(value).unwrap_or(Mode::Unknown(value))
     }
 }
 
@@ -111,18 +368,28 @@ impl TryFrom<u32> for Mode {
     type Error = PdError;
 
     fn try_from(value: u32) -> Result<Self, Self::Error> {
-        if Mode::Boot == value {
-            Ok(Mode::Boot)
-        } else if Mode::F211 == value {
-            Ok(Mode::F211)
-        } else if Mode::App0 == value {
-            Ok(Mode::App0)
-        } else if Mode::App1 == value {
-            Ok(Mode::App1)
-        } else if Mode::Wtpr == value {
-            Ok(Mode::Wtpr)
-        } else {
-            Err(PdError::InvalidParams)
+        match value {
+            MODE_BOOT => Ok(Mode::Boot),
+            MODE_F211 => Ok(Mode::F211),
+            MODE_APP0 => Ok(Mode::App0),
+            MODE_APP1 => Ok(Mode::App1),
+            MODE_WTPR => Ok(Mode::Wtpr),
+            MODE_PTCH => Ok(Mode::Ptch),
+            _ => Err(PdError::InvalidParams),
+        }
+    }
+}
+
+impl From<Mode> for u32 {
+    fn from(value: Mode) -> Self {
+        match value {
+            Mode::Boot => MODE_BOOT,
+            Mode::F211 => MODE_F211,
+            Mode::App0 => MODE_APP0,
+            Mode::App1 => MODE_APP1,
+            Mode::Wtpr => MODE_WTPR,
+            Mode::Ptch => MODE_PTCH,
+            Mode::Unknown(value) => value,
         }
     }
 }
@@ -130,7 +397,7 @@ impl TryFrom<u32> for Mode {
 #[allow(clippy::from_over_into)]
 impl Into<[u8; 4]> for Mode {
     fn into(self) -> [u8; 4] {
-        (self as u32).to_le_bytes()
+        u32::from(self).to_le_bytes()
     }
 }
 
@@ -144,6 +411,26 @@ pub(crate) const fn u32_from_str(value: &str) -> u32 {
     u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]).to_le()
 }
 
+/// Decodes an ASCII string from a register, stopping at the first null byte
+///
+/// Non-printable characters are dropped and trailing whitespace is trimmed
+pub(crate) fn ascii_to_string<const N: usize>(bytes: &[u8]) -> heapless::String<N> {
+    let mut string = heapless::String::new();
+    for c in bytes
+        .iter()
+        .take_while(|b| **b != 0)
+        .filter(|b| b.is_ascii_graphic() || **b == b' ')
+    {
+        if string.push(*c as char).is_err() {
+            break;
+        }
+    }
+
+    let len = string.trim_end().len();
+    string.truncate(len);
+    string
+}
+
 /// Common unit test functions
 #[cfg(test)]
 pub(crate) mod test {
@@ -190,4 +477,16 @@ pub(crate) mod test {
             sleep(Duration::from_nanos(ns as u64)).await;
         }
     }
+
+    #[test]
+    fn test_mode_from_raw() {
+        for mode in [Mode::Boot, Mode::F211, Mode::App0, Mode::App1, Mode::Wtpr, Mode::Ptch] {
+            assert_eq!(Mode::try_from(u32::from(mode)), Ok(mode));
+            assert_eq!(Mode::from_raw(u32::from(mode)), mode);
+        }
+
+        let unknown = u32_from_str("XYZW");
+        assert_eq!(Mode::try_from(unknown), Err(PdError::InvalidParams));
+        assert_eq!(Mode::from_raw(unknown), Mode::Unknown(unknown));
+    }
 }
@@ -0,0 +1,589 @@
+//! Power data object and request data object decoding
+use embedded_usb_pd::PdError;
+
+use crate::registers::field_sets::{ActivePdoContract, Status};
+
+/// Bit offset of the object position in an RDO
+const RDO_OBJECT_POSITION_SHIFT: u32 = 28;
+/// Mask of the object position in an RDO
+const RDO_OBJECT_POSITION_MASK: u32 = 0xF;
+/// Capability mismatch flag in an RDO
+const RDO_CAPABILITY_MISMATCH: u32 = 1 << 26;
+
+/// Raw request data object
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Rdo(pub u32);
+
+impl Rdo {
+    /// Position of the requested PDO in the source capabilities, starting at 1. Zero if there is no request.
+    pub fn object_position(&self) -> u8 {
+        ((self.0 >> RDO_OBJECT_POSITION_SHIFT) & RDO_OBJECT_POSITION_MASK) as u8
+    }
+
+    /// The sink couldn't be satisfied by any of the source's PDOs and requested less than it needs
+    pub fn capability_mismatch(&self) -> bool {
+        self.0 & RDO_CAPABILITY_MISMATCH != 0
+    }
+
+    /// Operating current in mA agreed for the given PDO
+    ///
+    /// Returns `None` if there is no request or the PDO isn't a fixed or variable supply, the RDO layout depends on the
+    /// type of the requested PDO.
+    pub fn operating_current_ma(&self, pdo: u32) -> Option<u16> {
+        if self.object_position() == 0 {
+            return None;
+        }
+
+        match pdo >> PDO_TYPE_SHIFT {
+            PDO_TYPE_FIXED | PDO_TYPE_VARIABLE => {
+                Some(decode_field(self.0, PDO_MIDDLE_SHIFT, CURRENT_UNIT_MA as u32) as u16)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<u32> for Rdo {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+/// Bit offset of the PDO type
+const PDO_TYPE_SHIFT: u32 = 30;
+/// Fixed supply PDO type
+const PDO_TYPE_FIXED: u32 = 0b00;
+/// Battery supply PDO type
+const PDO_TYPE_BATTERY: u32 = 0b01;
+/// Variable supply PDO type
+const PDO_TYPE_VARIABLE: u32 = 0b10;
+/// Augmented PDO type
+const PDO_TYPE_AUGMENTED: u32 = 0b11;
+
+/// Bit offset of the augmented PDO type
+const APDO_TYPE_SHIFT: u32 = 28;
+/// Mask of the augmented PDO type
+const APDO_TYPE_MASK: u32 = 0x3;
+/// SPR programmable power supply augmented PDO type
+const APDO_TYPE_SPR_PPS: u32 = 0b00;
+/// Bit offset of the PPS maximum voltage
+const PPS_MAX_VOLTAGE_SHIFT: u32 = 17;
+/// Bit offset of the PPS minimum voltage
+const PPS_MIN_VOLTAGE_SHIFT: u32 = 8;
+/// Mask of the PPS voltage fields
+const PPS_VOLTAGE_MASK: u32 = 0xFF;
+/// Mask of the PPS maximum current
+const PPS_CURRENT_MASK: u32 = 0x7F;
+/// PPS voltage resolution in mV
+const PPS_VOLTAGE_UNIT_MV: u32 = 100;
+/// PPS current resolution in mA
+const PPS_CURRENT_UNIT_MA: u32 = 50;
+
+/// Mask of a 10-bit PDO field
+const PDO_FIELD_MASK: u32 = 0x3FF;
+/// Bit offset of the upper 10-bit PDO field
+const PDO_UPPER_SHIFT: u32 = 20;
+/// Bit offset of the middle 10-bit PDO field
+const PDO_MIDDLE_SHIFT: u32 = 10;
+
+/// Voltage resolution in mV
+const VOLTAGE_UNIT_MV: u16 = 50;
+/// Current resolution in mA
+const CURRENT_UNIT_MA: u16 = 10;
+/// Power resolution in mW
+const POWER_UNIT_MW: u32 = 250;
+
+/// Fixed PDO dual-role power bit
+const FIXED_DUAL_ROLE_POWER: u32 = 1 << 29;
+/// Fixed PDO higher capability bit
+const FIXED_HIGHER_CAPABILITY: u32 = 1 << 28;
+/// Fixed PDO unconstrained power bit
+const FIXED_UNCONSTRAINED_POWER: u32 = 1 << 27;
+/// Fixed PDO USB communications capable bit
+const FIXED_USB_COMM: u32 = 1 << 26;
+/// Fixed PDO dual-role data bit
+const FIXED_DUAL_ROLE_DATA: u32 = 1 << 25;
+/// Fixed source PDO USB suspend supported bit, shares its position with the sink higher capability bit
+const FIXED_USB_SUSPEND_SUPPORTED: u32 = 1 << 28;
+/// Offset of the first PDO control bits in the first PDO
+const FIRST_PDO_CONTROL_SHIFT: u32 = 20;
+
+/// vSafe5V, the first sink PDO must be a fixed PDO at this voltage
+pub const VSAFE5V_MV: u16 = 5000;
+
+/// Sink power data object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SinkPdo {
+    /// Fixed supply
+    Fixed {
+        /// Voltage in mV
+        voltage_mv: u16,
+        /// Operational current in mA
+        operational_current_ma: u16,
+        /// Dual-role power, only valid in the first PDO
+        dual_role_power: bool,
+        /// Sink needs more than vSafe5V for full functionality, only valid in the first PDO
+        higher_capability: bool,
+        /// Unconstrained power available, only valid in the first PDO
+        unconstrained_power: bool,
+        /// USB communications capable, only valid in the first PDO
+        usb_comm: bool,
+        /// Dual-role data, only valid in the first PDO
+        dual_role_data: bool,
+    },
+    /// Battery supply
+    Battery {
+        /// Maximum voltage in mV
+        max_voltage_mv: u16,
+        /// Minimum voltage in mV
+        min_voltage_mv: u16,
+        /// Operational power in mW
+        operational_power_mw: u32,
+    },
+    /// Variable supply
+    Variable {
+        /// Maximum voltage in mV
+        max_voltage_mv: u16,
+        /// Minimum voltage in mV
+        min_voltage_mv: u16,
+        /// Operational current in mA
+        operational_current_ma: u16,
+    },
+}
+
+/// Encode a value into a 10-bit PDO field, the value must be an exact multiple of the unit
+fn encode_field(value: u32, unit: u32) -> Result<u32, PdError> {
+    if value % unit != 0 || value / unit > PDO_FIELD_MASK {
+        return Err(PdError::InvalidParams);
+    }
+
+    Ok(value / unit)
+}
+
+/// Decode a 10-bit PDO field
+fn decode_field(raw: u32, shift: u32, unit: u32) -> u32 {
+    ((raw >> shift) & PDO_FIELD_MASK) * unit
+}
+
+impl SinkPdo {
+    /// Encode into a raw PDO, returns `InvalidParams` if a value can't be represented
+    pub fn encode(&self) -> Result<u32, PdError> {
+        let voltage_unit = VOLTAGE_UNIT_MV as u32;
+        let current_unit = CURRENT_UNIT_MA as u32;
+
+        match *self {
+            SinkPdo::Fixed {
+                voltage_mv,
+                operational_current_ma,
+                dual_role_power,
+                higher_capability,
+                unconstrained_power,
+                usb_comm,
+                dual_role_data,
+            } => {
+                let mut raw = (PDO_TYPE_FIXED << PDO_TYPE_SHIFT)
+                    | (encode_field(voltage_mv as u32, voltage_unit)? << PDO_MIDDLE_SHIFT)
+                    | encode_field(operational_current_ma as u32, current_unit)?;
+                for (set, bit) in [
+                    (dual_role_power, FIXED_DUAL_ROLE_POWER),
+                    (higher_capability, FIXED_HIGHER_CAPABILITY),
+                    (unconstrained_power, FIXED_UNCONSTRAINED_POWER),
+                    (usb_comm, FIXED_USB_COMM),
+                    (dual_role_data, FIXED_DUAL_ROLE_DATA),
+                ] {
+                    if set {
+                        raw |= bit;
+                    }
+                }
+                Ok(raw)
+            }
+            SinkPdo::Battery {
+                max_voltage_mv,
+                min_voltage_mv,
+                operational_power_mw,
+            } => {
+                if min_voltage_mv > max_voltage_mv {
+                    return Err(PdError::InvalidParams);
+                }
+
+                Ok((PDO_TYPE_BATTERY << PDO_TYPE_SHIFT)
+                    | (encode_field(max_voltage_mv as u32, voltage_unit)? << PDO_UPPER_SHIFT)
+                    | (encode_field(min_voltage_mv as u32, voltage_unit)? << PDO_MIDDLE_SHIFT)
+                    | encode_field(operational_power_mw, POWER_UNIT_MW)?)
+            }
+            SinkPdo::Variable {
+                max_voltage_mv,
+                min_voltage_mv,
+                operational_current_ma,
+            } => {
+                if min_voltage_mv > max_voltage_mv {
+                    return Err(PdError::InvalidParams);
+                }
+
+                Ok((PDO_TYPE_VARIABLE << PDO_TYPE_SHIFT)
+                    | (encode_field(max_voltage_mv as u32, voltage_unit)? << PDO_UPPER_SHIFT)
+                    | (encode_field(min_voltage_mv as u32, voltage_unit)? << PDO_MIDDLE_SHIFT)
+                    | encode_field(operational_current_ma as u32, current_unit)?)
+            }
+        }
+    }
+}
+
+impl TryFrom<u32> for SinkPdo {
+    type Error = PdError;
+
+    fn try_from(raw: u32) -> Result<Self, Self::Error> {
+        let voltage_unit = VOLTAGE_UNIT_MV as u32;
+        let current_unit = CURRENT_UNIT_MA as u32;
+
+        match raw >> PDO_TYPE_SHIFT {
+            PDO_TYPE_FIXED => Ok(SinkPdo::Fixed {
+                voltage_mv: decode_field(raw, PDO_MIDDLE_SHIFT, voltage_unit) as u16,
+                operational_current_ma: decode_field(raw, 0, current_unit) as u16,
+                dual_role_power: raw & FIXED_DUAL_ROLE_POWER != 0,
+                higher_capability: raw & FIXED_HIGHER_CAPABILITY != 0,
+                unconstrained_power: raw & FIXED_UNCONSTRAINED_POWER != 0,
+                usb_comm: raw & FIXED_USB_COMM != 0,
+                dual_role_data: raw & FIXED_DUAL_ROLE_DATA != 0,
+            }),
+            PDO_TYPE_BATTERY => Ok(SinkPdo::Battery {
+                max_voltage_mv: decode_field(raw, PDO_UPPER_SHIFT, voltage_unit) as u16,
+                min_voltage_mv: decode_field(raw, PDO_MIDDLE_SHIFT, voltage_unit) as u16,
+                operational_power_mw: decode_field(raw, 0, POWER_UNIT_MW),
+            }),
+            PDO_TYPE_VARIABLE => Ok(SinkPdo::Variable {
+                max_voltage_mv: decode_field(raw, PDO_UPPER_SHIFT, voltage_unit) as u16,
+                min_voltage_mv: decode_field(raw, PDO_MIDDLE_SHIFT, voltage_unit) as u16,
+                operational_current_ma: decode_field(raw, 0, current_unit) as u16,
+            }),
+            // Augmented PDOs aren't supported
+            _ => Err(PdError::InvalidParams),
+        }
+    }
+}
+
+/// Source power data object
+///
+/// Capability flags are only present in the first PDO, see [`PartnerInfo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SourcePdo {
+    /// Fixed supply
+    Fixed {
+        /// Voltage in mV
+        voltage_mv: u16,
+        /// Maximum current in mA
+        max_current_ma: u16,
+    },
+    /// Battery supply
+    Battery {
+        /// Maximum voltage in mV
+        max_voltage_mv: u16,
+        /// Minimum voltage in mV
+        min_voltage_mv: u16,
+        /// Maximum power in mW
+        max_power_mw: u32,
+    },
+    /// Variable supply
+    Variable {
+        /// Maximum voltage in mV
+        max_voltage_mv: u16,
+        /// Minimum voltage in mV
+        min_voltage_mv: u16,
+        /// Maximum current in mA
+        max_current_ma: u16,
+    },
+}
+
+impl TryFrom<u32> for SourcePdo {
+    type Error = PdError;
+
+    fn try_from(raw: u32) -> Result<Self, Self::Error> {
+        let voltage_unit = VOLTAGE_UNIT_MV as u32;
+        let current_unit = CURRENT_UNIT_MA as u32;
+
+        match raw >> PDO_TYPE_SHIFT {
+            PDO_TYPE_FIXED => Ok(SourcePdo::Fixed {
+                voltage_mv: decode_field(raw, PDO_MIDDLE_SHIFT, voltage_unit) as u16,
+                max_current_ma: decode_field(raw, 0, current_unit) as u16,
+            }),
+            PDO_TYPE_BATTERY => Ok(SourcePdo::Battery {
+                max_voltage_mv: decode_field(raw, PDO_UPPER_SHIFT, voltage_unit) as u16,
+                min_voltage_mv: decode_field(raw, PDO_MIDDLE_SHIFT, voltage_unit) as u16,
+                max_power_mw: decode_field(raw, 0, POWER_UNIT_MW),
+            }),
+            PDO_TYPE_VARIABLE => Ok(SourcePdo::Variable {
+                max_voltage_mv: decode_field(raw, PDO_UPPER_SHIFT, voltage_unit) as u16,
+                min_voltage_mv: decode_field(raw, PDO_MIDDLE_SHIFT, voltage_unit) as u16,
+                max_current_ma: decode_field(raw, 0, current_unit) as u16,
+            }),
+            // Augmented PDOs aren't supported
+            _ => Err(PdError::InvalidParams),
+        }
+    }
+}
+
+/// Output range of an SPR programmable power supply
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PpsRange {
+    /// Minimum voltage in mV
+    pub min_voltage_mv: u16,
+    /// Maximum voltage in mV
+    pub max_voltage_mv: u16,
+    /// Maximum current in mA
+    pub max_current_ma: u16,
+}
+
+impl PpsRange {
+    /// Decode from a raw augmented PDO, returns `None` if it isn't an SPR PPS APDO
+    pub fn from_apdo(raw: u32) -> Option<Self> {
+        if raw >> PDO_TYPE_SHIFT != PDO_TYPE_AUGMENTED || (raw >> APDO_TYPE_SHIFT) & APDO_TYPE_MASK != APDO_TYPE_SPR_PPS
+        {
+            return None;
+        }
+
+        Some(Self {
+            min_voltage_mv: (((raw >> PPS_MIN_VOLTAGE_SHIFT) & PPS_VOLTAGE_MASK) * PPS_VOLTAGE_UNIT_MV) as u16,
+            max_voltage_mv: (((raw >> PPS_MAX_VOLTAGE_SHIFT) & PPS_VOLTAGE_MASK) * PPS_VOLTAGE_UNIT_MV) as u16,
+            max_current_ma: ((raw & PPS_CURRENT_MASK) * PPS_CURRENT_UNIT_MA) as u16,
+        })
+    }
+}
+
+/// Capabilities advertised by the source partner in the first PDO of its source capabilities
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PartnerInfo {
+    /// Source is externally powered, e.g. mains rather than a battery
+    pub unconstrained_power: bool,
+    /// Source is dual-role power
+    pub dual_role_power: bool,
+    /// Source is dual-role data
+    pub dual_role_data: bool,
+    /// Source is capable of USB communications
+    pub usb_comm: bool,
+    /// Source supports USB suspend
+    pub usb_suspend_supported: bool,
+}
+
+impl PartnerInfo {
+    /// Decode from the first PDO of the source capabilities
+    pub fn from_first_pdo(raw: u32) -> Self {
+        Self {
+            unconstrained_power: raw & FIXED_UNCONSTRAINED_POWER != 0,
+            dual_role_power: raw & FIXED_DUAL_ROLE_POWER != 0,
+            dual_role_data: raw & FIXED_DUAL_ROLE_DATA != 0,
+            usb_comm: raw & FIXED_USB_COMM != 0,
+            usb_suspend_supported: raw & FIXED_USB_SUSPEND_SUPPORTED != 0,
+        }
+    }
+
+    /// Decode from the active contract, returns `None` unless there is an explicit contract with the port as sink
+    pub fn new(status: &Status, contract: &ActivePdoContract) -> Option<Self> {
+        // As source the active PDO is our own, not the partner's
+        if !status.plug_present() || status.port_role() || contract.active_pdo() == 0 {
+            return None;
+        }
+
+        Some(Self::from_first_pdo(
+            (contract.first_pdo_control() as u32) << FIRST_PDO_CONTROL_SHIFT,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rdo_object_position() {
+        assert_eq!(Rdo(0).object_position(), 0);
+        assert_eq!(Rdo(0x1000_0000).object_position(), 1);
+        assert_eq!(Rdo(0x7012_c0c8).object_position(), 7);
+        // EPR object positions use the full field
+        assert_eq!(Rdo(0xD000_0000).object_position(), 13);
+    }
+
+    #[test]
+    fn test_rdo_capability_mismatch() {
+        assert!(!Rdo(0x1004_B12C).capability_mismatch());
+        assert!(Rdo(0x1404_B12C).capability_mismatch());
+    }
+
+    #[test]
+    fn test_rdo_operating_current() {
+        // Position 1, 3A operating, 3A max operating
+        let rdo = Rdo(0x1004_B12C);
+        assert_eq!(rdo.operating_current_ma(0x0001_912C), Some(3000));
+        assert_eq!(rdo.operating_current_ma(0x9901_9096), Some(3000));
+        // Battery and augmented PDOs use a different RDO layout
+        assert_eq!(rdo.operating_current_ma(0x4F02_D03C), None);
+        assert_eq!(rdo.operating_current_ma(0xC000_0000), None);
+        // No request
+        assert_eq!(Rdo(0).operating_current_ma(0x0001_912C), None);
+    }
+
+    #[test]
+    fn test_sink_pdo_fixed() {
+        let pdo = SinkPdo::Fixed {
+            voltage_mv: 5000,
+            operational_current_ma: 3000,
+            dual_role_power: true,
+            higher_capability: false,
+            unconstrained_power: false,
+            usb_comm: true,
+            dual_role_data: true,
+        };
+        let raw = 0x2601_912C;
+        assert_eq!(pdo.encode(), Ok(raw));
+        assert_eq!(SinkPdo::try_from(raw), Ok(pdo));
+    }
+
+    #[test]
+    fn test_sink_pdo_variable_battery() {
+        let variable = SinkPdo::Variable {
+            max_voltage_mv: 20000,
+            min_voltage_mv: 5000,
+            operational_current_ma: 1500,
+        };
+        let raw = 0x9901_9096;
+        assert_eq!(variable.encode(), Ok(raw));
+        assert_eq!(SinkPdo::try_from(raw), Ok(variable));
+
+        let battery = SinkPdo::Battery {
+            max_voltage_mv: 12000,
+            min_voltage_mv: 9000,
+            operational_power_mw: 15000,
+        };
+        let raw = 0x4F02_D03C;
+        assert_eq!(battery.encode(), Ok(raw));
+        assert_eq!(SinkPdo::try_from(raw), Ok(battery));
+    }
+
+    #[test]
+    fn test_sink_pdo_invalid() {
+        // Not a multiple of 50 mV
+        let pdo = SinkPdo::Variable {
+            max_voltage_mv: 20010,
+            min_voltage_mv: 5000,
+            operational_current_ma: 1500,
+        };
+        assert_eq!(pdo.encode(), Err(PdError::InvalidParams));
+
+        // Minimum above maximum
+        let pdo = SinkPdo::Battery {
+            max_voltage_mv: 5000,
+            min_voltage_mv: 9000,
+            operational_power_mw: 15000,
+        };
+        assert_eq!(pdo.encode(), Err(PdError::InvalidParams));
+
+        // Augmented PDO
+        assert_eq!(SinkPdo::try_from(0xC000_0000), Err(PdError::InvalidParams));
+    }
+
+    #[test]
+    fn test_pps_range() {
+        // 3.3V to 21V, 3A
+        assert_eq!(
+            PpsRange::from_apdo(0xC1A4_213C),
+            Some(PpsRange {
+                min_voltage_mv: 3300,
+                max_voltage_mv: 21000,
+                max_current_ma: 3000,
+            })
+        );
+        // EPR AVS APDO
+        assert_eq!(PpsRange::from_apdo(0xD1A4_213C), None);
+        // Fixed PDO, or no contract
+        assert_eq!(PpsRange::from_apdo(0x0001_912C), None);
+        assert_eq!(PpsRange::from_apdo(0), None);
+    }
+
+    #[test]
+    fn test_source_pdo() {
+        // 5V 3A, flags are ignored
+        assert_eq!(
+            SourcePdo::try_from(0x2601_912C),
+            Ok(SourcePdo::Fixed {
+                voltage_mv: 5000,
+                max_current_ma: 3000,
+            })
+        );
+        assert_eq!(
+            SourcePdo::try_from(0x9901_9096),
+            Ok(SourcePdo::Variable {
+                max_voltage_mv: 20000,
+                min_voltage_mv: 5000,
+                max_current_ma: 1500,
+            })
+        );
+        assert_eq!(
+            SourcePdo::try_from(0x4F02_D03C),
+            Ok(SourcePdo::Battery {
+                max_voltage_mv: 12000,
+                min_voltage_mv: 9000,
+                max_power_mw: 15000,
+            })
+        );
+        assert_eq!(SourcePdo::try_from(0xC000_0000), Err(PdError::InvalidParams));
+    }
+
+    #[test]
+    fn test_partner_info_from_first_pdo() {
+        assert_eq!(PartnerInfo::from_first_pdo(0), PartnerInfo::default());
+        // 5V 3A, unconstrained, USB comms capable
+        assert_eq!(
+            PartnerInfo::from_first_pdo(0x0C01_912C),
+            PartnerInfo {
+                unconstrained_power: true,
+                usb_comm: true,
+                ..Default::default()
+            }
+        );
+        // 5V 3A, dual-role power, USB suspend, dual-role data
+        assert_eq!(
+            PartnerInfo::from_first_pdo(0x3201_912C),
+            PartnerInfo {
+                dual_role_power: true,
+                dual_role_data: true,
+                usb_suspend_supported: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_partner_info_new() {
+        let mut status = Status::new_zero();
+        let mut contract = ActivePdoContract::new_zero();
+        contract.set_active_pdo(0x0201_912C);
+        // Unconstrained and dual-role power
+        contract.set_first_pdo_control(0x280);
+
+        // No plug
+        assert_eq!(PartnerInfo::new(&status, &contract), None);
+
+        status.set_plug_present(true);
+        assert_eq!(
+            PartnerInfo::new(&status, &contract),
+            Some(PartnerInfo {
+                unconstrained_power: true,
+                dual_role_power: true,
+                ..Default::default()
+            })
+        );
+
+        // Port is the source
+        status.set_port_role(true);
+        assert_eq!(PartnerInfo::new(&status, &contract), None);
+
+        // No contract
+        status.set_port_role(false);
+        contract.set_active_pdo(0);
+        assert_eq!(PartnerInfo::new(&status, &contract), None);
+    }
+}
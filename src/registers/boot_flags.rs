@@ -0,0 +1,123 @@
+//! Firmware region status
+//!
+//! The controller's flash holds two firmware regions. On boot the controller loads the application from the active
+//! region and falls back to the other region if it fails validation. A firmware update always writes the region that
+//! isn't executing, which becomes the staged region, and the controller runs it after a reset that switches banks.
+use super::field_sets::BootFlags;
+
+/// Firmware region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FirmwareRegion {
+    /// First firmware region
+    Region0,
+    /// Second firmware region
+    Region1,
+}
+
+impl FirmwareRegion {
+    /// Returns the other region
+    pub fn other(self) -> Self {
+        match self {
+            FirmwareRegion::Region0 => FirmwareRegion::Region1,
+            FirmwareRegion::Region1 => FirmwareRegion::Region0,
+        }
+    }
+}
+
+/// Status of a single firmware region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RegionInfo {
+    /// The image in this region passed validation
+    pub valid: bool,
+    /// Firmware version, only known for the executing region
+    pub version: Option<u32>,
+}
+
+/// Executing and staged firmware regions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FirmwareRegions {
+    /// Region the running firmware was loaded from
+    pub executing: FirmwareRegion,
+    /// Region a firmware update targets
+    pub staged: FirmwareRegion,
+    /// Status of region 0
+    pub region0: RegionInfo,
+    /// Status of region 1
+    pub region1: RegionInfo,
+}
+
+impl FirmwareRegions {
+    /// Decode the regions from the boot flags and the version of the running firmware
+    pub fn new(flags: &BootFlags, version: u32) -> Self {
+        let executing = if flags.active_region() {
+            FirmwareRegion::Region1
+        } else {
+            FirmwareRegion::Region0
+        };
+
+        let info = |region: FirmwareRegion, invalid: bool| RegionInfo {
+            valid: !invalid,
+            version: (region == executing).then_some(version),
+        };
+
+        Self {
+            executing,
+            staged: executing.other(),
+            region0: info(FirmwareRegion::Region0, flags.region_0_invalid()),
+            region1: info(FirmwareRegion::Region1, flags.region_1_invalid()),
+        }
+    }
+
+    /// Returns the status of the given region
+    pub fn region(&self, region: FirmwareRegion) -> &RegionInfo {
+        match region {
+            FirmwareRegion::Region0 => &self.region0,
+            FirmwareRegion::Region1 => &self.region1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_firmware_regions_region0() {
+        let regions = FirmwareRegions::new(&BootFlags::new_zero(), 0x1234);
+        assert_eq!(regions.executing, FirmwareRegion::Region0);
+        assert_eq!(regions.staged, FirmwareRegion::Region1);
+        assert_eq!(
+            *regions.region(FirmwareRegion::Region0),
+            RegionInfo {
+                valid: true,
+                version: Some(0x1234)
+            }
+        );
+        assert_eq!(
+            *regions.region(FirmwareRegion::Region1),
+            RegionInfo {
+                valid: true,
+                version: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_firmware_regions_region1() {
+        // Region 0 failed validation so the controller fell back to region 1
+        let mut flags = BootFlags::new_zero();
+        flags.set_active_region(true);
+        flags.set_region_0_invalid(true);
+
+        let regions = FirmwareRegions::new(&flags, 0x5678);
+        assert_eq!(regions.executing, FirmwareRegion::Region1);
+        assert_eq!(regions.staged, FirmwareRegion::Region0);
+        assert!(!regions.region0.valid);
+        assert_eq!(regions.region0.version, None);
+        assert!(regions.region1.valid);
+        assert_eq!(regions.region1.version, Some(0x5678));
+    }
+}
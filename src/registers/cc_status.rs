@@ -0,0 +1,200 @@
+//! Decoded CC line status
+use embedded_usb_pd::{type_c, PdError};
+
+use super::field_sets::{PortConfig, PortControl, Status};
+use super::{PlugMode, TypecStateMachine};
+
+/// CC pin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CcPin {
+    /// CC1 line
+    Cc1,
+    /// CC2 line
+    Cc2,
+}
+
+/// Connector orientation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Orientation {
+    /// Unflipped, CC1 is the active CC line
+    #[default]
+    Normal,
+    /// Flipped, CC2 is the active CC line
+    Flipped,
+}
+
+/// Type-C accessory attached to the port
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Accessory {
+    /// No accessory attached
+    #[default]
+    None,
+    /// Audio adapter accessory
+    Audio,
+    /// Debug accessory
+    Debug,
+}
+
+/// CC line status of a port
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CcStatus {
+    /// CC line used for PD communication, `None` when nothing is connected
+    pub active_cc: Option<CcPin>,
+    /// Connector orientation
+    pub orientation: Orientation,
+    /// Attached accessory
+    pub accessory: Accessory,
+}
+
+impl From<Status> for CcStatus {
+    fn from(status: Status) -> Self {
+        let orientation = if status.plug_orientation() {
+            Orientation::Flipped
+        } else {
+            Orientation::Normal
+        };
+
+        let connected = matches!(status.connection_state(), PlugMode::ConnectedNoRa | PlugMode::Connected);
+        let active_cc = if status.plug_present() && connected {
+            Some(match orientation {
+                Orientation::Normal => CcPin::Cc1,
+                Orientation::Flipped => CcPin::Cc2,
+            })
+        } else {
+            None
+        };
+
+        let accessory = match status.connection_state() {
+            PlugMode::Audio => Accessory::Audio,
+            PlugMode::Debug => Accessory::Debug,
+            _ => Accessory::None,
+        };
+
+        Self {
+            active_cc,
+            orientation,
+            accessory,
+        }
+    }
+}
+
+/// CC termination presented by the port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Termination {
+    /// No termination, the port is disabled or toggling as a dual-role port while unattached
+    Open,
+    /// Pull-down, presented when sinking
+    Rd,
+    /// Pull-up advertising the given current, presented when sourcing
+    Rp(type_c::Current),
+}
+
+impl Termination {
+    /// Decode the termination from the port status, configuration, and control registers
+    ///
+    /// While attached the termination follows the current power role. While unattached it follows the configured
+    /// Type-C state machine.
+    pub fn new(status: &Status, config: &PortConfig, control: &PortControl) -> Result<Self, PdError> {
+        let sourcing = if status.plug_present() {
+            status.port_role()
+        } else {
+            match config.typec_state_machine() {
+                TypecStateMachine::Sink => false,
+                TypecStateMachine::Source => true,
+                TypecStateMachine::Drp | TypecStateMachine::Disabled => return Ok(Termination::Open),
+            }
+        };
+
+        if sourcing {
+            Ok(Termination::Rp(control.typec_current().try_into()?))
+        } else {
+            Ok(Termination::Rd)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::registers::TypecCurrent;
+
+    fn status(plug_present: bool, state: PlugMode, flipped: bool) -> Status {
+        let mut status = Status::new_zero();
+        status.set_plug_present(plug_present);
+        status.set_connection_state(state);
+        status.set_plug_orientation(flipped);
+        status
+    }
+
+    #[test]
+    fn test_cc_status_orientation() {
+        let cc = CcStatus::from(status(true, PlugMode::Connected, false));
+        assert_eq!(cc.active_cc, Some(CcPin::Cc1));
+        assert_eq!(cc.orientation, Orientation::Normal);
+        assert_eq!(cc.accessory, Accessory::None);
+
+        let cc = CcStatus::from(status(true, PlugMode::ConnectedNoRa, true));
+        assert_eq!(cc.active_cc, Some(CcPin::Cc2));
+        assert_eq!(cc.orientation, Orientation::Flipped);
+        assert_eq!(cc.accessory, Accessory::None);
+    }
+
+    #[test]
+    fn test_cc_status_not_connected() {
+        assert_eq!(
+            CcStatus::from(status(false, PlugMode::NotConnected, false)),
+            CcStatus::default()
+        );
+    }
+
+    #[test]
+    fn test_termination_attached() {
+        let mut config = PortConfig::new_zero();
+        config.set_typec_state_machine(TypecStateMachine::Drp);
+        let mut control = PortControl::new_zero();
+        control.set_typec_current(TypecCurrent::Current3A0);
+
+        let mut source = status(true, PlugMode::Connected, false);
+        source.set_port_role(true);
+        assert_eq!(
+            Termination::new(&source, &config, &control),
+            Ok(Termination::Rp(type_c::Current::Current3A0))
+        );
+
+        let sink = status(true, PlugMode::Connected, false);
+        assert_eq!(Termination::new(&sink, &config, &control), Ok(Termination::Rd));
+    }
+
+    #[test]
+    fn test_termination_unattached() {
+        let unattached = status(false, PlugMode::NotConnected, false);
+        let control = PortControl::new_zero();
+
+        let mut config = PortConfig::new_zero();
+        for (state_machine, expected) in [
+            (TypecStateMachine::Sink, Termination::Rd),
+            (TypecStateMachine::Source, Termination::Rp(type_c::Current::UsbDefault)),
+            (TypecStateMachine::Drp, Termination::Open),
+            (TypecStateMachine::Disabled, Termination::Open),
+        ] {
+            config.set_typec_state_machine(state_machine);
+            assert_eq!(Termination::new(&unattached, &config, &control), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn test_cc_status_accessory() {
+        let cc = CcStatus::from(status(true, PlugMode::Debug, true));
+        assert_eq!(cc.active_cc, None);
+        assert_eq!(cc.orientation, Orientation::Flipped);
+        assert_eq!(cc.accessory, Accessory::Debug);
+
+        let cc = CcStatus::from(status(true, PlugMode::Audio, false));
+        assert_eq!(cc.accessory, Accessory::Audio);
+    }
+}
@@ -0,0 +1,110 @@
+//! Chip identification, decoded from the device info register
+//!
+//! Device info is an ASCII string starting with the part number followed by the silicon revision as `HW` and four
+//! hex digits, e.g. `TPS66994 HW0001`. There's no separate device ID register, so this is the only place the part
+//! number and revision are reported.
+use crate::{TPS66993_NUM_PORTS, TPS66994_NUM_PORTS};
+
+/// Prefix of the silicon revision field
+const REVISION_PREFIX: &str = "HW";
+
+/// Part number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PartNumber {
+    /// Single port TPS66993
+    Tps66993,
+    /// Dual port TPS66994
+    Tps66994,
+}
+
+impl PartNumber {
+    /// Returns the number of ports on this part
+    pub fn num_ports(self) -> usize {
+        match self {
+            PartNumber::Tps66993 => TPS66993_NUM_PORTS,
+            PartNumber::Tps66994 => TPS66994_NUM_PORTS,
+        }
+    }
+}
+
+/// Part number and silicon revision
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChipId {
+    /// Part number, `None` if the device info doesn't name a known part
+    pub part_number: Option<PartNumber>,
+    /// Silicon revision as reported, `None` if the device info doesn't include it
+    pub revision: Option<u16>,
+}
+
+impl ChipId {
+    /// Decode from the device info string
+    pub fn new(info: &str) -> Self {
+        let mut fields = info.split_ascii_whitespace();
+        let part_number = match fields.next() {
+            Some("TPS66993") => Some(PartNumber::Tps66993),
+            Some("TPS66994") => Some(PartNumber::Tps66994),
+            _ => None,
+        };
+
+        let revision = fields
+            .find_map(|field| field.strip_prefix(REVISION_PREFIX))
+            .and_then(|rev| u16::from_str_radix(rev, 16).ok());
+
+        Self { part_number, revision }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_chip_id() {
+        assert_eq!(
+            ChipId::new("TPS66993 HW0011"),
+            ChipId {
+                part_number: Some(PartNumber::Tps66993),
+                revision: Some(0x11),
+            }
+        );
+        assert_eq!(
+            ChipId::new("TPS66994 HW0001 FW0010.00.01"),
+            ChipId {
+                part_number: Some(PartNumber::Tps66994),
+                revision: Some(0x1),
+            }
+        );
+
+        // Unknown parts still report their revision
+        assert_eq!(
+            ChipId::new("TPS6699x HW0002"),
+            ChipId {
+                part_number: None,
+                revision: Some(0x2),
+            }
+        );
+
+        assert_eq!(
+            ChipId::new("TPS66994"),
+            ChipId {
+                part_number: Some(PartNumber::Tps66994),
+                revision: None,
+            }
+        );
+        assert_eq!(
+            ChipId::new("TPS66994 HWxyz"),
+            ChipId {
+                part_number: Some(PartNumber::Tps66994),
+                revision: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_part_number_ports() {
+        assert_eq!(PartNumber::Tps66993.num_ports(), TPS66993_NUM_PORTS);
+        assert_eq!(PartNumber::Tps66994.num_ports(), TPS66994_NUM_PORTS);
+    }
+}
@@ -0,0 +1,169 @@
+//! Snapshot of the writable configuration registers, for cloning a configuration across units
+//!
+//! A [`ControllerConfig`] holds the contents of these registers:
+//!
+//! | Scope      | Registers                                                              |
+//! |------------|------------------------------------------------------------------------|
+//! | Controller | System config                                                          |
+//! | Port       | Port config, port control, interrupt mask, TX sink caps, TX identity   |
+//!
+//! Read-only registers, command data, and the mailbox aren't included, neither is the customer use register since
+//! its contents are per unit. The snapshot implements bincode's `Encode` and `Decode` like the register contents
+//! elsewhere in the crate, so it can be stored as a blob, see [`CONTROLLER_CONFIG_LEN`] for its encoded length.
+use bincode::de::Decoder;
+use bincode::enc::Encoder;
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{Decode, Encode};
+
+use super::field_sets::{IntEventBus1, PortConfig, PortControl, SystemConfig};
+use super::tx_identity::{TxIdentity, REG_TX_IDENTITY_LEN};
+use super::tx_sink_caps::{SinkCapabilities, REG_TX_SINK_CAPS_LEN};
+use super::Register;
+use crate::MAX_SUPPORTED_PORTS;
+
+/// Encoded length of a single port's configuration
+const PORT_CONFIG_LEN: usize = Register::PortConfig.size()
+    + Register::PortControl.size()
+    + Register::IntMaskBus1.size()
+    + REG_TX_SINK_CAPS_LEN
+    + REG_TX_IDENTITY_LEN;
+
+/// Maximum encoded length of a [`ControllerConfig`] with the standard fixed-int bincode configuration
+pub const CONTROLLER_CONFIG_LEN: usize = Register::SystemConfig.size() + 1 + MAX_SUPPORTED_PORTS * PORT_CONFIG_LEN;
+
+/// Configuration of a single port
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortSettings {
+    /// Port config
+    pub port_config: PortConfig,
+    /// Port control
+    pub port_control: PortControl,
+    /// Interrupt mask
+    pub interrupt_mask: IntEventBus1,
+    /// Sink capabilities advertised to the partner
+    pub tx_sink_caps: SinkCapabilities,
+    /// Response to Discover Identity
+    pub tx_identity: TxIdentity,
+}
+
+/// Configuration of the whole controller
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControllerConfig {
+    /// Global system config
+    pub system_config: SystemConfig,
+    /// Configuration of each active port, in port order
+    pub ports: heapless::Vec<PortSettings, MAX_SUPPORTED_PORTS>,
+}
+
+impl Encode for PortSettings {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        Encode::encode(&<[u8; Register::PortConfig.size()]>::from(self.port_config), encoder)?;
+        Encode::encode(&<[u8; Register::PortControl.size()]>::from(self.port_control), encoder)?;
+        Encode::encode(
+            &<[u8; Register::IntMaskBus1.size()]>::from(self.interrupt_mask),
+            encoder,
+        )?;
+        Encode::encode(&self.tx_sink_caps, encoder)?;
+        Encode::encode(&self.tx_identity, encoder)
+    }
+}
+
+impl<Context> Decode<Context> for PortSettings {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let port_config: [u8; Register::PortConfig.size()] = Decode::decode(decoder)?;
+        let port_control: [u8; Register::PortControl.size()] = Decode::decode(decoder)?;
+        let interrupt_mask: [u8; Register::IntMaskBus1.size()] = Decode::decode(decoder)?;
+        Ok(Self {
+            port_config: port_config.into(),
+            port_control: port_control.into(),
+            interrupt_mask: interrupt_mask.into(),
+            tx_sink_caps: Decode::decode(decoder)?,
+            tx_identity: Decode::decode(decoder)?,
+        })
+    }
+}
+
+impl Encode for ControllerConfig {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        Encode::encode(
+            &<[u8; Register::SystemConfig.size()]>::from(self.system_config),
+            encoder,
+        )?;
+        Encode::encode(&(self.ports.len() as u8), encoder)?;
+        for port in &self.ports {
+            Encode::encode(port, encoder)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<Context> Decode<Context> for ControllerConfig {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let system_config: [u8; Register::SystemConfig.size()] = Decode::decode(decoder)?;
+        let count: u8 = Decode::decode(decoder)?;
+        if count as usize > MAX_SUPPORTED_PORTS {
+            return Err(DecodeError::Other("Too many ports"));
+        }
+
+        let mut ports = heapless::Vec::new();
+        for _ in 0..count {
+            // Can't fail, count is bounded by the capacity
+            let _ = ports.push(Decode::decode(decoder)?);
+        }
+
+        Ok(Self {
+            system_config: system_config.into(),
+            ports,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bincode::config;
+
+    use super::*;
+    use crate::registers::TypecStateMachine;
+
+    #[test]
+    fn test_round_trip() {
+        let mut system_config = SystemConfig::new_zero();
+        system_config.set_enable_spm(true);
+        let mut port_config = PortConfig::new_zero();
+        port_config.set_typec_state_machine(TypecStateMachine::Drp);
+        let mut interrupt_mask = IntEventBus1::new_zero();
+        interrupt_mask.set_cmd_1_completed(true);
+
+        let port = PortSettings {
+            port_config,
+            port_control: PortControl::new_zero(),
+            interrupt_mask,
+            tx_sink_caps: SinkCapabilities::from_policy(&[(5000, 3000), (9000, 2000)]).unwrap(),
+            tx_identity: TxIdentity::new(&[0x1234_5678, 0, 0]).unwrap(),
+        };
+        let mut ports = heapless::Vec::new();
+        ports.push(port.clone()).unwrap();
+        ports.push(port).unwrap();
+        let exported = ControllerConfig { system_config, ports };
+
+        let mut buf = [0u8; CONTROLLER_CONFIG_LEN];
+        let len =
+            bincode::encode_into_slice(&exported, &mut buf, config::standard().with_fixed_int_encoding()).unwrap();
+        assert_eq!(len, CONTROLLER_CONFIG_LEN);
+
+        let (decoded, _): (ControllerConfig, _) =
+            bincode::decode_from_slice(&buf, config::standard().with_fixed_int_encoding()).unwrap();
+        assert_eq!(decoded, exported);
+    }
+
+    #[test]
+    fn test_decode_too_many_ports() {
+        let mut buf = [0u8; CONTROLLER_CONFIG_LEN];
+        buf[Register::SystemConfig.size()] = MAX_SUPPORTED_PORTS as u8 + 1;
+
+        let result: Result<(ControllerConfig, _), _> =
+            bincode::decode_from_slice(&buf, config::standard().with_fixed_int_encoding());
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,128 @@
+//! Discovered SVIDs register
+//!
+//! Byte 0 holds the number of SVIDs discovered on SOP in bits 3:0 and on SOP' in bits 7:4. Byte 1 bits 0 and 1 are set
+//! when SVID discovery has completed on SOP and SOP' respectively. The SOP SVIDs follow, then the SOP' SVIDs, each
+//! list padded to its maximum length.
+use bincode::de::Decoder;
+use bincode::error::DecodeError;
+use bincode::Decode;
+
+use crate::collections::SvidList;
+
+/// Discovered SVIDs register
+pub const REG_DISCOVERED_SVIDS: u8 = super::Register::DiscoveredSvids.addr();
+/// Maximum number of SOP SVIDs
+pub const MAX_SOP_SVIDS: usize = 16;
+/// Maximum number of SOP' SVIDs
+pub const MAX_SOP_PRIME_SVIDS: usize = 8;
+/// Discovered SVIDs register length
+pub const REG_DISCOVERED_SVIDS_LEN: usize = 2 + 2 * (MAX_SOP_SVIDS + MAX_SOP_PRIME_SVIDS);
+
+/// Mask of a SVID count in the first byte
+const SVID_COUNT_MASK: u8 = 0xF;
+/// Bit offset of the SOP' SVID count in the first byte
+const SOP_PRIME_COUNT_SHIFT: u8 = 4;
+/// Set when SOP discovery has completed
+const SOP_COMPLETE: u8 = 1 << 0;
+/// Set when SOP' discovery has completed
+const SOP_PRIME_COMPLETE: u8 = 1 << 1;
+
+/// SVIDs discovered from the port partner and cable
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DiscoveredSvids {
+    /// SVID discovery on SOP has completed
+    pub sop_complete: bool,
+    /// SVID discovery on SOP' has completed
+    pub sop_prime_complete: bool,
+    /// SVIDs supported by the port partner
+    pub sop: SvidList,
+    /// SVIDs supported by the cable
+    pub sop_prime: heapless::Vec<u16, MAX_SOP_PRIME_SVIDS>,
+}
+
+/// Decode a padded list of SVIDs, keeping the first `count`
+fn decode_svids<D: Decoder, const N: usize>(
+    decoder: &mut D,
+    count: usize,
+) -> Result<heapless::Vec<u16, N>, DecodeError> {
+    if count > N {
+        return Err(DecodeError::Other("Invalid SVID count"));
+    }
+
+    let mut svids = heapless::Vec::new();
+    for i in 0..N {
+        let svid: u16 = Decode::decode(decoder)?;
+        if i < count {
+            // Can't fail, count is bounded by the capacity
+            let _ = svids.push(svid);
+        }
+    }
+
+    Ok(svids)
+}
+
+impl<Context> Decode<Context> for DiscoveredSvids {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let counts: u8 = Decode::decode(decoder)?;
+        let flags: u8 = Decode::decode(decoder)?;
+        let sop = decode_svids(decoder, (counts & SVID_COUNT_MASK) as usize)?;
+        let sop_prime = decode_svids(decoder, ((counts >> SOP_PRIME_COUNT_SHIFT) & SVID_COUNT_MASK) as usize)?;
+
+        Ok(DiscoveredSvids {
+            sop_complete: flags & SOP_COMPLETE != 0,
+            sop_prime_complete: flags & SOP_PRIME_COMPLETE != 0,
+            sop,
+            sop_prime,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bincode::config;
+
+    use super::*;
+
+    #[test]
+    fn test_decode_discovered_svids() {
+        let mut raw = [0u8; REG_DISCOVERED_SVIDS_LEN];
+        // Three SOP SVIDs, one SOP' SVID, both complete
+        raw[0] = 0x13;
+        raw[1] = 0x03;
+        // DisplayPort, Thunderbolt, and a vendor SVID
+        raw[2..8].copy_from_slice(&[0x01, 0xFF, 0x87, 0x80, 0x51, 0x04]);
+        raw[2 + 2 * MAX_SOP_SVIDS..4 + 2 * MAX_SOP_SVIDS].copy_from_slice(&[0x87, 0x80]);
+
+        let (decoded, _): (DiscoveredSvids, _) =
+            bincode::decode_from_slice(&raw, config::standard().with_fixed_int_encoding()).unwrap();
+        assert!(decoded.sop_complete);
+        assert!(decoded.sop_prime_complete);
+        assert_eq!(decoded.sop.as_slice(), &[0xFF01, 0x8087, 0x0451]);
+        assert_eq!(decoded.sop_prime.as_slice(), &[0x8087]);
+    }
+
+    #[test]
+    fn test_decode_discovered_svids_empty() {
+        let mut raw = [0u8; REG_DISCOVERED_SVIDS_LEN];
+        raw[1] = 0x01;
+
+        let (decoded, _): (DiscoveredSvids, _) =
+            bincode::decode_from_slice(&raw, config::standard().with_fixed_int_encoding()).unwrap();
+        assert!(decoded.sop_complete);
+        assert!(!decoded.sop_prime_complete);
+        assert!(decoded.sop.is_empty());
+        assert!(decoded.sop_prime.is_empty());
+    }
+
+    #[test]
+    fn test_decode_discovered_svids_invalid_count() {
+        let mut raw = [0u8; REG_DISCOVERED_SVIDS_LEN];
+        // More SOP' SVIDs than fit in the register
+        raw[0] = 0x90;
+
+        let result: Result<(DiscoveredSvids, _), _> =
+            bincode::decode_from_slice(&raw, config::standard().with_fixed_int_encoding());
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! Human-readable rendering of raw register snapshots
+//!
+//! A dump is a list of registers and their raw contents. Each register is rendered as a header with its name, address,
+//! and size, followed by lines of hex bytes with an ASCII column, similar to `hexdump -C`.
+use core::fmt::{Result, Write};
+
+use super::Register;
+
+/// Number of bytes rendered per line
+pub const BYTES_PER_LINE: usize = 16;
+
+/// Returns the ASCII rendering of a byte, `.` if it isn't printable
+fn ascii(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' {
+        byte as char
+    } else {
+        '.'
+    }
+}
+
+/// Write the contents of a single register
+///
+/// The contents are written as given, a dump that's shorter or longer than [`Register::size`] is still rendered.
+pub fn format_register(register: Register, data: &[u8], writer: &mut impl Write) -> Result {
+    writeln!(
+        writer,
+        "{:?} @ {:#04x} [{} bytes]:",
+        register,
+        register.addr(),
+        register.size()
+    )?;
+
+    for (line, chunk) in data.chunks(BYTES_PER_LINE).enumerate() {
+        write!(writer, "  {:02x}:", line * BYTES_PER_LINE)?;
+        for byte in chunk {
+            write!(writer, " {:02x}", byte)?;
+        }
+        // Align the ASCII column on the last line
+        for _ in chunk.len()..BYTES_PER_LINE {
+            writer.write_str("   ")?;
+        }
+
+        writer.write_str("  |")?;
+        for byte in chunk {
+            writer.write_char(ascii(*byte))?;
+        }
+        writer.write_str("|\n")?;
+    }
+
+    Ok(())
+}
+
+/// Write a dump of several registers, see [`format_register`]
+pub fn format_dump(dump: &[(Register, &[u8])], writer: &mut impl Write) -> Result {
+    for (register, data) in dump {
+        format_register(*register, data, writer)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_register() {
+        let mut out = heapless::String::<256>::new();
+        format_register(Register::Mode, b"APP0", &mut out).unwrap();
+        assert_eq!(
+            out.as_str(),
+            "Mode @ 0x03 [4 bytes]:\n  00: 41 50 50 30                                      |APP0|\n"
+        );
+    }
+
+    #[test]
+    fn test_format_dump() {
+        let mut status = [0u8; 18];
+        status[0] = 0x01;
+        status[17] = 0x7F;
+
+        let mut out = heapless::String::<512>::new();
+        format_dump(
+            &[(Register::Version, &[0x78, 0x56, 0x34, 0x12]), (Register::Uid, &status)],
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            out.as_str(),
+            "Version @ 0x0f [4 bytes]:\n\
+             \x20 00: 78 56 34 12                                      |xV4.|\n\
+             Uid @ 0x05 [16 bytes]:\n\
+             \x20 00: 01 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00  |................|\n\
+             \x20 10: 00 7f                                            |..|\n"
+        );
+    }
+
+    #[test]
+    fn test_format_dump_overflow() {
+        // Writer errors are propagated
+        let mut out = heapless::String::<8>::new();
+        assert!(format_dump(&[(Register::Mode, b"APP0")], &mut out).is_err());
+    }
+}
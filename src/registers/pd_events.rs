@@ -0,0 +1,237 @@
+//! PD events decoded from the interrupt flags and from port status changes
+//!
+//! A single interrupt can report several events at once. [`decode`] lists those reported by the flags, and
+//! [`super::status_change::diff`] those found by comparing port status snapshots after a status updated interrupt.
+//! Both produce [`PdInterruptEvent`]s, whose priority order is faults first, then connection changes, role changes,
+//! contract changes, and messages from the partner. Command completion and other flags that only concern the driver
+//! aren't decoded. Some events also depend on the port status read alongside the flags, see [`needs_status`].
+use super::field_sets::{IntEventBus1, Status};
+use super::port_config::PowerRole;
+use super::{PlugMode, VbusMode};
+
+/// PD event reported by the interrupt flags or a status change, in priority order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PdInterruptEvent {
+    /// A PD hard reset was performed
+    HardReset,
+    /// Overcurrent detected
+    Overcurrent,
+    /// A plug was inserted or removed
+    PlugEvent,
+    /// A plug was inserted, from a status change
+    Attached,
+    /// A plug was removed, from a status change
+    Detached,
+    /// Connection state changed, from a status change
+    ConnectionChanged(PlugMode),
+    /// Fast role swap completed
+    FastRoleSwapCompleted,
+    /// Power role swap completed
+    PowerSwapCompleted,
+    /// Data role swap completed
+    DataSwapCompleted,
+    /// Power role changed while attached, e.g. after a power role swap, from a status change
+    PowerRoleChanged {
+        /// The port is now the source
+        source: bool,
+    },
+    /// Data role changed while attached, e.g. after a data role swap, from a status change
+    DataRoleChanged {
+        /// The port is now the DFP
+        dfp: bool,
+    },
+    /// A new contract was negotiated
+    NewContract {
+        /// Power role of the port under the new contract, [`PowerRole::Sink`] or [`PowerRole::Source`]
+        role: PowerRole,
+    },
+    /// The contract ended, from a status change
+    ContractEnded,
+    /// EPR contract entered or exited, from a status change
+    EprModeChanged {
+        /// EPR mode is now active
+        active: bool,
+    },
+    /// VBUS status changed, from a status change
+    VbusChanged(VbusMode),
+    /// Source capabilities received from the partner
+    SourceCapsReceived,
+    /// Sink capabilities received from the partner
+    SinkCapsReceived,
+    /// The partner requested a power role swap
+    PowerSwapRequested,
+    /// The partner requested a data role swap
+    DataSwapRequested,
+    /// The partner sent an Alert message
+    AlertReceived,
+    /// The partner sent an Attention VDM
+    AttentionReceived,
+    /// The partner sent a VDM
+    VdmReceived,
+}
+
+/// Number of kinds of [`PdInterruptEvent`], events of the same kind differ only in the data they carry
+pub const PD_EVENT_KINDS: usize = 22;
+
+/// Events decoded from a single set of interrupt flags or a single status change
+pub type PdInterruptEvents = heapless::Vec<PdInterruptEvent, PD_EVENT_KINDS>;
+
+impl PdInterruptEvent {
+    /// Returns the position of this kind of event in the priority order, lower is higher priority
+    pub fn priority(&self) -> usize {
+        match self {
+            PdInterruptEvent::HardReset => 0,
+            PdInterruptEvent::Overcurrent => 1,
+            PdInterruptEvent::PlugEvent => 2,
+            PdInterruptEvent::Attached => 3,
+            PdInterruptEvent::Detached => 4,
+            PdInterruptEvent::ConnectionChanged(_) => 5,
+            PdInterruptEvent::FastRoleSwapCompleted => 6,
+            PdInterruptEvent::PowerSwapCompleted => 7,
+            PdInterruptEvent::DataSwapCompleted => 8,
+            PdInterruptEvent::PowerRoleChanged { .. } => 9,
+            PdInterruptEvent::DataRoleChanged { .. } => 10,
+            PdInterruptEvent::NewContract { .. } => 11,
+            PdInterruptEvent::ContractEnded => 12,
+            PdInterruptEvent::EprModeChanged { .. } => 13,
+            PdInterruptEvent::VbusChanged(_) => 14,
+            PdInterruptEvent::SourceCapsReceived => 15,
+            PdInterruptEvent::SinkCapsReceived => 16,
+            PdInterruptEvent::PowerSwapRequested => 17,
+            PdInterruptEvent::DataSwapRequested => 18,
+            PdInterruptEvent::AlertReceived => 19,
+            PdInterruptEvent::AttentionReceived => 20,
+            PdInterruptEvent::VdmReceived => 21,
+        }
+    }
+}
+
+/// Returns the power role of a port under its current contract
+pub(super) fn contract_role(status: &Status) -> PowerRole {
+    if status.port_role() {
+        PowerRole::Source
+    } else {
+        PowerRole::Sink
+    }
+}
+
+/// Returns true if decoding the flags depends on the port status
+///
+/// The role of a new contract is taken from the status rather than from which contract flag is set. When this
+/// returns false any status can be passed to [`decode`].
+pub fn needs_status(flags: &IntEventBus1) -> bool {
+    flags.new_consumer_contract() || flags.new_provider_contract()
+}
+
+/// Returns the events reported by the flags in priority order, `status` is the port status read alongside the flags
+///
+/// Events found by diffing status snapshots aren't reported here, see [`super::status_change::diff`].
+pub fn decode(flags: &IntEventBus1, status: &Status) -> PdInterruptEvents {
+    let mut events = PdInterruptEvents::new();
+    let reported = [
+        (flags.hard_reset(), PdInterruptEvent::HardReset),
+        (flags.overcurrent(), PdInterruptEvent::Overcurrent),
+        (flags.plug_event(), PdInterruptEvent::PlugEvent),
+        (
+            flags.fast_role_swap_completed(),
+            PdInterruptEvent::FastRoleSwapCompleted,
+        ),
+        (flags.power_swap_completed(), PdInterruptEvent::PowerSwapCompleted),
+        (flags.data_swap_completed(), PdInterruptEvent::DataSwapCompleted),
+        (
+            needs_status(flags),
+            PdInterruptEvent::NewContract {
+                role: contract_role(status),
+            },
+        ),
+        (flags.source_caps_received(), PdInterruptEvent::SourceCapsReceived),
+        (flags.sink_caps_received(), PdInterruptEvent::SinkCapsReceived),
+        (flags.power_swap_requested(), PdInterruptEvent::PowerSwapRequested),
+        (flags.data_swap_requested(), PdInterruptEvent::DataSwapRequested),
+        (flags.alert_message_received(), PdInterruptEvent::AlertReceived),
+        (flags.attention_received(), PdInterruptEvent::AttentionReceived),
+        (flags.vdm_received(), PdInterruptEvent::VdmReceived),
+    ];
+
+    for (_, event) in reported.into_iter().filter(|(set, _)| *set) {
+        // Can't fail, there are fewer candidates than the capacity
+        let _ = events.push(event);
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let mut flags = IntEventBus1::new_zero();
+        flags.set_vdm_received(true);
+        flags.set_new_consumer_contract(true);
+        flags.set_hard_reset(true);
+        flags.set_cmd_1_completed(true);
+
+        assert_eq!(
+            decode(&flags, &Status::new_zero()).as_slice(),
+            &[
+                PdInterruptEvent::HardReset,
+                PdInterruptEvent::NewContract { role: PowerRole::Sink },
+                PdInterruptEvent::VdmReceived,
+            ]
+        );
+        assert!(decode(&IntEventBus1::new_zero(), &Status::new_zero()).is_empty());
+    }
+
+    #[test]
+    fn test_decode_contract_role() {
+        let mut source = Status::new_zero();
+        source.set_port_role(true);
+
+        // The role comes from the status, regardless of which contract flag is set
+        for (consumer, provider) in [(true, false), (false, true), (true, true)] {
+            let mut flags = IntEventBus1::new_zero();
+            flags.set_new_consumer_contract(consumer);
+            flags.set_new_provider_contract(provider);
+            assert!(needs_status(&flags));
+
+            assert_eq!(
+                decode(&flags, &Status::new_zero()).as_slice(),
+                &[PdInterruptEvent::NewContract { role: PowerRole::Sink }]
+            );
+            assert_eq!(
+                decode(&flags, &source).as_slice(),
+                &[PdInterruptEvent::NewContract {
+                    role: PowerRole::Source
+                }]
+            );
+        }
+
+        assert!(!needs_status(&IntEventBus1::new_zero()));
+    }
+
+    #[test]
+    fn test_decode_in_priority_order() {
+        let mut flags = IntEventBus1::new_zero();
+        flags.set_hard_reset(true);
+        flags.set_overcurrent(true);
+        flags.set_plug_event(true);
+        flags.set_fast_role_swap_completed(true);
+        flags.set_power_swap_completed(true);
+        flags.set_data_swap_completed(true);
+        flags.set_new_provider_contract(true);
+        flags.set_source_caps_received(true);
+        flags.set_sink_caps_received(true);
+        flags.set_power_swap_requested(true);
+        flags.set_data_swap_requested(true);
+        flags.set_alert_message_received(true);
+        flags.set_attention_received(true);
+        flags.set_vdm_received(true);
+
+        let events = decode(&flags, &Status::new_zero());
+        assert_eq!(events.len(), 14);
+        assert!(events.windows(2).all(|pair| pair[0].priority() < pair[1].priority()));
+    }
+}
@@ -0,0 +1,196 @@
+//! Typed views over the port configuration register
+use embedded_usb_pd::PdError;
+
+use super::field_sets::{PortConfig, PortControl};
+use super::{TypecStateMachine, TypecSupportOptions};
+
+/// Power role the Type-C state machine starts in, takes effect on the next attach
+///
+/// [`RolePreference`] only applies when the port is configured as [`PowerRole::Drp`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PowerRole {
+    /// Sink only, the port never sources
+    Sink,
+    /// Source only
+    Source,
+    /// Dual-role, toggling between source and sink
+    Drp,
+}
+
+impl TryFrom<TypecStateMachine> for PowerRole {
+    type Error = PdError;
+
+    fn try_from(value: TypecStateMachine) -> Result<Self, Self::Error> {
+        match value {
+            TypecStateMachine::Sink => Ok(PowerRole::Sink),
+            TypecStateMachine::Source => Ok(PowerRole::Source),
+            TypecStateMachine::Drp => Ok(PowerRole::Drp),
+            TypecStateMachine::Disabled => Err(PdError::InvalidParams),
+        }
+    }
+}
+
+impl From<PowerRole> for TypecStateMachine {
+    fn from(value: PowerRole) -> Self {
+        match value {
+            PowerRole::Sink => TypecStateMachine::Sink,
+            PowerRole::Source => TypecStateMachine::Source,
+            PowerRole::Drp => TypecStateMachine::Drp,
+        }
+    }
+}
+
+/// Autonomous power role policy
+///
+/// Stronger than [`PowerRole`], the policy also blocks the role swaps that would leave the port in a forbidden role.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RolePolicy {
+    /// Only sink, the port never sources or swaps to source
+    SinkOnly,
+    /// Only source, the port never sinks or swaps to sink
+    SourceOnly,
+    /// Dual-role, swaps are left as configured
+    DualRole,
+}
+
+impl RolePolicy {
+    /// Returns true if the policy allows the given power role, `true` for source as in the status register
+    pub fn allows(self, source: bool) -> bool {
+        match self {
+            RolePolicy::SinkOnly => !source,
+            RolePolicy::SourceOnly => source,
+            RolePolicy::DualRole => true,
+        }
+    }
+
+    /// Disable the role swaps the policy forbids in the given port control value
+    pub fn apply_swaps(self, control: &mut PortControl) {
+        match self {
+            RolePolicy::SinkOnly => {
+                control.set_process_swap_to_source(false);
+                control.set_initiate_swap_to_source(false);
+            }
+            RolePolicy::SourceOnly => {
+                control.set_process_swap_to_sink(false);
+                control.set_initiate_swap_to_sink(false);
+            }
+            RolePolicy::DualRole => {}
+        }
+    }
+}
+
+impl From<RolePolicy> for PowerRole {
+    fn from(value: RolePolicy) -> Self {
+        match value {
+            RolePolicy::SinkOnly => PowerRole::Sink,
+            RolePolicy::SourceOnly => PowerRole::Source,
+            RolePolicy::DualRole => PowerRole::Drp,
+        }
+    }
+}
+
+/// Dual-role port preference, takes effect on the next attach
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RolePreference {
+    /// No preference, the role is determined by normal DRP toggling
+    #[default]
+    None,
+    /// Prefer the source role using Try.SRC
+    TrySrc,
+    /// Prefer the sink role using Try.SNK
+    TrySnk,
+}
+
+impl TryFrom<TypecSupportOptions> for RolePreference {
+    type Error = PdError;
+
+    fn try_from(value: TypecSupportOptions) -> Result<Self, Self::Error> {
+        match value {
+            TypecSupportOptions::NoOptions => Ok(RolePreference::None),
+            TypecSupportOptions::TrySrc => Ok(RolePreference::TrySrc),
+            TypecSupportOptions::TrySnk => Ok(RolePreference::TrySnk),
+            _ => Err(PdError::InvalidParams),
+        }
+    }
+}
+
+impl From<RolePreference> for TypecSupportOptions {
+    fn from(value: RolePreference) -> Self {
+        match value {
+            RolePreference::None => TypecSupportOptions::NoOptions,
+            RolePreference::TrySrc => TypecSupportOptions::TrySrc,
+            RolePreference::TrySnk => TypecSupportOptions::TrySnk,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_role_preference_round_trip() {
+        for pref in [RolePreference::None, RolePreference::TrySrc, RolePreference::TrySnk] {
+            let options: TypecSupportOptions = pref.into();
+            assert_eq!(RolePreference::try_from(options).ok(), Some(pref));
+        }
+    }
+
+    #[test]
+    fn test_role_preference_mapping() {
+        assert_eq!(
+            TypecSupportOptions::from(RolePreference::None),
+            TypecSupportOptions::NoOptions
+        );
+        assert_eq!(
+            TypecSupportOptions::from(RolePreference::TrySrc),
+            TypecSupportOptions::TrySrc
+        );
+        assert_eq!(
+            TypecSupportOptions::from(RolePreference::TrySnk),
+            TypecSupportOptions::TrySnk
+        );
+    }
+
+    #[test]
+    fn test_power_role_mapping() {
+        for role in [PowerRole::Sink, PowerRole::Source, PowerRole::Drp] {
+            let state: TypecStateMachine = role.into();
+            assert_eq!(PowerRole::try_from(state).ok(), Some(role));
+        }
+        assert!(PowerRole::try_from(TypecStateMachine::Disabled).is_err());
+    }
+
+    #[test]
+    fn test_role_policy() {
+        assert!(RolePolicy::SinkOnly.allows(false));
+        assert!(!RolePolicy::SinkOnly.allows(true));
+        assert!(!RolePolicy::SourceOnly.allows(false));
+        assert!(RolePolicy::SourceOnly.allows(true));
+        assert!(RolePolicy::DualRole.allows(false));
+        assert!(RolePolicy::DualRole.allows(true));
+
+        let mut all = PortControl::new_zero();
+        all.set_process_swap_to_sink(true);
+        all.set_initiate_swap_to_sink(true);
+        all.set_process_swap_to_source(true);
+        all.set_initiate_swap_to_source(true);
+
+        let mut control = all;
+        RolePolicy::SinkOnly.apply_swaps(&mut control);
+        assert!(control.process_swap_to_sink() && control.initiate_swap_to_sink());
+        assert!(!control.process_swap_to_source() && !control.initiate_swap_to_source());
+
+        let mut control = all;
+        RolePolicy::SourceOnly.apply_swaps(&mut control);
+        assert!(!control.process_swap_to_sink() && !control.initiate_swap_to_sink());
+        assert!(control.process_swap_to_source() && control.initiate_swap_to_source());
+
+        let mut control = all;
+        RolePolicy::DualRole.apply_swaps(&mut control);
+        assert_eq!(control, all);
+    }
+}
@@ -0,0 +1,148 @@
+//! Typed views over the port control register
+use core::ops::RangeInclusive;
+
+use super::field_sets::{PortControl, Status};
+use super::{VbusMode, VconnCurrentLimit};
+
+/// Lower VCONN current limit
+pub const VCONN_CURRENT_LIMIT_LOW_MA: u16 = 410;
+/// Higher VCONN current limit, the default
+pub const VCONN_CURRENT_LIMIT_HIGH_MA: u16 = 590;
+/// Supported VCONN current limits, only the two ends of the range are selectable
+///
+/// The default of 590 mA covers active cables drawing up to the 1 W Type-C requires at the minimum VCONN voltage. A
+/// board that can't supply that much can lower the limit, at the risk of tripping on high-draw cables.
+pub const VCONN_CURRENT_LIMIT_RANGE_MA: RangeInclusive<u16> = VCONN_CURRENT_LIMIT_LOW_MA..=VCONN_CURRENT_LIMIT_HIGH_MA;
+
+/// Automatic discovery and VDM configuration
+///
+/// By default the controller issues Discover Identity on attach and manages SVID/mode discovery and
+/// alternate mode entry on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AutoVdmConfig {
+    /// Automatically issue Discover Identity VDMs to the port partner and cable on attach
+    pub discover_identity: bool,
+    /// Automatically perform SVID and mode discovery, otherwise these are left to the host
+    pub discover_modes: bool,
+}
+
+impl Default for AutoVdmConfig {
+    fn default() -> Self {
+        PortControl::new().into()
+    }
+}
+
+impl AutoVdmConfig {
+    /// Apply this configuration to the given port control value
+    pub fn apply(&self, control: &mut PortControl) {
+        control.set_automatic_id_request(self.discover_identity);
+        // Intrusive mode hands the alternate mode process over to the host
+        control.set_am_intrusive_mode(!self.discover_modes);
+    }
+}
+
+impl From<PortControl> for AutoVdmConfig {
+    fn from(control: PortControl) -> Self {
+        Self {
+            discover_identity: control.automatic_id_request(),
+            discover_modes: !control.am_intrusive_mode(),
+        }
+    }
+}
+
+/// Fast role swap status
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FrsStatus {
+    /// Fast role swap is enabled on the port
+    pub armed: bool,
+    /// A fast role swap completed since the status was last read
+    pub triggered: bool,
+    /// The port is attached as the source
+    pub sourcing: bool,
+    /// VBUS is above vSafe0V
+    pub vbus_present: bool,
+}
+
+impl FrsStatus {
+    /// Decode the status from the port control and status registers, `triggered` is tracked by the caller
+    pub fn new(control: &PortControl, status: &Status, triggered: bool) -> Self {
+        Self {
+            armed: control.fr_swap_enabled(),
+            triggered,
+            sourcing: status.plug_present() && status.port_role(),
+            vbus_present: status.vbus_status() != VbusMode::AtVsafe0,
+        }
+    }
+}
+
+/// Convert a VCONN current limit to mA
+pub fn vconn_current_limit_to_ma(limit: VconnCurrentLimit) -> u16 {
+    match limit {
+        VconnCurrentLimit::Current410ma => VCONN_CURRENT_LIMIT_LOW_MA,
+        VconnCurrentLimit::Current590ma => VCONN_CURRENT_LIMIT_HIGH_MA,
+    }
+}
+
+/// Convert a VCONN current limit in mA to the highest supported limit that doesn't exceed it
+///
+/// Returns `None` if the limit is outside [`VCONN_CURRENT_LIMIT_RANGE_MA`].
+pub fn vconn_current_limit_from_ma(ma: u16) -> Option<VconnCurrentLimit> {
+    if !VCONN_CURRENT_LIMIT_RANGE_MA.contains(&ma) {
+        return None;
+    }
+
+    Some(if ma < VCONN_CURRENT_LIMIT_HIGH_MA {
+        VconnCurrentLimit::Current410ma
+    } else {
+        VconnCurrentLimit::Current590ma
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_vconn_current_limit() {
+        assert_eq!(vconn_current_limit_to_ma(VconnCurrentLimit::Current410ma), 410);
+        assert_eq!(vconn_current_limit_to_ma(VconnCurrentLimit::Current590ma), 590);
+        assert_eq!(vconn_current_limit_to_ma(PortControl::new().vconn_current_limit()), 590);
+
+        assert_eq!(vconn_current_limit_from_ma(410), Some(VconnCurrentLimit::Current410ma));
+        // Rounds down to the nearest step
+        assert_eq!(vconn_current_limit_from_ma(589), Some(VconnCurrentLimit::Current410ma));
+        assert_eq!(vconn_current_limit_from_ma(590), Some(VconnCurrentLimit::Current590ma));
+        assert_eq!(vconn_current_limit_from_ma(409), None);
+        assert_eq!(vconn_current_limit_from_ma(600), None);
+    }
+
+    #[test]
+    fn test_frs_status() {
+        let mut control = PortControl::new_zero();
+        control.set_fr_swap_enabled(true);
+        let mut status = Status::new_zero();
+        assert_eq!(
+            FrsStatus::new(&control, &status, false),
+            FrsStatus {
+                armed: true,
+                ..Default::default()
+            }
+        );
+
+        // Swapped to source and providing VBUS
+        status.set_plug_present(true);
+        status.set_port_role(true);
+        status.set_vbus_status(VbusMode::Atvsafe5);
+        assert_eq!(
+            FrsStatus::new(&control, &status, true),
+            FrsStatus {
+                armed: true,
+                triggered: true,
+                sourcing: true,
+                vbus_present: true,
+            }
+        );
+    }
+}
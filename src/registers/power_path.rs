@@ -0,0 +1,63 @@
+//! Decoded power path fault status
+use embedded_usb_pd::{PdError, PortId};
+
+use super::field_sets::PowerPathStatus;
+use crate::{PORT0, PORT1};
+
+/// Overcurrent faults on a port's power paths
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OvercurrentStatus {
+    /// Internal VBUS switch overcurrent
+    pub vbus: bool,
+    /// VCONN switch overcurrent
+    pub vconn: bool,
+}
+
+impl OvercurrentStatus {
+    /// Decode the overcurrent faults for the given port
+    pub fn new(status: &PowerPathStatus, port: PortId) -> Result<Self, PdError> {
+        match port {
+            PORT0 => Ok(Self {
+                vbus: status.pa_int_vbus_oc(),
+                vconn: status.pa_vconn_oc(),
+            }),
+            PORT1 => Ok(Self {
+                vbus: status.pb_int_vbus_oc(),
+                vconn: status.pb_vconn_oc(),
+            }),
+            _ => Err(PdError::InvalidPort),
+        }
+    }
+
+    /// Returns true if any overcurrent fault is present
+    pub fn any(&self) -> bool {
+        self.vbus || self.vconn
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_overcurrent_status() {
+        let mut status = PowerPathStatus::new_zero();
+        status.set_pa_int_vbus_oc(true);
+        status.set_pb_vconn_oc(true);
+
+        let port0 = OvercurrentStatus::new(&status, PORT0).unwrap();
+        assert!(port0.vbus);
+        assert!(!port0.vconn);
+        assert!(port0.any());
+
+        let port1 = OvercurrentStatus::new(&status, PORT1).unwrap();
+        assert!(!port1.vbus);
+        assert!(port1.vconn);
+
+        assert!(!OvercurrentStatus::new(&PowerPathStatus::new_zero(), PORT0)
+            .unwrap()
+            .any());
+        assert!(OvercurrentStatus::new(&status, PortId(2)).is_err());
+    }
+}
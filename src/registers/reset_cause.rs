@@ -0,0 +1,86 @@
+//! Most severe reset recorded in the PD status register
+//!
+//! PD status holds separate details for the last soft reset, hard reset, data reset, and error recovery. The details
+//! persist until the next reset of the same kind and there's no record of which happened last, so the most severe
+//! reset recorded is reported, in the order error recovery > hard reset > data reset > soft reset. The reported
+//! reset isn't necessarily the most recent one.
+use super::field_sets::PdStatus;
+use super::{PdDataResetDetails, PdErrorRecoveryDetails, PdHardResetDetails, PdSoftResetDetails};
+
+/// Most severe reset recorded, see the [module docs](self) for the priority order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ResetCause {
+    /// No reset recorded
+    None,
+    /// Error recovery, the port disconnected and re-attached, e.g. after a fault or a failed swap
+    ErrorRecovery(PdErrorRecoveryDetails),
+    /// Hard reset, sent or received, e.g. requested by the partner or host or after a protocol timeout
+    HardReset(PdHardResetDetails),
+    /// Data reset, e.g. requested by the partner or host
+    DataReset(PdDataResetDetails),
+    /// Soft reset, sent or received after a protocol error
+    SoftReset(PdSoftResetDetails),
+}
+
+impl ResetCause {
+    /// Decode the most severe reset recorded in PD status, error recovery > hard reset > data reset > soft reset
+    pub fn new(status: &PdStatus) -> Self {
+        let error_recovery = status.error_recovery_details();
+        if error_recovery != PdErrorRecoveryDetails::NoErrorRecovery {
+            return Self::ErrorRecovery(error_recovery);
+        }
+
+        let hard_reset = status.hard_reset_details();
+        if hard_reset != PdHardResetDetails::ResetValueNoHardReset {
+            return Self::HardReset(hard_reset);
+        }
+
+        let data_reset = status.data_reset_details();
+        if data_reset != PdDataResetDetails::NoDataReset {
+            return Self::DataReset(data_reset);
+        }
+
+        let soft_reset = status.soft_reset_details();
+        if soft_reset != PdSoftResetDetails::NoSoftReset {
+            return Self::SoftReset(soft_reset);
+        }
+
+        Self::None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reset_cause() {
+        let mut status = PdStatus::new_zero();
+        assert_eq!(ResetCause::new(&status), ResetCause::None);
+
+        status.set_soft_reset_details(PdSoftResetDetails::MessageRetriesExhausted);
+        assert_eq!(
+            ResetCause::new(&status),
+            ResetCause::SoftReset(PdSoftResetDetails::MessageRetriesExhausted)
+        );
+
+        status.set_data_reset_details(PdDataResetDetails::ReceivedFromPortPartner);
+        assert_eq!(
+            ResetCause::new(&status),
+            ResetCause::DataReset(PdDataResetDetails::ReceivedFromPortPartner)
+        );
+
+        status.set_hard_reset_details(PdHardResetDetails::ReceivedFromPortPartner);
+        assert_eq!(
+            ResetCause::new(&status),
+            ResetCause::HardReset(PdHardResetDetails::ReceivedFromPortPartner)
+        );
+
+        status.set_error_recovery_details(PdErrorRecoveryDetails::OverVoltageOnPxVbus);
+        assert_eq!(
+            ResetCause::new(&status),
+            ResetCause::ErrorRecovery(PdErrorRecoveryDetails::OverVoltageOnPxVbus)
+        );
+    }
+}
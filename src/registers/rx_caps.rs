@@ -0,0 +1,103 @@
+//! Received source and sink capabilities registers
+//!
+//! Both registers hold the capabilities most recently received from the port partner, in the same layout as the
+//! transmit sink capabilities register. Received sink capabilities decode as [`super::tx_sink_caps::SinkCapabilities`].
+use bincode::de::Decoder;
+use bincode::error::DecodeError;
+use bincode::Decode;
+
+use super::tx_sink_caps::MAX_SINK_PDOS;
+use crate::pdo::SourcePdo;
+
+/// Received source capabilities register
+pub const REG_RX_SOURCE_CAPS: u8 = super::Register::RxSourceCaps.addr();
+/// Received source capabilities register length
+pub const REG_RX_SOURCE_CAPS_LEN: usize = super::Register::RxSourceCaps.size();
+/// Received sink capabilities register
+pub const REG_RX_SINK_CAPS: u8 = super::Register::RxSinkCaps.addr();
+/// Received sink capabilities register length
+pub const REG_RX_SINK_CAPS_LEN: usize = super::Register::RxSinkCaps.size();
+/// Maximum number of source PDOs
+pub const MAX_SOURCE_PDOS: usize = MAX_SINK_PDOS;
+
+/// Mask of the PDO count in the first byte
+const PDO_COUNT_MASK: u8 = 0x7;
+
+/// Returns the number of valid PDOs in a raw received capabilities register, without decoding the PDOs
+pub fn pdo_count(raw: &[u8]) -> u8 {
+    raw.first().map_or(0, |count| count & PDO_COUNT_MASK)
+}
+
+/// Source capabilities received from the port partner
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SourceCapabilities {
+    pdos: heapless::Vec<SourcePdo, MAX_SOURCE_PDOS>,
+}
+
+impl SourceCapabilities {
+    /// Returns the source PDOs
+    pub fn pdos(&self) -> &[SourcePdo] {
+        &self.pdos
+    }
+}
+
+impl<Context> Decode<Context> for SourceCapabilities {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let count: u8 = Decode::decode(decoder)?;
+        let count = pdo_count(&[count]) as usize;
+
+        let mut pdos = heapless::Vec::new();
+        for i in 0..MAX_SOURCE_PDOS {
+            let raw: u32 = Decode::decode(decoder)?;
+            if i < count {
+                let pdo = SourcePdo::try_from(raw).map_err(|_| DecodeError::Other("Invalid source PDO"))?;
+                // Can't fail, count is bounded by the capacity
+                let _ = pdos.push(pdo);
+            }
+        }
+
+        Ok(Self { pdos })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bincode::config;
+
+    use super::*;
+
+    #[test]
+    fn test_pdo_count() {
+        assert_eq!(pdo_count(&[]), 0);
+        assert_eq!(pdo_count(&[0x00, 0x2C]), 0);
+        // Upper bits aren't part of the count
+        assert_eq!(pdo_count(&[0xF3]), 3);
+    }
+
+    #[test]
+    fn test_decode_source_caps() {
+        let mut buf = [0u8; REG_RX_SOURCE_CAPS_LEN];
+        buf[0] = 2;
+        buf[1..5].copy_from_slice(&0x0801_912Cu32.to_le_bytes());
+        buf[5..9].copy_from_slice(&0x0006_41F4u32.to_le_bytes());
+        // Stale PDO past the count is ignored
+        buf[9..13].copy_from_slice(&0xC000_0000u32.to_le_bytes());
+
+        let (caps, _): (SourceCapabilities, _) =
+            bincode::decode_from_slice(&buf, config::standard().with_fixed_int_encoding()).unwrap();
+        assert_eq!(
+            caps.pdos(),
+            &[
+                SourcePdo::Fixed {
+                    voltage_mv: 5000,
+                    max_current_ma: 3000,
+                },
+                SourcePdo::Fixed {
+                    voltage_mv: 20000,
+                    max_current_ma: 5000,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! Port events decoded by diffing port status snapshots
+//!
+//! The status updated interrupt only reports that something in the status register changed. Comparing the register,
+//! along with the active contract, against the previous snapshot turns that into the specific events that occurred.
+//! They're reported as [`PdInterruptEvent`]s, the same events decoded from the interrupt flags.
+use super::field_sets::Status;
+use super::pd_events::{contract_role, PdInterruptEvent, PdInterruptEvents};
+
+/// Port state compared by [`diff`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PortSnapshot {
+    /// Port status
+    pub status: Status,
+    /// Raw RDO of the active contract, zero if there is no contract
+    pub active_rdo: u32,
+}
+
+impl Default for PortSnapshot {
+    /// An unattached port without a contract
+    fn default() -> Self {
+        Self {
+            status: Status::new_zero(),
+            active_rdo: 0,
+        }
+    }
+}
+
+/// Returns the events between two snapshots, in priority order
+///
+/// Role, contract, and EPR changes are only reported while attached, they aren't meaningful otherwise. A contract
+/// ending is reported regardless, since it usually ends with a detach.
+pub fn diff(previous: &PortSnapshot, current: &PortSnapshot) -> PdInterruptEvents {
+    let mut events = PdInterruptEvents::new();
+    let (previous_status, status) = (&previous.status, &current.status);
+    let attached = status.plug_present();
+    let contract_changed = previous.active_rdo != current.active_rdo;
+
+    let changes = [
+        (previous_status.plug_present() != attached).then_some(if attached {
+            PdInterruptEvent::Attached
+        } else {
+            PdInterruptEvent::Detached
+        }),
+        (previous_status.connection_state() != status.connection_state())
+            .then(|| PdInterruptEvent::ConnectionChanged(status.connection_state())),
+        (attached && previous_status.port_role() != status.port_role()).then(|| PdInterruptEvent::PowerRoleChanged {
+            source: status.port_role(),
+        }),
+        (attached && previous_status.data_role() != status.data_role()).then(|| PdInterruptEvent::DataRoleChanged {
+            dfp: status.data_role(),
+        }),
+        (attached && contract_changed && current.active_rdo != 0).then(|| PdInterruptEvent::NewContract {
+            role: contract_role(status),
+        }),
+        (contract_changed && current.active_rdo == 0).then_some(PdInterruptEvent::ContractEnded),
+        (attached && previous_status.erp_mode() != status.erp_mode()).then(|| PdInterruptEvent::EprModeChanged {
+            active: status.erp_mode(),
+        }),
+        (previous_status.vbus_status() != status.vbus_status())
+            .then(|| PdInterruptEvent::VbusChanged(status.vbus_status())),
+    ];
+
+    for event in changes.into_iter().flatten() {
+        // Can't fail, there are fewer candidates than the capacity
+        let _ = events.push(event);
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::registers::port_config::PowerRole;
+    use crate::registers::{PlugMode, VbusMode};
+
+    fn attached() -> PortSnapshot {
+        let mut status = Status::new_zero();
+        status.set_plug_present(true);
+        status.set_connection_state(PlugMode::Connected);
+        status.set_vbus_status(VbusMode::Atvsafe5);
+        PortSnapshot { status, active_rdo: 0 }
+    }
+
+    #[test]
+    fn test_diff_no_change() {
+        let snapshot = attached();
+        assert!(diff(&snapshot, &snapshot).is_empty());
+    }
+
+    #[test]
+    fn test_diff_attach_detach() {
+        let detached = PortSnapshot::default();
+        let attached = attached();
+
+        assert_eq!(
+            diff(&detached, &attached).as_slice(),
+            &[
+                PdInterruptEvent::Attached,
+                PdInterruptEvent::ConnectionChanged(PlugMode::Connected),
+                PdInterruptEvent::VbusChanged(VbusMode::Atvsafe5),
+            ]
+        );
+        assert_eq!(
+            diff(&attached, &detached).as_slice(),
+            &[
+                PdInterruptEvent::Detached,
+                PdInterruptEvent::ConnectionChanged(PlugMode::NotConnected),
+                PdInterruptEvent::VbusChanged(VbusMode::AtVsafe0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_roles_and_epr() {
+        let previous = attached();
+        let mut current = previous;
+        current.status.set_port_role(true);
+        current.status.set_data_role(true);
+        current.status.set_erp_mode(true);
+
+        assert_eq!(
+            diff(&previous, &current).as_slice(),
+            &[
+                PdInterruptEvent::PowerRoleChanged { source: true },
+                PdInterruptEvent::DataRoleChanged { dfp: true },
+                PdInterruptEvent::EprModeChanged { active: true },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_contract() {
+        let previous = attached();
+
+        // New contract as sink
+        let mut current = previous;
+        current.active_rdo = 0x1304b12c;
+        assert_eq!(
+            diff(&previous, &current).as_slice(),
+            &[PdInterruptEvent::NewContract { role: PowerRole::Sink }]
+        );
+
+        // Renegotiated as source after a power role swap
+        let mut swapped = current;
+        swapped.status.set_port_role(true);
+        swapped.active_rdo = 0x2304b12c;
+        assert_eq!(
+            diff(&current, &swapped).as_slice(),
+            &[
+                PdInterruptEvent::PowerRoleChanged { source: true },
+                PdInterruptEvent::NewContract {
+                    role: PowerRole::Source
+                },
+            ]
+        );
+
+        // Ended along with a detach
+        assert_eq!(
+            diff(&swapped, &PortSnapshot::default()).as_slice(),
+            &[
+                PdInterruptEvent::Detached,
+                PdInterruptEvent::ConnectionChanged(PlugMode::NotConnected),
+                PdInterruptEvent::ContractEnded,
+                PdInterruptEvent::VbusChanged(VbusMode::AtVsafe0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_roles_ignored_while_detached() {
+        let previous = PortSnapshot::default();
+        let mut current = previous;
+        current.status.set_port_role(true);
+        current.status.set_data_role(true);
+        current.active_rdo = 0x1304b12c;
+
+        assert!(diff(&previous, &current).is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+//! Port telemetry snapshot
+//!
+//! Bundles the port status and active contract so telemetry can be read in one go instead of through separate getters.
+//! Two snapshots can be compared with [`PortTelemetry::diff`] to log only what changed.
+use super::field_sets::{ActivePdoContract, ActiveRdoContract, Status};
+use super::PlugMode;
+use crate::pdo::{Rdo, SourcePdo};
+
+/// Maximum number of changes produced by a single diff
+pub const MAX_TELEMETRY_CHANGES: usize = 5;
+
+/// Change between two telemetry snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TelemetryChange {
+    /// Connection state changed
+    Connection(PlugMode),
+    /// Power role changed
+    PowerRole {
+        /// The port is now the source
+        source: bool,
+    },
+    /// Object position of the contracted PDO changed, zero if there is no contract
+    ContractIndex(u8),
+    /// Contract voltage changed, see [`PortTelemetry::contract_voltage_mv`]
+    Voltage(Option<u16>),
+    /// Contract operating current changed, in mA
+    Current(u16),
+}
+
+/// Changes produced by a single diff, in the order of [`TelemetryChange`]'s variants
+pub type TelemetryChanges = heapless::Vec<TelemetryChange, MAX_TELEMETRY_CHANGES>;
+
+/// Port status and active contract read together
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PortTelemetry {
+    /// Port status
+    pub status: Status,
+    /// Raw PDO of the active contract, zero if there is no contract
+    pub active_pdo: u32,
+    /// RDO of the active contract, zero if there is no contract
+    pub active_rdo: Rdo,
+    /// Operating current of the active contract in mA, see [`Rdo::operating_current_ma`]
+    ///
+    /// The controller doesn't measure VBUS current, this is the current negotiated by the contract. Zero if there is
+    /// no contract or the contract isn't for a fixed or variable supply.
+    pub contract_current_ma: u16,
+}
+
+impl PortTelemetry {
+    /// Create a snapshot from the port status and active contract registers
+    pub fn new(status: Status, pdo_contract: &ActivePdoContract, rdo_contract: &ActiveRdoContract) -> Self {
+        let active_pdo = pdo_contract.active_pdo();
+        let active_rdo = Rdo(rdo_contract.active_rdo());
+        Self {
+            status,
+            active_pdo,
+            active_rdo,
+            contract_current_ma: active_rdo.operating_current_ma(active_pdo).unwrap_or(0),
+        }
+    }
+
+    /// Returns the voltage of the active contract in mV
+    ///
+    /// `None` if there is no contract or the contract isn't for a fixed supply, other supplies only have a range.
+    pub fn contract_voltage_mv(&self) -> Option<u16> {
+        if self.active_pdo == 0 {
+            return None;
+        }
+
+        match SourcePdo::try_from(self.active_pdo) {
+            Ok(SourcePdo::Fixed { voltage_mv, .. }) => Some(voltage_mv),
+            _ => None,
+        }
+    }
+
+    /// Returns what changed since the `prev` snapshot
+    pub fn diff(&self, prev: &PortTelemetry) -> TelemetryChanges {
+        let mut changes = TelemetryChanges::new();
+        let position = self.active_rdo.object_position();
+        let voltage_mv = self.contract_voltage_mv();
+
+        let candidates = [
+            (prev.status.connection_state() != self.status.connection_state())
+                .then(|| TelemetryChange::Connection(self.status.connection_state())),
+            (prev.status.port_role() != self.status.port_role()).then(|| TelemetryChange::PowerRole {
+                source: self.status.port_role(),
+            }),
+            (prev.active_rdo.object_position() != position).then_some(TelemetryChange::ContractIndex(position)),
+            (prev.contract_voltage_mv() != voltage_mv).then_some(TelemetryChange::Voltage(voltage_mv)),
+            (prev.contract_current_ma != self.contract_current_ma)
+                .then_some(TelemetryChange::Current(self.contract_current_ma)),
+        ];
+
+        for change in candidates.into_iter().flatten() {
+            // Can't fail, there are exactly as many candidates as the capacity
+            let _ = changes.push(change);
+        }
+
+        changes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Fixed 5 V 3 A source PDO
+    const PDO_5V: u32 = 0x0001_912C;
+    /// Fixed 9 V 3 A source PDO
+    const PDO_9V: u32 = 0x0002_D12C;
+    /// Variable 5-20 V 3 A source PDO
+    const PDO_VARIABLE: u32 = 0x9901_912C;
+
+    fn contract(pdo: u32, position: u8, current_ma: u16) -> PortTelemetry {
+        let mut status = Status::new_zero();
+        status.set_plug_present(true);
+        status.set_connection_state(PlugMode::Connected);
+        PortTelemetry {
+            status,
+            active_pdo: pdo,
+            active_rdo: Rdo((position as u32) << 28),
+            contract_current_ma: current_ma,
+        }
+    }
+
+    #[test]
+    fn test_contract_voltage() {
+        assert_eq!(contract(PDO_5V, 1, 3000).contract_voltage_mv(), Some(5000));
+        assert_eq!(contract(PDO_VARIABLE, 2, 3000).contract_voltage_mv(), None);
+        assert_eq!(contract(0, 0, 0).contract_voltage_mv(), None);
+    }
+
+    #[test]
+    fn test_diff_no_change() {
+        let telemetry = contract(PDO_5V, 1, 3000);
+        assert!(telemetry.diff(&telemetry).is_empty());
+    }
+
+    #[test]
+    fn test_diff_contract() {
+        let previous = contract(PDO_5V, 1, 3000);
+        assert_eq!(
+            contract(PDO_9V, 2, 2000).diff(&previous).as_slice(),
+            &[
+                TelemetryChange::ContractIndex(2),
+                TelemetryChange::Voltage(Some(9000)),
+                TelemetryChange::Current(2000),
+            ]
+        );
+        assert_eq!(
+            contract(PDO_VARIABLE, 2, 3000).diff(&previous).as_slice(),
+            &[TelemetryChange::ContractIndex(2), TelemetryChange::Voltage(None)]
+        );
+    }
+
+    #[test]
+    fn test_diff_connection_and_role() {
+        let previous = contract(PDO_5V, 1, 3000);
+        let mut current = previous;
+        current.status.set_port_role(true);
+        assert_eq!(
+            current.diff(&previous).as_slice(),
+            &[TelemetryChange::PowerRole { source: true }]
+        );
+
+        let detached = PortTelemetry {
+            status: Status::new_zero(),
+            active_pdo: 0,
+            active_rdo: Rdo(0),
+            contract_current_ma: 0,
+        };
+        assert_eq!(
+            detached.diff(&previous).as_slice(),
+            &[
+                TelemetryChange::Connection(PlugMode::NotConnected),
+                TelemetryChange::ContractIndex(0),
+                TelemetryChange::Voltage(None),
+                TelemetryChange::Current(0),
+            ]
+        );
+    }
+}
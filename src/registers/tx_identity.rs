@@ -0,0 +1,169 @@
+//! Transmit identity register
+//!
+//! Holds the response to Discover Identity requests from the port partner. Byte 0 holds the number of valid VDOs in
+//! bits 2:0 and the NAK flag in bit 7, followed by the VDOs as little-endian u32 values, padded to
+//! [`MAX_IDENTITY_VDOS`].
+//!
+//! By default the controller ACKs Discover Identity with the VDOs from the application configuration, in either data
+//! role. A host whose default identity confuses a partner can NAK the request or respond with its own VDOs, e.g. to
+//! advertise a DFP VDO.
+use bincode::de::Decoder;
+use bincode::enc::Encoder;
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{Decode, Encode};
+use embedded_usb_pd::PdError;
+
+/// Transmit identity register
+pub const REG_TX_IDENTITY: u8 = super::Register::TxIdentity.addr();
+/// Maximum number of identity VDOs, the ID header, cert stat, product, and up to three product type VDOs
+pub const MAX_IDENTITY_VDOS: usize = 6;
+/// Minimum number of identity VDOs in an ACK, the ID header, cert stat, and product VDOs
+pub const MIN_IDENTITY_VDOS: usize = 3;
+/// Transmit identity register length
+pub const REG_TX_IDENTITY_LEN: usize = 1 + 4 * MAX_IDENTITY_VDOS;
+
+/// Mask of the VDO count in the first byte
+const VDO_COUNT_MASK: u8 = 0x7;
+/// NAK Discover Identity instead of responding with the VDOs
+const NAK_MASK: u8 = 0x80;
+
+/// Response to Discover Identity
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TxIdentity {
+    nak: bool,
+    vdos: heapless::Vec<u32, MAX_IDENTITY_VDOS>,
+}
+
+impl TxIdentity {
+    /// Respond to Discover Identity with the given VDOs, in the order they're sent
+    ///
+    /// A DFP VDO goes in the product type VDOs, after any UFP VDOs. Returns `InvalidParams` if there are fewer than
+    /// [`MIN_IDENTITY_VDOS`] or more than [`MAX_IDENTITY_VDOS`] VDOs.
+    pub fn new(vdos: &[u32]) -> Result<Self, PdError> {
+        if vdos.len() < MIN_IDENTITY_VDOS {
+            return Err(PdError::InvalidParams);
+        }
+
+        Ok(Self {
+            nak: false,
+            vdos: heapless::Vec::from_slice(vdos).map_err(|_| PdError::InvalidParams)?,
+        })
+    }
+
+    /// NAK Discover Identity
+    pub fn nak() -> Self {
+        Self {
+            nak: true,
+            vdos: heapless::Vec::new(),
+        }
+    }
+
+    /// Returns true if Discover Identity is NAKed
+    pub fn is_nak(&self) -> bool {
+        self.nak
+    }
+
+    /// Returns the identity VDOs
+    pub fn vdos(&self) -> &[u32] {
+        &self.vdos
+    }
+}
+
+impl Encode for TxIdentity {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        let mut header = self.vdos.len() as u8;
+        if self.nak {
+            header |= NAK_MASK;
+        }
+
+        Encode::encode(&header, encoder)?;
+        for i in 0..MAX_IDENTITY_VDOS {
+            Encode::encode(self.vdos.get(i).unwrap_or(&0), encoder)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<Context> Decode<Context> for TxIdentity {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let header: u8 = Decode::decode(decoder)?;
+        let count = (header & VDO_COUNT_MASK) as usize;
+        if count > MAX_IDENTITY_VDOS {
+            return Err(DecodeError::Other("Invalid identity VDO count"));
+        }
+
+        let mut vdos = heapless::Vec::new();
+        for i in 0..MAX_IDENTITY_VDOS {
+            let vdo: u32 = Decode::decode(decoder)?;
+            if i < count {
+                // Can't fail, count is bounded by the capacity
+                let _ = vdos.push(vdo);
+            }
+        }
+
+        Ok(Self {
+            nak: header & NAK_MASK != 0,
+            vdos,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bincode::config;
+
+    use super::*;
+
+    #[test]
+    fn test_new_vdo_count() {
+        assert_eq!(TxIdentity::new(&[1, 2]), Err(PdError::InvalidParams));
+        assert_eq!(TxIdentity::new(&[1, 2, 3, 4, 5, 6, 7]), Err(PdError::InvalidParams));
+        assert_eq!(TxIdentity::new(&[1, 2, 3]).unwrap().vdos(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_encode_decode_identity() {
+        let identity = TxIdentity::new(&[0x2080_04B4, 0, 0x0001_0000, 0x2000_0000]).unwrap();
+        let mut buf = [0u8; REG_TX_IDENTITY_LEN];
+        bincode::encode_into_slice(&identity, &mut buf, config::standard().with_fixed_int_encoding()).unwrap();
+
+        let mut expected = [0u8; REG_TX_IDENTITY_LEN];
+        expected[0] = 4;
+        expected[1..5].copy_from_slice(&0x2080_04B4u32.to_le_bytes());
+        expected[9..13].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+        expected[13..17].copy_from_slice(&0x2000_0000u32.to_le_bytes());
+        assert_eq!(buf, expected);
+
+        let (decoded, _): (TxIdentity, _) =
+            bincode::decode_from_slice(&buf, config::standard().with_fixed_int_encoding()).unwrap();
+        assert_eq!(decoded, identity);
+    }
+
+    #[test]
+    fn test_encode_decode_nak() {
+        let mut buf = [0u8; REG_TX_IDENTITY_LEN];
+        bincode::encode_into_slice(
+            TxIdentity::nak(),
+            &mut buf,
+            config::standard().with_fixed_int_encoding(),
+        )
+        .unwrap();
+        assert_eq!(buf[0], NAK_MASK);
+
+        let (decoded, _): (TxIdentity, _) =
+            bincode::decode_from_slice(&buf, config::standard().with_fixed_int_encoding()).unwrap();
+        assert!(decoded.is_nak());
+        assert!(decoded.vdos().is_empty());
+    }
+
+    #[test]
+    fn test_decode_invalid_count() {
+        let mut buf = [0u8; REG_TX_IDENTITY_LEN];
+        buf[0] = 7;
+        assert!(
+            bincode::decode_from_slice::<TxIdentity, _>(&buf, config::standard().with_fixed_int_encoding()).is_err()
+        );
+    }
+}
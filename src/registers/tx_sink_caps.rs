@@ -0,0 +1,392 @@
+//! Transmit sink capabilities register
+//!
+//! Byte 0 holds the number of valid PDOs in bits 2:0, followed by the PDOs as little-endian u32 values, padded to
+//! [`MAX_SINK_PDOS`].
+use bincode::de::Decoder;
+use bincode::enc::Encoder;
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{Decode, Encode};
+use embedded_usb_pd::PdError;
+
+use super::rx_caps::SourceCapabilities;
+use crate::pdo::{SinkPdo, SourcePdo, VSAFE5V_MV};
+
+/// Transmit sink capabilities register
+pub const REG_TX_SINK_CAPS: u8 = super::Register::TxSinkCaps.addr();
+/// Maximum number of sink PDOs
+pub const MAX_SINK_PDOS: usize = 7;
+/// Transmit sink capabilities register length
+pub const REG_TX_SINK_CAPS_LEN: usize = 1 + 4 * MAX_SINK_PDOS;
+
+/// Mask of the PDO count in the first byte
+const PDO_COUNT_MASK: u8 = 0x7;
+/// vSafe5V current advertised when a sink policy doesn't have a 5V entry, the USB 3 default current
+pub const DEFAULT_VSAFE5V_CURRENT_MA: u16 = 900;
+
+/// Sink capabilities advertised to the port partner
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SinkCapabilities {
+    pdos: heapless::Vec<SinkPdo, MAX_SINK_PDOS>,
+}
+
+impl SinkCapabilities {
+    /// Create sink capabilities from the given PDOs
+    ///
+    /// Returns `InvalidParams` if there are no PDOs, too many PDOs, the first PDO isn't a fixed vSafe5V PDO, or any PDO
+    /// can't be encoded.
+    pub fn new(pdos: &[SinkPdo]) -> Result<Self, PdError> {
+        match pdos.first() {
+            Some(SinkPdo::Fixed { voltage_mv, .. }) if *voltage_mv == VSAFE5V_MV => (),
+            _ => return Err(PdError::InvalidParams),
+        }
+
+        for pdo in pdos {
+            pdo.encode()?;
+        }
+
+        Ok(Self {
+            pdos: heapless::Vec::from_slice(pdos).map_err(|_| PdError::InvalidParams)?,
+        })
+    }
+
+    /// Create sink capabilities from a sink policy of `(voltage_mv, max_current_ma)` entries
+    ///
+    /// Each entry becomes a fixed PDO, so the controller only requests source PDOs at a policy voltage and limits the
+    /// requested current to the entry's current. If no source PDO matches a policy voltage the controller falls back to
+    /// vSafe5V, which is advertised at [`DEFAULT_VSAFE5V_CURRENT_MA`] if the policy doesn't have a 5V entry.
+    ///
+    /// Returns `InvalidParams` if the policy has duplicate voltages, too many entries, or any entry can't be encoded.
+    pub fn from_policy(policy: &[(u16, u16)]) -> Result<Self, PdError> {
+        let mut entries: heapless::Vec<(u16, u16), MAX_SINK_PDOS> =
+            heapless::Vec::from_slice(policy).map_err(|_| PdError::InvalidParams)?;
+        if !entries.iter().any(|(voltage_mv, _)| *voltage_mv == VSAFE5V_MV) {
+            entries
+                .push((VSAFE5V_MV, DEFAULT_VSAFE5V_CURRENT_MA))
+                .map_err(|_| PdError::InvalidParams)?;
+        }
+
+        // Fixed PDOs must be in order of increasing voltage
+        entries.sort_unstable_by_key(|(voltage_mv, _)| *voltage_mv);
+        if entries.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return Err(PdError::InvalidParams);
+        }
+
+        let mut pdos: heapless::Vec<SinkPdo, MAX_SINK_PDOS> = heapless::Vec::new();
+        for (voltage_mv, max_current_ma) in entries {
+            // Can't fail, both vecs have the same capacity
+            let _ = pdos.push(SinkPdo::Fixed {
+                voltage_mv,
+                operational_current_ma: max_current_ma,
+                dual_role_power: false,
+                higher_capability: false,
+                unconstrained_power: false,
+                usb_comm: false,
+                dual_role_data: false,
+            });
+        }
+
+        Self::new(&pdos)
+    }
+
+    /// Returns the sink PDOs
+    pub fn pdos(&self) -> &[SinkPdo] {
+        &self.pdos
+    }
+
+    /// Returns these capabilities limited to what the given source can fully provide
+    ///
+    /// A fixed PDO is kept only if the source offers a fixed PDO at the same voltage with at least the operational
+    /// current. The vSafe5V PDO is always kept, limited to the source's vSafe5V current. Battery and variable PDOs
+    /// can't be checked against a fixed source and are dropped. The controller then requests the highest power PDO
+    /// left, so a request that caused a capability mismatch falls back to the best one the source can satisfy.
+    pub fn satisfiable_by(&self, source: &SourceCapabilities) -> Result<Self, PdError> {
+        let source_current_ma = |voltage_mv: u16| fixed_source_current_ma(source, voltage_mv);
+
+        let mut pdos: heapless::Vec<SinkPdo, MAX_SINK_PDOS> = heapless::Vec::new();
+        for pdo in &self.pdos {
+            let pdo = match *pdo {
+                SinkPdo::Fixed {
+                    voltage_mv,
+                    operational_current_ma,
+                    dual_role_power,
+                    higher_capability,
+                    unconstrained_power,
+                    usb_comm,
+                    dual_role_data,
+                } => match source_current_ma(voltage_mv) {
+                    Some(max_current_ma) if voltage_mv == VSAFE5V_MV => SinkPdo::Fixed {
+                        voltage_mv,
+                        operational_current_ma: operational_current_ma.min(max_current_ma),
+                        dual_role_power,
+                        higher_capability,
+                        unconstrained_power,
+                        usb_comm,
+                        dual_role_data,
+                    },
+                    Some(max_current_ma) if max_current_ma >= operational_current_ma => *pdo,
+                    // Sources must always offer vSafe5V, keep it as is if this one doesn't
+                    None if voltage_mv == VSAFE5V_MV => *pdo,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+
+            // Can't fail, both vecs have the same capacity
+            let _ = pdos.push(pdo);
+        }
+
+        Self::new(&pdos)
+    }
+
+    /// Returns these capabilities reduced to vSafe5V and the highest power PDO of the given source they allow
+    ///
+    /// Meant for capabilities created by [`Self::from_policy`], where the operational current is the most the sink may
+    /// draw rather than the current it needs. Each fixed PDO's current is first limited to the source's current at the
+    /// same voltage, then [`Self::satisfiable_by`] drops the voltages the source doesn't offer. The highest power PDO
+    /// left is selected, so the controller can only request it. If no policy voltage other than vSafe5V is offered, or
+    /// none gives more power than vSafe5V, only vSafe5V is kept and the controller falls back to it.
+    pub fn best_for(&self, source: &SourceCapabilities) -> Result<Self, PdError> {
+        let mut limited = self.clone();
+        for pdo in limited.pdos.iter_mut() {
+            if let SinkPdo::Fixed {
+                voltage_mv,
+                operational_current_ma,
+                ..
+            } = pdo
+            {
+                if let Some(max_current_ma) = fixed_source_current_ma(source, *voltage_mv) {
+                    *operational_current_ma = (*operational_current_ma).min(max_current_ma);
+                }
+            }
+        }
+
+        let limited = limited.satisfiable_by(source)?;
+        let power_mw = |pdo: &SinkPdo| match pdo {
+            SinkPdo::Fixed {
+                voltage_mv,
+                operational_current_ma,
+                ..
+            } => u32::from(*voltage_mv) * u32::from(*operational_current_ma) / 1000,
+            _ => 0,
+        };
+
+        let (vsafe5v, others) = limited.pdos.split_first().ok_or(PdError::InvalidParams)?;
+        match others.iter().max_by_key(|pdo| power_mw(pdo)) {
+            Some(best) if power_mw(best) > power_mw(vsafe5v) => Self::new(&[*vsafe5v, *best]),
+            _ => Self::new(&[*vsafe5v]),
+        }
+    }
+}
+
+/// Returns the current of the source's fixed PDO at the given voltage, if it offers one
+fn fixed_source_current_ma(source: &SourceCapabilities, voltage_mv: u16) -> Option<u16> {
+    source.pdos().iter().find_map(|pdo| match pdo {
+        SourcePdo::Fixed {
+            voltage_mv: source_mv,
+            max_current_ma,
+        } if *source_mv == voltage_mv => Some(*max_current_ma),
+        _ => None,
+    })
+}
+
+impl Encode for SinkCapabilities {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        Encode::encode(&(self.pdos.len() as u8), encoder)?;
+        for i in 0..MAX_SINK_PDOS {
+            let raw = match self.pdos.get(i) {
+                Some(pdo) => pdo.encode().map_err(|_| EncodeError::Other("Invalid sink PDO"))?,
+                None => 0,
+            };
+            Encode::encode(&raw, encoder)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<Context> Decode<Context> for SinkCapabilities {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let count: u8 = Decode::decode(decoder)?;
+        let count = (count & PDO_COUNT_MASK) as usize;
+
+        let mut pdos = heapless::Vec::new();
+        for i in 0..MAX_SINK_PDOS {
+            let raw: u32 = Decode::decode(decoder)?;
+            if i < count {
+                let pdo = SinkPdo::try_from(raw).map_err(|_| DecodeError::Other("Invalid sink PDO"))?;
+                // Can't fail, count is bounded by the capacity
+                let _ = pdos.push(pdo);
+            }
+        }
+
+        Ok(Self { pdos })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bincode::config;
+
+    use super::*;
+    use crate::registers::rx_caps::REG_RX_SOURCE_CAPS_LEN;
+
+    fn fixed(voltage_mv: u16, operational_current_ma: u16) -> SinkPdo {
+        SinkPdo::Fixed {
+            voltage_mv,
+            operational_current_ma,
+            dual_role_power: false,
+            higher_capability: false,
+            unconstrained_power: false,
+            usb_comm: false,
+            dual_role_data: false,
+        }
+    }
+
+    #[test]
+    fn test_encode_decode_sink_caps() {
+        let pdos = [
+            fixed(5000, 3000),
+            fixed(9000, 2000),
+            SinkPdo::Variable {
+                max_voltage_mv: 20000,
+                min_voltage_mv: 5000,
+                operational_current_ma: 1500,
+            },
+        ];
+        let caps = SinkCapabilities::new(&pdos).unwrap();
+
+        let mut buf = [0u8; REG_TX_SINK_CAPS_LEN];
+        bincode::encode_into_slice(&caps, &mut buf, config::standard().with_fixed_int_encoding()).unwrap();
+
+        let mut expected = [0u8; REG_TX_SINK_CAPS_LEN];
+        expected[0] = 3;
+        expected[1..5].copy_from_slice(&0x0001_912Cu32.to_le_bytes());
+        expected[5..9].copy_from_slice(&0x0002_D0C8u32.to_le_bytes());
+        expected[9..13].copy_from_slice(&0x9901_9096u32.to_le_bytes());
+        assert_eq!(buf, expected);
+
+        let (decoded, _): (SinkCapabilities, _) =
+            bincode::decode_from_slice(&buf, config::standard().with_fixed_int_encoding()).unwrap();
+        assert_eq!(decoded, caps);
+    }
+
+    #[test]
+    fn test_sink_caps_validation() {
+        // Empty
+        assert_eq!(SinkCapabilities::new(&[]), Err(PdError::InvalidParams));
+        // First PDO isn't vSafe5V
+        assert_eq!(SinkCapabilities::new(&[fixed(9000, 2000)]), Err(PdError::InvalidParams));
+        // Too many PDOs
+        assert_eq!(
+            SinkCapabilities::new(&[fixed(5000, 3000); 8]),
+            Err(PdError::InvalidParams)
+        );
+        // Current can't be encoded
+        assert_eq!(
+            SinkCapabilities::new(&[fixed(5000, 3000), fixed(9000, 20000)]),
+            Err(PdError::InvalidParams)
+        );
+    }
+
+    #[test]
+    fn test_sink_caps_satisfiable_by() {
+        let source = |pdos: &[u32]| {
+            let mut buf = [0u8; REG_RX_SOURCE_CAPS_LEN];
+            buf[0] = pdos.len() as u8;
+            for (i, pdo) in pdos.iter().enumerate() {
+                buf[1 + 4 * i..5 + 4 * i].copy_from_slice(&pdo.to_le_bytes());
+            }
+
+            let (caps, _): (SourceCapabilities, _) =
+                bincode::decode_from_slice(&buf, config::standard().with_fixed_int_encoding()).unwrap();
+            caps
+        };
+
+        let caps = SinkCapabilities::new(&[
+            fixed(5000, 3000),
+            fixed(9000, 3000),
+            fixed(15000, 3000),
+            fixed(20000, 5000),
+            SinkPdo::Variable {
+                max_voltage_mv: 20000,
+                min_voltage_mv: 5000,
+                operational_current_ma: 1500,
+            },
+        ])
+        .unwrap();
+
+        // 5V/2A, 9V/3A, 20V/3A
+        let source = source(&[0x0001_90C8, 0x0002_D12C, 0x0006_412C]);
+        assert_eq!(
+            caps.satisfiable_by(&source).unwrap().pdos(),
+            &[fixed(5000, 2000), fixed(9000, 3000)]
+        );
+    }
+
+    #[test]
+    fn test_sink_caps_best_for() {
+        let source = |pdos: &[u32]| {
+            let mut buf = [0u8; REG_RX_SOURCE_CAPS_LEN];
+            buf[0] = pdos.len() as u8;
+            for (i, pdo) in pdos.iter().enumerate() {
+                buf[1 + 4 * i..5 + 4 * i].copy_from_slice(&pdo.to_le_bytes());
+            }
+
+            let (caps, _): (SourceCapabilities, _) =
+                bincode::decode_from_slice(&buf, config::standard().with_fixed_int_encoding()).unwrap();
+            caps
+        };
+        let policy = SinkCapabilities::from_policy(&[(5000, 3000), (15000, 3000), (20000, 5000)]).unwrap();
+
+        // 5V/3A, 9V/3A, 15V/3A, 20V/3A, 20V is limited to the source's current but still gives the most power
+        let source_20v = source(&[0x0001_912C, 0x0002_D12C, 0x0004_B12C, 0x0006_412C]);
+        assert_eq!(
+            policy.best_for(&source_20v).unwrap().pdos(),
+            &[fixed(5000, 3000), fixed(20000, 3000)]
+        );
+
+        // 5V/3A, 15V/3A
+        let source_15v = source(&[0x0001_912C, 0x0004_B12C]);
+        assert_eq!(
+            policy.best_for(&source_15v).unwrap().pdos(),
+            &[fixed(5000, 3000), fixed(15000, 3000)]
+        );
+
+        // No policy voltage other than vSafe5V is offered, 5V/2A, 9V/3A
+        let source_9v = source(&[0x0001_90C8, 0x0002_D12C]);
+        assert_eq!(policy.best_for(&source_9v).unwrap().pdos(), &[fixed(5000, 2000)]);
+    }
+
+    #[test]
+    fn test_sink_caps_from_policy() {
+        // Sorted by voltage
+        assert_eq!(
+            SinkCapabilities::from_policy(&[(20000, 5000), (5000, 3000)])
+                .unwrap()
+                .pdos(),
+            &[fixed(5000, 3000), fixed(20000, 5000)]
+        );
+
+        // vSafe5V fallback
+        assert_eq!(
+            SinkCapabilities::from_policy(&[(9000, 2000)]).unwrap().pdos(),
+            &[fixed(5000, DEFAULT_VSAFE5V_CURRENT_MA), fixed(9000, 2000)]
+        );
+        assert_eq!(
+            SinkCapabilities::from_policy(&[]).unwrap().pdos(),
+            &[fixed(5000, DEFAULT_VSAFE5V_CURRENT_MA)]
+        );
+
+        // Duplicate voltage
+        assert_eq!(
+            SinkCapabilities::from_policy(&[(9000, 2000), (9000, 3000)]),
+            Err(PdError::InvalidParams)
+        );
+        // No room for the vSafe5V fallback
+        assert_eq!(
+            SinkCapabilities::from_policy(&[(9000, 2000); MAX_SINK_PDOS]),
+            Err(PdError::InvalidParams)
+        );
+    }
+}
@@ -0,0 +1,30 @@
+//! Decoded USB connection status
+use super::field_sets::{DataStatus, Status};
+use super::UsbHostMode;
+
+/// USB connection status of a port
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UsbStatus {
+    /// A USB host is present on the port
+    pub host_present: bool,
+    /// A USB device is present on the port, we are the USB host
+    pub device_present: bool,
+    /// USB2 data path is enabled
+    pub usb2_enabled: bool,
+    /// USB3 data path is enabled
+    pub usb3_enabled: bool,
+}
+
+impl UsbStatus {
+    /// Decode USB status from the port status and data status registers
+    pub fn new(status: &Status, data_status: &DataStatus) -> Self {
+        Self {
+            host_present: status.usb_host() == UsbHostMode::HostPresent,
+            // Data role is 0 when we're the DFP
+            device_present: data_status.data_connection() && !data_status.usb_data_role(),
+            usb2_enabled: data_status.usb_2_connection(),
+            usb3_enabled: data_status.usb_3_connection(),
+        }
+    }
+}